@@ -0,0 +1,188 @@
+use std::{
+    future::{self, Future},
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use tokio::runtime::{Builder, Runtime};
+
+use crate::{check, concurrent::TimedOutcome, TestOutcome, Threading};
+
+pub(crate) type TestFuture = Pin<Box<dyn Future<Output = TestOutcome>>>;
+
+// What async tests get to see of the child. Unlike `ChildInfo` it is owned,
+// so it can be moved into the future.
+#[derive(Debug, Clone)]
+pub struct Probe {
+    pub(crate) pid: u32,
+    pub(crate) port: Option<u16>,
+    pub(crate) uptime: Duration,
+}
+
+impl Probe {
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    // The port given to the child by `with_dynamic_port`
+    pub fn port(&self) -> Option<u16> {
+        self.port
+    }
+
+    // How long the child had been running when the check started
+    pub fn uptime(&self) -> Duration {
+        self.uptime
+    }
+}
+
+pub(crate) struct AsyncTests<S: Threading> {
+    tests: Vec<(String, Box<S::AsyncTest>)>,
+    // Kept between checks, like the one of `GrpcCheck`
+    runtime: Option<Runtime>,
+}
+
+impl<S: Threading> Default for AsyncTests<S> {
+    fn default() -> Self {
+        Self {
+            tests: vec![],
+            runtime: None,
+        }
+    }
+}
+
+impl<S: Threading> AsyncTests<S> {
+    pub(crate) fn push(&mut self, name: String, test: Box<S::AsyncTest>) {
+        self.tests.push((name, test));
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.tests.is_empty()
+    }
+
+    pub(crate) fn names(&self) -> impl Iterator<Item = &str> {
+        self.tests.iter().map(|(name, _)| name.as_str())
+    }
+
+    // Runs every test concurrently on a single thread until the deadline.
+    // Tests that didn't finish in time count as failed, and panics are caught
+    // like for the other tests.
+    pub(crate) fn run(&mut self, probe: &Probe, deadline: Instant) -> Vec<TimedOutcome> {
+        if self.runtime.is_none() {
+            self.runtime = Builder::new_current_thread().enable_time().build().ok();
+        }
+        let Some(runtime) = &self.runtime else {
+            return vec![(TestOutcome::Unhealthy, None, None); self.tests.len()];
+        };
+
+        let tests = &mut self.tests;
+        runtime.block_on(async {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            let mut running: Vec<_> = tests
+                .iter_mut()
+                .map(|(_, test)| {
+                    let started = Instant::now();
+                    let test = panic::catch_unwind(AssertUnwindSafe(|| test(probe.clone())));
+                    Some((
+                        started,
+                        test.map(|test| Box::pin(tokio::time::timeout(timeout, test))),
+                    ))
+                })
+                .collect();
+            let mut results = vec![(TestOutcome::Unhealthy, None, None); running.len()];
+
+            future::poll_fn(|cx| {
+                for (slot, result) in running.iter_mut().zip(&mut results) {
+                    let Some((started, test)) = slot else {
+                        continue;
+                    };
+                    match poll(test, cx) {
+                        Poll::Pending => continue,
+                        Poll::Ready(Ok(Ok(outcome))) => {
+                            *result = (outcome, Some(started.elapsed()), None)
+                        }
+                        Poll::Ready(Ok(Err(_))) => {}
+                        Poll::Ready(Err(message)) => {
+                            *result = (
+                                TestOutcome::Unhealthy,
+                                Some(started.elapsed()),
+                                Some(message),
+                            )
+                        }
+                    }
+                    *slot = None;
+                }
+                match running.iter().all(Option::is_none) {
+                    true => Poll::Ready(()),
+                    false => Poll::Pending,
+                }
+            })
+            .await;
+            results
+        })
+    }
+}
+
+// Polls a test, turning a panic while creating or polling it into its message
+fn poll<F: Future + Unpin>(
+    test: &mut Result<F, Box<dyn std::any::Any + Send>>,
+    cx: &mut Context<'_>,
+) -> Poll<Result<F::Output, String>> {
+    match test {
+        Ok(test) => match panic::catch_unwind(AssertUnwindSafe(|| Pin::new(test).poll(cx))) {
+            Ok(poll) => poll.map(Ok),
+            Err(payload) => Poll::Ready(Err(check::panic_message(&*payload))),
+        },
+        Err(payload) => Poll::Ready(Err(check::panic_message(&**payload))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Local;
+
+    fn probe() -> Probe {
+        Probe {
+            pid: 42,
+            port: None,
+            uptime: Duration::ZERO,
+        }
+    }
+
+    fn outcomes(results: Vec<TimedOutcome>) -> Vec<TestOutcome> {
+        results.into_iter().map(|(outcome, ..)| outcome).collect()
+    }
+
+    #[test]
+    fn it_runs_tests_concurrently_until_the_deadline() {
+        let mut tests = AsyncTests::<Local>::default();
+        for delay in [50, 50, 500] {
+            tests.push(
+                format!("sleep {}", delay),
+                Box::new(move |probe| {
+                    Box::pin(async move {
+                        tokio::time::sleep(Duration::from_millis(delay)).await;
+                        TestOutcome::from(probe.pid() == 42)
+                    })
+                }),
+            );
+        }
+        tests.push("panicking".into(), Box::new(|_| panic!("no probe")));
+
+        let started = Instant::now();
+        let results = tests.run(&probe(), started + Duration::from_millis(200));
+        assert!(started.elapsed() < Duration::from_millis(400));
+        assert_eq!(results[3].2.as_deref(), Some("no probe"));
+        assert_eq!(
+            outcomes(results),
+            vec![
+                TestOutcome::Healthy,
+                TestOutcome::Healthy,
+                TestOutcome::Unhealthy,
+                TestOutcome::Unhealthy
+            ]
+        );
+    }
+}
@@ -0,0 +1,366 @@
+use std::{
+    collections::HashMap,
+    env,
+    ffi::{OsStr, OsString},
+    fs, io,
+    path::{Path, PathBuf},
+    process::{Child, Command, ExitStatus},
+    sync::{Arc, Mutex},
+};
+
+pub(crate) type SharedBackend = Arc<Mutex<dyn ProcessBackend>>;
+
+// How children are started and controlled, with processes known by their
+// pid. Supervisors use `StdBackend` unless given another one, e.g. a
+// `MockBackend` to test a setup without running real programs.
+pub trait ProcessBackend: Send {
+    fn spawn(&mut self, command: &mut Command) -> io::Result<u32>;
+    fn try_wait(&mut self, pid: u32) -> io::Result<Option<ExitStatus>>;
+    fn kill(&mut self, pid: u32) -> io::Result<()>;
+    fn signal(&mut self, pid: u32, signal: i32) -> io::Result<()>;
+
+    // Hands the `Child` of a spawned process over to the supervisor, which
+    // then controls it directly and can use its stdio. Backends without real
+    // children keep the default.
+    fn take_child(&mut self, pid: u32) -> Option<Child> {
+        let _ = pid;
+        None
+    }
+
+    // Checks that the program of `command` can be started at all, before
+    // supervising it. Backends without real programs keep the default.
+    fn preflight(&self, command: &Command) -> io::Result<()> {
+        let _ = command;
+        Ok(())
+    }
+}
+
+// Runs children with `std::process`
+#[derive(Default)]
+pub struct StdBackend {
+    children: HashMap<u32, Child>,
+}
+
+impl ProcessBackend for StdBackend {
+    fn spawn(&mut self, command: &mut Command) -> io::Result<u32> {
+        let child = command.spawn()?;
+        let pid = child.id();
+        self.children.insert(pid, child);
+        Ok(pid)
+    }
+
+    // Reaped children are forgotten, their status is only reported once
+    fn try_wait(&mut self, pid: u32) -> io::Result<Option<ExitStatus>> {
+        let status = self.child(pid)?.try_wait()?;
+        if status.is_some() {
+            self.children.remove(&pid);
+        }
+        Ok(status)
+    }
+
+    fn kill(&mut self, pid: u32) -> io::Result<()> {
+        let child = self.child(pid)?;
+        child.kill()?;
+        child.wait().map(drop)
+    }
+
+    #[cfg(unix)]
+    fn signal(&mut self, pid: u32, signal: i32) -> io::Result<()> {
+        self.child(pid)?;
+        match unsafe { libc::kill(pid as libc::pid_t, signal) } {
+            0 => Ok(()),
+            _ => Err(io::Error::last_os_error()),
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn signal(&mut self, _pid: u32, _signal: i32) -> io::Result<()> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    fn take_child(&mut self, pid: u32) -> Option<Child> {
+        self.children.remove(&pid)
+    }
+
+    fn preflight(&self, command: &Command) -> io::Result<()> {
+        let program = resolve(command)?;
+        if is_executable(&program) {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("{} is not executable", program.display()),
+            ))
+        }
+    }
+}
+
+// Finds the program like spawning would: as given when it's a path, else in
+// the PATH of the child
+pub(crate) fn resolve(command: &Command) -> io::Result<PathBuf> {
+    let program = Path::new(command.get_program());
+    let not_found = |place: &str| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} not found {}", program.display(), place),
+        )
+    };
+    if program.components().count() > 1 {
+        // Relative paths may be meant from the working directory of either
+        // the supervisor or the child
+        let candidates = [
+            Some(program.to_path_buf()),
+            command.get_current_dir().map(|dir| dir.join(program)),
+        ];
+        return candidates
+            .into_iter()
+            .flatten()
+            .find(|candidate| candidate.is_file())
+            .ok_or_else(|| not_found("on disk"));
+    }
+
+    let path = command
+        .get_envs()
+        .find(|(key, _)| *key == OsStr::new("PATH"))
+        .and_then(|(_, value)| value.map(OsStr::to_os_string))
+        .or_else(|| env::var_os("PATH"))
+        .unwrap_or_default();
+    env::split_paths(&path)
+        .flat_map(|dir| candidates(dir.join(program)))
+        .find(|candidate| candidate.is_file())
+        .ok_or_else(|| not_found("in PATH"))
+}
+
+#[cfg(unix)]
+fn candidates(path: PathBuf) -> Vec<PathBuf> {
+    vec![path]
+}
+
+#[cfg(windows)]
+fn candidates(path: PathBuf) -> Vec<PathBuf> {
+    match path.extension() {
+        Some(_) => vec![path],
+        None => vec![path.with_extension("exe"), path],
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::metadata(path).is_ok_and(|metadata| metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(windows)]
+fn is_executable(path: &Path) -> bool {
+    fs::metadata(path).is_ok_and(|metadata| metadata.is_file())
+}
+
+impl StdBackend {
+    fn child(&mut self, pid: u32) -> io::Result<&mut Child> {
+        self.children
+            .get_mut(&pid)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such child"))
+    }
+}
+
+#[cfg(unix)]
+fn exit_status(code: i32) -> ExitStatus {
+    std::os::unix::process::ExitStatusExt::from_raw(code << 8)
+}
+
+#[cfg(windows)]
+fn exit_status(code: i32) -> ExitStatus {
+    std::os::windows::process::ExitStatusExt::from_raw(code as u32)
+}
+
+struct MockProcess {
+    pid: u32,
+    command: Vec<OsString>,
+    exit_code: Option<i32>,
+    signals: Vec<i32>,
+}
+
+#[derive(Default)]
+struct MockState {
+    processes: Vec<MockProcess>,
+    exit_on_spawn: Option<i32>,
+    spawn_error: Option<io::ErrorKind>,
+}
+
+// A backend pretending to run processes, which only exit when told to.
+// Clones share their processes, so a test can keep one to drive and inspect
+// what the supervisor did with the other.
+#[derive(Clone, Default)]
+pub struct MockBackend {
+    state: Arc<Mutex<MockState>>,
+}
+
+const MOCK_KILLED: i32 = 137;
+
+impl MockBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Makes every process spawned from now on exit right away with `code`
+    pub fn exit_on_spawn(&self, code: Option<i32>) {
+        self.state.lock().unwrap().exit_on_spawn = code;
+    }
+
+    // Makes spawning fail with `kind` from now on
+    pub fn fail_spawns(&self, kind: Option<io::ErrorKind>) {
+        self.state.lock().unwrap().spawn_error = kind;
+    }
+
+    pub fn exit(&self, pid: u32, code: i32) {
+        self.with_process(pid, |process| {
+            process.exit_code.get_or_insert(code);
+        });
+    }
+
+    // The pids of every process spawned so far, oldest first
+    pub fn pids(&self) -> Vec<u32> {
+        let state = self.state.lock().unwrap();
+        state.processes.iter().map(|process| process.pid).collect()
+    }
+
+    pub fn is_running(&self, pid: u32) -> bool {
+        self.with_process(pid, |process| process.exit_code.is_none())
+            .unwrap_or(false)
+    }
+
+    // The program and arguments the process was spawned with
+    pub fn command(&self, pid: u32) -> Option<Vec<OsString>> {
+        self.with_process(pid, |process| process.command.clone())
+    }
+
+    pub fn signals(&self, pid: u32) -> Vec<i32> {
+        self.with_process(pid, |process| process.signals.clone())
+            .unwrap_or_default()
+    }
+
+    fn with_process<T>(&self, pid: u32, f: impl FnOnce(&mut MockProcess) -> T) -> Option<T> {
+        let mut state = self.state.lock().unwrap();
+        state
+            .processes
+            .iter_mut()
+            .find(|process| process.pid == pid)
+            .map(f)
+    }
+
+    fn process(&self, pid: u32) -> io::Result<()> {
+        self.with_process(pid, |_| ())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such process"))
+    }
+}
+
+impl ProcessBackend for MockBackend {
+    fn spawn(&mut self, command: &mut Command) -> io::Result<u32> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(kind) = state.spawn_error {
+            return Err(kind.into());
+        }
+        let pid = state.processes.len() as u32 + 1;
+        let exit_code = state.exit_on_spawn;
+        state.processes.push(MockProcess {
+            pid,
+            command: std::iter::once(command.get_program())
+                .chain(command.get_args())
+                .map(Into::into)
+                .collect(),
+            exit_code,
+            signals: vec![],
+        });
+        Ok(pid)
+    }
+
+    fn try_wait(&mut self, pid: u32) -> io::Result<Option<ExitStatus>> {
+        self.process(pid)?;
+        Ok(self
+            .with_process(pid, |process| process.exit_code.map(exit_status))
+            .flatten())
+    }
+
+    fn kill(&mut self, pid: u32) -> io::Result<()> {
+        self.process(pid)?;
+        self.exit(pid, MOCK_KILLED);
+        Ok(())
+    }
+
+    fn signal(&mut self, pid: u32, signal: i32) -> io::Result<()> {
+        self.process(pid)?;
+        self.with_process(pid, |process| process.signals.push(signal));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mock_processes_run_until_told_otherwise() {
+        let mock = MockBackend::new();
+        let mut backend = mock.clone();
+        let pid = backend
+            .spawn(Command::new("server").arg("--port=80"))
+            .unwrap();
+
+        assert_eq!(mock.pids(), vec![pid]);
+        assert_eq!(
+            mock.command(pid),
+            Some(vec!["server".into(), "--port=80".into()])
+        );
+        assert!(mock.is_running(pid));
+        assert_eq!(backend.try_wait(pid).unwrap(), None);
+
+        backend.signal(pid, 15).unwrap();
+        assert_eq!(mock.signals(pid), vec![15]);
+        mock.exit(pid, 3);
+        assert!(!mock.is_running(pid));
+        assert_eq!(backend.try_wait(pid).unwrap().unwrap().code(), Some(3));
+        assert!(backend.kill(pid + 1).is_err());
+
+        mock.fail_spawns(Some(io::ErrorKind::NotFound));
+        assert!(backend.spawn(&mut Command::new("server")).is_err());
+    }
+
+    #[test]
+    fn std_backend_checks_programs() {
+        let backend = StdBackend::default();
+        assert!(backend.preflight(&Command::new("sleep")).is_ok());
+
+        let error = backend
+            .preflight(&Command::new("no-such-program"))
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+        assert_eq!(error.to_string(), "no-such-program not found in PATH");
+
+        let mut command = Command::new("sleep");
+        command.env("PATH", "/nonexistent");
+        assert!(backend.preflight(&command).is_err());
+
+        let path = env::temp_dir().join(format!("not-executable-{}", std::process::id()));
+        fs::write(&path, "").unwrap();
+        let error = backend.preflight(&Command::new(&path)).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+    }
+
+    #[test]
+    fn std_backend_hands_over_children() {
+        let mut backend = StdBackend::default();
+        let pid = backend.spawn(Command::new("sleep").arg("5")).unwrap();
+        assert_eq!(backend.try_wait(pid).unwrap(), None);
+        backend.kill(pid).unwrap();
+        assert!(backend.try_wait(pid).unwrap().is_some());
+        assert!(backend.try_wait(pid).is_err());
+
+        let pid = backend.spawn(Command::new("sleep").arg("5")).unwrap();
+        let mut child = backend.take_child(pid).unwrap();
+        assert!(backend.try_wait(pid).is_err());
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+}
@@ -0,0 +1,42 @@
+use std::process::ExitStatus;
+
+// Whether to wait for the backoff time when the child had already exited by
+// the time its tests failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BackoffPolicy {
+    #[default]
+    Always,
+    // Restart right away when the child exited successfully
+    SkipOnCleanExit,
+    // Restart right away when the child exited, whatever its status
+    SkipOnExit,
+}
+
+impl BackoffPolicy {
+    pub(crate) fn skips(self, exit_status: Option<ExitStatus>) -> bool {
+        match (self, exit_status) {
+            (Self::Always, _) | (_, None) => false,
+            (Self::SkipOnCleanExit, Some(status)) => status.success(),
+            (Self::SkipOnExit, Some(_)) => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use super::*;
+
+    #[test]
+    fn it_skips_backoff_depending_on_exit_status() {
+        let success = Command::new("true").status().unwrap();
+        let failure = Command::new("false").status().unwrap();
+
+        assert!(!BackoffPolicy::Always.skips(Some(success)));
+        assert!(!BackoffPolicy::SkipOnExit.skips(None));
+        assert!(BackoffPolicy::SkipOnCleanExit.skips(Some(success)));
+        assert!(!BackoffPolicy::SkipOnCleanExit.skips(Some(failure)));
+        assert!(BackoffPolicy::SkipOnExit.skips(Some(failure)));
+    }
+}
@@ -0,0 +1,315 @@
+use std::{env, fs, net::SocketAddr, process, time::Duration};
+
+use supervised_process::{CommandCheck, HealthCheck, HttpCheck, RunOutcome, SupervisedProcess};
+
+const USAGE: &str = "usage: supervise [options] -- <command> [args...]
+
+options:
+  --config <file>          read options from a file of `key = value` lines
+  --name <name>            name used in messages
+  --interval <duration>    time between checks (default 30s)
+  --initial-delay <dur>    time before the first check
+  --backoff <duration>     time to wait before restarting (default 30s)
+  --restarts <n>           give up after n restarts (default unlimited)
+  --working-dir <dir>      directory the command runs in
+  --http-check <url>       pass while the URL answers with a 2xx status
+  --command-check <cmd>    pass while the shell command exits with 0
+  --control-socket <path>  serve JSON control commands on a Unix socket
+  --status-server <addr>   serve /status and /healthz over HTTP
+
+durations are like 30s, 250ms or 1h 30m, and in seconds without a unit. On
+SIGHUP the options are read again, and applied to the running supervisor.";
+
+#[derive(Debug, Default, PartialEq)]
+struct Options {
+    name: Option<String>,
+    interval: Option<Duration>,
+    initial_delay: Option<Duration>,
+    backoff: Option<Duration>,
+    restarts: Option<u64>,
+    working_dir: Option<String>,
+    http_checks: Vec<String>,
+    command_checks: Vec<String>,
+    control_socket: Option<String>,
+    status_server: Option<SocketAddr>,
+    command: Vec<String>,
+}
+
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    supervised_process::parse_duration(value).map_err(|e| e.to_string())
+}
+
+impl Options {
+    // Options given more than once override earlier ones, except for checks
+    // which add up
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "name" => self.name = Some(value.into()),
+            "interval" => self.interval = Some(parse_duration(value)?),
+            "initial-delay" => self.initial_delay = Some(parse_duration(value)?),
+            "backoff" => self.backoff = Some(parse_duration(value)?),
+            "restarts" => {
+                self.restarts = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("invalid number of restarts '{}'", value))?,
+                )
+            }
+            "working-dir" => self.working_dir = Some(value.into()),
+            "http-check" => self.http_checks.push(value.into()),
+            "command-check" => self.command_checks.push(value.into()),
+            #[cfg(all(feature = "control", unix))]
+            "control-socket" => self.control_socket = Some(value.into()),
+            #[cfg(not(all(feature = "control", unix)))]
+            "control-socket" => return Err("built without control socket support".into()),
+            #[cfg(feature = "status-server")]
+            "status-server" => {
+                self.status_server = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("invalid address '{}'", value))?,
+                )
+            }
+            #[cfg(not(feature = "status-server"))]
+            "status-server" => return Err("built without status server support".into()),
+            // Only in config files, arguments are split on whitespace
+            "command" => self.command = value.split_whitespace().map(Into::into).collect(),
+            "config" => self.read_config(value)?,
+            _ => return Err(format!("unknown option '{}'", key)),
+        }
+        Ok(())
+    }
+
+    fn read_config(&mut self, path: &str) -> Result<(), String> {
+        let contents = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        for line in contents.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("{}: invalid line '{}'", path, line))?;
+            self.set(key.trim(), value.trim())?;
+        }
+        Ok(())
+    }
+
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                options.command = args.collect();
+                break;
+            }
+            let key = arg
+                .strip_prefix("--")
+                .ok_or_else(|| format!("unexpected argument '{}'", arg))?;
+            let value = args
+                .next()
+                .ok_or_else(|| format!("missing value for '{}'", arg))?;
+            options.set(key, &value)?;
+        }
+        if options.command.is_empty() {
+            return Err("no command to supervise".into());
+        }
+        Ok(options)
+    }
+
+    fn checks(&self) -> Vec<Box<dyn HealthCheck + Send>> {
+        let mut checks: Vec<Box<dyn HealthCheck + Send>> = vec![];
+        for url in &self.http_checks {
+            checks.push(Box::new(HttpCheck::new(url)));
+        }
+        for command in &self.command_checks {
+            #[cfg(unix)]
+            let check = CommandCheck::new("sh", ["-c", command]);
+            #[cfg(windows)]
+            let check = CommandCheck::new("cmd", ["/C", command]);
+            checks.push(Box::new(check));
+        }
+        checks
+    }
+
+    // The program itself can't change, only its arguments
+    #[cfg(unix)]
+    fn reload_config(&self) -> supervised_process::ReloadConfig {
+        let mut config = supervised_process::ReloadConfig::new()
+            .with_args(&self.command[1..])
+            .with_checks(self.checks());
+        if let Some(interval) = self.interval {
+            config = config.with_check_interval(interval);
+        }
+        if let Some(backoff) = self.backoff {
+            config = config.with_backoff_time(backoff);
+        }
+        config
+    }
+
+    fn supervisor(self) -> SupervisedProcess<'static> {
+        let mut process = SupervisedProcess::new(&self.command[0]).with_args(&self.command[1..]);
+        if let Some(name) = &self.name {
+            process = process.with_name(name);
+        }
+        if let Some(interval) = self.interval {
+            process = process.with_check_interval(interval);
+        }
+        if let Some(initial_delay) = self.initial_delay {
+            process = process.with_initial_delay(initial_delay);
+        }
+        if let Some(backoff) = self.backoff {
+            process = process.with_backoff_time(backoff);
+        }
+        if let Some(restarts) = self.restarts {
+            process = process.with_restart_times(restarts);
+        }
+        if let Some(working_dir) = &self.working_dir {
+            process = process.with_working_dir(working_dir);
+        }
+        for check in self.checks() {
+            process = process.add_check(check);
+        }
+        #[cfg(all(feature = "control", unix))]
+        if let Some(path) = self.control_socket {
+            process = process.with_control_socket(path);
+        }
+        #[cfg(feature = "status-server")]
+        if let Some(addr) = self.status_server {
+            process = process.with_status_server(addr);
+        }
+        process
+    }
+}
+
+#[cfg(unix)]
+fn reload_on_hangup(handle: supervised_process::SupervisorHandle) {
+    use std::{
+        sync::atomic::{AtomicBool, Ordering},
+        thread,
+    };
+
+    static HANGUP: AtomicBool = AtomicBool::new(false);
+
+    extern "C" fn on_hangup(_: libc::c_int) {
+        HANGUP.store(true, Ordering::SeqCst);
+    }
+
+    unsafe {
+        libc::signal(libc::SIGHUP, on_hangup as *const () as libc::sighandler_t);
+    }
+    // Only an atomic store is safe in the handler, the reload happens here
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_millis(200));
+        if !HANGUP.swap(false, Ordering::SeqCst) {
+            continue;
+        }
+        let reloaded = Options::parse(env::args().skip(1)).and_then(|options| {
+            handle
+                .reload(options.reload_config())
+                .map_err(|e| e.to_string())
+        });
+        if let Err(e) = reloaded {
+            eprintln!("supervise: not reloading: {}", e);
+        }
+    });
+}
+
+fn main() {
+    let options = match Options::parse(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("supervise: {}\n\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+    let mut process = match options.supervisor().build() {
+        Ok(process) => process,
+        Err(e) => {
+            eprintln!("supervise: {}", e);
+            process::exit(2);
+        }
+    };
+    #[cfg(unix)]
+    reload_on_hangup(process.handle());
+    match process.run() {
+        // Giving up on the child is a failure too
+        Ok(RunOutcome::RestartsExhausted | RunOutcome::FatalTest | RunOutcome::ChildFailed) => {
+            process::exit(1)
+        }
+        Ok(_) => {}
+        Err(e) => {
+            eprintln!("supervise: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn it_parses_durations() {
+        assert_eq!(parse_duration("10s"), Ok(Duration::from_secs(10)));
+        assert_eq!(parse_duration("250ms"), Ok(Duration::from_millis(250)));
+        assert_eq!(parse_duration("1.5m"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("2"), Ok(Duration::from_secs(2)));
+        assert_eq!(parse_duration("1h 30m"), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("s"), Err("invalid duration 's'".to_string()));
+    }
+
+    #[test]
+    fn it_parses_flags_and_the_command() {
+        let options = Options::parse(args(&[
+            "--interval",
+            "10s",
+            "--restarts",
+            "5",
+            "--http-check",
+            "http://localhost:8080/health",
+            "--",
+            "./my-daemon",
+            "--flag",
+        ]))
+        .unwrap();
+        assert_eq!(
+            options,
+            Options {
+                interval: Some(Duration::from_secs(10)),
+                restarts: Some(5),
+                http_checks: vec!["http://localhost:8080/health".into()],
+                command: args(&["./my-daemon", "--flag"]),
+                ..Options::default()
+            }
+        );
+
+        assert!(Options::parse(args(&["--interval", "10s"])).is_err());
+        assert!(Options::parse(args(&["--bogus", "1", "--", "true"])).is_err());
+    }
+
+    #[test]
+    fn flags_override_the_config_file() {
+        let path = env::temp_dir().join(format!("supervise-{}.conf", process::id()));
+        fs::write(
+            &path,
+            "# web server\nname = web\ninterval = 1m\ncommand = ./my-daemon --flag\n",
+        )
+        .unwrap();
+        let options = Options::parse(args(&[
+            "--config",
+            path.to_str().unwrap(),
+            "--interval",
+            "5s",
+        ]))
+        .unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(options.name.as_deref(), Some("web"));
+        assert_eq!(options.interval, Some(Duration::from_secs(5)));
+        assert_eq!(options.command, args(&["./my-daemon", "--flag"]));
+    }
+}
@@ -0,0 +1,88 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+// Stops restarting for a cool-down period once the child failed too many
+// times within a window, then resumes with a clean slate
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    max_failures: u32,
+    window: Duration,
+    cool_down: Duration,
+    failures: VecDeque<Instant>,
+}
+
+impl CircuitBreaker {
+    pub fn new(max_failures: u32, window: Duration, cool_down: Duration) -> Self {
+        Self {
+            max_failures,
+            window,
+            cool_down,
+            failures: VecDeque::new(),
+        }
+    }
+
+    pub(crate) fn max_failures(&self) -> u32 {
+        self.max_failures
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.failures.clear();
+    }
+
+    // Records a failure, returning the cool-down to wait if the breaker opened
+    pub(crate) fn record_failure(&mut self, now: Instant) -> Option<Duration> {
+        while self
+            .failures
+            .front()
+            .is_some_and(|&failure| now.duration_since(failure) > self.window)
+        {
+            self.failures.pop_front();
+        }
+        self.failures.push_back(now);
+
+        if self.failures.len() >= self.max_failures as usize {
+            self.failures.clear();
+            Some(self.cool_down)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_opens_after_too_many_failures_within_the_window() {
+        let mut breaker = CircuitBreaker::new(3, Duration::from_secs(10), Duration::from_secs(60));
+        let start = Instant::now();
+
+        assert_eq!(breaker.record_failure(start), None);
+        assert_eq!(breaker.record_failure(start + Duration::from_secs(1)), None);
+        assert_eq!(
+            breaker.record_failure(start + Duration::from_secs(2)),
+            Some(Duration::from_secs(60))
+        );
+        // Failures before the breaker opened don't count anymore
+        assert_eq!(breaker.record_failure(start + Duration::from_secs(3)), None);
+    }
+
+    #[test]
+    fn it_forgets_failures_outside_the_window() {
+        let mut breaker = CircuitBreaker::new(2, Duration::from_secs(10), Duration::from_secs(60));
+        let start = Instant::now();
+
+        assert_eq!(breaker.record_failure(start), None);
+        assert_eq!(
+            breaker.record_failure(start + Duration::from_secs(11)),
+            None
+        );
+        assert_eq!(
+            breaker.record_failure(start + Duration::from_secs(12)),
+            Some(Duration::from_secs(60))
+        );
+    }
+}
@@ -0,0 +1,43 @@
+use std::num::NonZeroU64;
+
+// How many times the child may be restarted after failing, spelled out so
+// that never restarting it can't be mistaken for restarting it without limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RestartBudget {
+    Never,
+    Limited(NonZeroU64),
+    #[default]
+    Unlimited,
+}
+
+impl RestartBudget {
+    // Zero is `Never`
+    pub fn times(times: u64) -> Self {
+        match NonZeroU64::new(times) {
+            Some(times) => Self::Limited(times),
+            None => Self::Never,
+        }
+    }
+
+    // The restarts left, None for unlimited ones
+    pub(crate) fn remaining(self) -> Option<u64> {
+        match self {
+            Self::Never => Some(0),
+            Self::Limited(times) => Some(times.get()),
+            Self::Unlimited => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_restarts_are_never_restarting() {
+        assert_eq!(RestartBudget::times(0), RestartBudget::Never);
+        assert_eq!(RestartBudget::times(0).remaining(), Some(0));
+        assert_eq!(RestartBudget::times(3).remaining(), Some(3));
+        assert_eq!(RestartBudget::default().remaining(), None);
+    }
+}
@@ -0,0 +1,220 @@
+use std::{
+    ffi::CString,
+    fs, io,
+    os::unix::{ffi::OsStrExt, process::CommandExt},
+    path::{Path, PathBuf},
+    process::Command,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{ChildInfo, HealthCheck, TestOutcome};
+
+const REMOVE_TIMEOUT: Duration = Duration::from_secs(1);
+const REMOVE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+// Limits of the cgroup each child gets, covering everything it starts
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CgroupLimits {
+    memory_max: Option<u64>,
+    cpu_max: Option<(Duration, Duration)>,
+    pids_max: Option<u64>,
+}
+
+impl CgroupLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // In bytes
+    pub fn with_memory_max(self, memory_max: u64) -> Self {
+        Self {
+            memory_max: Some(memory_max),
+            ..self
+        }
+    }
+
+    // `quota` of CPU time every `period`, e.g. 50ms every 100ms for half a
+    // CPU
+    pub fn with_cpu_max(self, quota: Duration, period: Duration) -> Self {
+        Self {
+            cpu_max: Some((quota, period)),
+            ..self
+        }
+    }
+
+    pub fn with_pids_max(self, pids_max: u64) -> Self {
+        Self {
+            pids_max: Some(pids_max),
+            ..self
+        }
+    }
+
+    fn files(&self) -> Vec<(&'static str, String)> {
+        let mut files = vec![];
+        if let Some(memory_max) = self.memory_max {
+            files.push(("memory.max", memory_max.to_string()));
+        }
+        if let Some((quota, period)) = self.cpu_max {
+            let cpu_max = format!("{} {}", quota.as_micros(), period.as_micros());
+            files.push(("cpu.max", cpu_max));
+        }
+        if let Some(pids_max) = self.pids_max {
+            files.push(("pids.max", pids_max.to_string()));
+        }
+        files
+    }
+}
+
+// The cgroup of a single child. Whatever is left in it is killed when it is
+// dropped, so that it can be removed before the next child gets its own.
+pub(crate) struct ChildCgroup {
+    path: PathBuf,
+}
+
+impl ChildCgroup {
+    pub(crate) fn create(parent: &Path, name: &str, limits: &CgroupLimits) -> io::Result<Self> {
+        let path = parent.join(name);
+        fs::create_dir_all(&path)?;
+        let cgroup = Self { path };
+        for (file, value) in limits.files() {
+            fs::write(cgroup.path.join(file), value)?;
+        }
+        Ok(cgroup)
+    }
+
+    // Moves the child into the cgroup before it runs the program, so that
+    // nothing it starts escapes it
+    pub(crate) fn enter_on_spawn(&self, command: &mut Command) -> io::Result<()> {
+        let procs = self.path.join("cgroup.procs");
+        let procs = CString::new(procs.as_os_str().as_bytes())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let enter = move || {
+            let fd = unsafe { libc::open(procs.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC) };
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            // 0 stands for the process writing it
+            let written = unsafe { libc::write(fd, b"0".as_ptr().cast(), 1) };
+            let error = io::Error::last_os_error();
+            unsafe { libc::close(fd) };
+            match written {
+                1 => Ok(()),
+                _ => Err(error),
+            }
+        };
+        unsafe { command.pre_exec(enter) };
+        Ok(())
+    }
+
+    // In bytes
+    pub(crate) fn memory_usage(&self) -> Option<u64> {
+        let current = fs::read_to_string(self.path.join("memory.current")).ok()?;
+        current.trim().parse().ok()
+    }
+
+    pub(crate) fn cpu_usage(&self) -> Option<Duration> {
+        let stat = fs::read_to_string(self.path.join("cpu.stat")).ok()?;
+        stat.lines()
+            .find_map(|line| line.strip_prefix("usage_usec "))
+            .and_then(|usage| usage.trim().parse().ok())
+            .map(Duration::from_micros)
+    }
+
+    // How many processes of the cgroup the OOM killer killed
+    pub(crate) fn oom_kills(&self) -> u64 {
+        let events = fs::read_to_string(self.path.join("memory.events")).unwrap_or_default();
+        events
+            .lines()
+            .find_map(|line| line.strip_prefix("oom_kill "))
+            .and_then(|count| count.trim().parse().ok())
+            .unwrap_or(0)
+    }
+
+    // Without cgroup.kill, the processes are killed one by one
+    fn kill(&self) {
+        if fs::write(self.path.join("cgroup.kill"), "1").is_ok() {
+            return;
+        }
+        let procs = fs::read_to_string(self.path.join("cgroup.procs")).unwrap_or_default();
+        for pid in procs.lines().filter_map(|pid| pid.parse().ok()) {
+            unsafe { libc::kill(pid, libc::SIGKILL) };
+        }
+    }
+}
+
+impl Drop for ChildCgroup {
+    fn drop(&mut self) {
+        self.kill();
+        let deadline = Instant::now() + REMOVE_TIMEOUT;
+        while fs::remove_dir(&self.path).is_err() && Instant::now() < deadline {
+            thread::sleep(REMOVE_POLL_INTERVAL);
+        }
+    }
+}
+
+// Fails once the cgroup of the child uses `max` bytes of memory or more.
+// Ignored when the child has no cgroup of its own.
+pub struct CgroupMemoryCheck {
+    name: String,
+    max: u64,
+}
+
+impl CgroupMemoryCheck {
+    pub fn new(max: u64) -> Self {
+        Self {
+            name: format!("cgroup memory below {}", max),
+            max,
+        }
+    }
+}
+
+impl HealthCheck for CgroupMemoryCheck {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn check(&mut self, child: &mut ChildInfo) -> TestOutcome {
+        match child.memory_usage() {
+            Some(usage) => TestOutcome::from(usage < self.max),
+            None => TestOutcome::Ignore,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+
+    #[test]
+    fn it_writes_the_limits_and_reads_the_usage() {
+        let parent = env::temp_dir().join(format!("cgroup-{}", process::id()));
+        let limits = CgroupLimits::new()
+            .with_memory_max(64 << 20)
+            .with_cpu_max(Duration::from_millis(50), Duration::from_millis(100))
+            .with_pids_max(32);
+        let cgroup = ChildCgroup::create(&parent, "web-1", &limits).unwrap();
+        let read = |file: &str| fs::read_to_string(parent.join("web-1").join(file)).unwrap();
+        assert_eq!(read("memory.max"), "67108864");
+        assert_eq!(read("cpu.max"), "50000 100000");
+        assert_eq!(read("pids.max"), "32");
+
+        fs::write(parent.join("web-1/memory.current"), "4096\n").unwrap();
+        fs::write(
+            parent.join("web-1/cpu.stat"),
+            "usage_usec 1500\nuser_usec 1000\n",
+        )
+        .unwrap();
+        assert_eq!(cgroup.memory_usage(), Some(4096));
+        assert_eq!(cgroup.cpu_usage(), Some(Duration::from_micros(1500)));
+        assert_eq!(cgroup.oom_kills(), 0);
+        fs::write(parent.join("web-1/memory.events"), "oom 1\noom_kill 1\n").unwrap();
+        assert_eq!(cgroup.oom_kills(), 1);
+
+        // Only a real cgroup can be removed with the files still in it
+        std::mem::forget(cgroup);
+        fs::remove_dir_all(&parent).unwrap();
+    }
+}
@@ -0,0 +1,227 @@
+use std::{
+    any::Any,
+    error::Error,
+    fmt,
+    time::{Duration, Instant},
+};
+
+use crate::{ChildInfo, TestOutcome, Threading};
+
+// A reusable test, for checks that carry configuration or state of their own
+pub trait HealthCheck {
+    fn name(&self) -> &str;
+    fn check(&mut self, child: &mut ChildInfo) -> TestOutcome;
+
+    // Lets checks tell when the probe itself failed, e.g. to resolve a name,
+    // rather than the child being unhealthy
+    fn try_check(&mut self, child: &mut ChildInfo) -> Result<TestOutcome, TestError> {
+        Ok(self.check(child))
+    }
+
+    // What the checks making up this one found when it last ran, for
+    // composite checks
+    fn sub_results(&self) -> Vec<SubResult> {
+        vec![]
+    }
+}
+
+// The result of a check within a composite one. Errors of its probe are kept
+// as messages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubResult {
+    pub name: String,
+    pub result: Result<TestOutcome, String>,
+    pub sub_results: Vec<SubResult>,
+}
+
+// Closures passed to `add_test` along with their name
+impl<F, O> HealthCheck for (String, F)
+where
+    F: FnMut(&mut ChildInfo) -> O,
+    O: IntoTestResult,
+{
+    fn name(&self) -> &str {
+        &self.0
+    }
+
+    // Errors of the probe can't tell whether the child is healthy
+    fn check(&mut self, child: &mut ChildInfo) -> TestOutcome {
+        self.try_check(child).unwrap_or(TestOutcome::Ignore)
+    }
+
+    fn try_check(&mut self, child: &mut ChildInfo) -> Result<TestOutcome, TestError> {
+        (self.1)(child).into_test_result()
+    }
+}
+
+impl<T: HealthCheck + ?Sized> HealthCheck for Box<T> {
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+
+    fn check(&mut self, child: &mut ChildInfo) -> TestOutcome {
+        (**self).check(child)
+    }
+
+    fn try_check(&mut self, child: &mut ChildInfo) -> Result<TestOutcome, TestError> {
+        (**self).try_check(child)
+    }
+
+    fn sub_results(&self) -> Vec<SubResult> {
+        (**self).sub_results()
+    }
+}
+
+// A test that couldn't run its probe, as opposed to one that found the child
+// unhealthy. Anything that converts to a boxed error converts to it, so `?`
+// works on the results of the probe.
+pub struct TestError(Box<dyn Error + Send + Sync>);
+
+impl TestError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self(message.into().into())
+    }
+
+    pub fn inner(&self) -> &(dyn Error + Send + Sync + 'static) {
+        &*self.0
+    }
+}
+
+impl<E: Into<Box<dyn Error + Send + Sync>>> From<E> for TestError {
+    fn from(error: E) -> Self {
+        Self(error.into())
+    }
+}
+
+impl fmt::Display for TestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl fmt::Debug for TestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+// What tests passed to `add_test` can return: an outcome, or a result telling
+// errors of the probe apart
+pub trait IntoTestResult {
+    fn into_test_result(self) -> Result<TestOutcome, TestError>;
+}
+
+impl<O: Into<TestOutcome>> IntoTestResult for O {
+    fn into_test_result(self) -> Result<TestOutcome, TestError> {
+        Ok(self.into())
+    }
+}
+
+impl<O: Into<TestOutcome>> IntoTestResult for Result<O, TestError> {
+    fn into_test_result(self) -> Result<TestOutcome, TestError> {
+        self.map(Into::into)
+    }
+}
+
+// What a test panicked with, when it's a message
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    match (
+        payload.downcast_ref::<&str>(),
+        payload.downcast_ref::<String>(),
+    ) {
+        (Some(message), _) => message.to_string(),
+        (_, Some(message)) => message.clone(),
+        _ => "panicked".to_string(),
+    }
+}
+
+// A sequential test, with its own interval if it doesn't run on every check.
+// Advisory tests are reported like the others but never fail a check.
+pub(crate) struct ScheduledCheck<S: Threading> {
+    pub(crate) check: Box<S::Check<'static>>,
+    pub(crate) every: Option<Duration>,
+    pub(crate) due: Option<Instant>,
+    pub(crate) advisory: bool,
+}
+
+impl<S: Threading> ScheduledCheck<S> {
+    pub(crate) fn is_due(&self, now: Instant, regular: bool) -> bool {
+        match self.due {
+            Some(due) if self.every.is_some() => now >= due,
+            _ => regular,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::OutputTail;
+    use crate::{Local, SupervisedProcess};
+
+    struct FailAfter {
+        checks: u32,
+        limit: u32,
+    }
+
+    impl HealthCheck for FailAfter {
+        fn name(&self) -> &str {
+            "fail after"
+        }
+
+        fn check(&mut self, _: &mut ChildInfo) -> TestOutcome {
+            self.checks += 1;
+            TestOutcome::from(self.checks <= self.limit)
+        }
+    }
+
+    #[test]
+    fn it_runs_custom_checks() {
+        let mut passing_count = 0;
+        let mut failures = vec![];
+
+        let mut process = SupervisedProcess::new("sleep")
+            .with_args(["5"])
+            .add_check(FailAfter {
+                checks: 0,
+                limit: 2,
+            })
+            .with_check_interval(Duration::from_millis(10))
+            .with_restart_times(0)
+            .on_tests_passing(|| passing_count += 1)
+            .on_test_error(|name: &str| failures.push(name.to_string()));
+        assert!(process.run().is_ok());
+        drop(process);
+
+        assert_eq!(passing_count, 2);
+        assert_eq!(failures, vec!["fail after"]);
+    }
+
+    #[test]
+    fn named_closures_are_checks() {
+        let mut check = ("always true".to_string(), |_: &mut ChildInfo| true);
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let output = OutputTail::default();
+        let mut info = ChildInfo::new(&mut child, Instant::now(), &output);
+        assert_eq!(check.name(), "always true");
+        assert_eq!(check.check(&mut info), TestOutcome::Healthy);
+        child.wait().unwrap();
+    }
+
+    #[test]
+    fn checks_with_their_own_interval_run_when_due() {
+        let now = Instant::now();
+        let mut check = ScheduledCheck::<Local> {
+            check: Box::new(("check".to_string(), |_: &mut ChildInfo| true)),
+            every: None,
+            due: Some(now + Duration::from_secs(1)),
+            advisory: false,
+        };
+        assert!(check.is_due(now, true));
+        assert!(!check.is_due(now, false));
+
+        check.every = Some(Duration::from_secs(1));
+        assert!(!check.is_due(now, true));
+        assert!(check.is_due(now + Duration::from_secs(1), false));
+    }
+}
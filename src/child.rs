@@ -0,0 +1,414 @@
+use std::{
+    io,
+    process::{Child, ExitStatus},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    backend::SharedBackend, output::OutputTail, scratch::ScratchDir, watchdog::StdioWatchdog, Clock,
+};
+
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+pub(crate) struct RunningChild {
+    // None when supervising a process we didn't spawn, or one the backend
+    // kept to itself
+    pub(crate) child: Option<Child>,
+    backend: Option<SharedBackend>,
+    pid: u32,
+    // Kept once reaped, as the backend may forget about the child by then
+    status: Option<ExitStatus>,
+    pub(crate) started: Instant,
+    pub(crate) watchdog: Option<StdioWatchdog>,
+    pub(crate) output: OutputTail,
+    #[cfg(windows)]
+    pub(crate) job: Option<crate::windows::JobObject>,
+    #[cfg(all(feature = "cgroup", target_os = "linux"))]
+    pub(crate) cgroup: Option<crate::cgroup::ChildCgroup>,
+    // OOM kills across the system when the child started, for when it has no
+    // cgroup to tell
+    #[cfg(target_os = "linux")]
+    oom_kills: Option<u64>,
+    pub(crate) scratch_dir: Option<ScratchDir>,
+}
+
+impl RunningChild {
+    // `started` comes from the clock of the supervisor, like every time
+    // compared to it
+    pub(crate) fn new(child: Child, watchdog: Option<StdioWatchdog>, started: Instant) -> Self {
+        Self {
+            pid: child.id(),
+            status: None,
+            child: Some(child),
+            backend: None,
+            started,
+            watchdog,
+            output: OutputTail::default(),
+            #[cfg(windows)]
+            job: None,
+            #[cfg(all(feature = "cgroup", target_os = "linux"))]
+            cgroup: None,
+            #[cfg(target_os = "linux")]
+            oom_kills: crate::linux::oom_kills(),
+            scratch_dir: None,
+        }
+    }
+
+    pub(crate) fn from_backend(pid: u32, backend: SharedBackend, started: Instant) -> Self {
+        Self {
+            child: None,
+            backend: Some(backend),
+            pid,
+            status: None,
+            started,
+            watchdog: None,
+            output: OutputTail::default(),
+            #[cfg(windows)]
+            job: None,
+            #[cfg(all(feature = "cgroup", target_os = "linux"))]
+            cgroup: None,
+            #[cfg(target_os = "linux")]
+            oom_kills: crate::linux::oom_kills(),
+            scratch_dir: None,
+        }
+    }
+
+    #[cfg(unix)]
+    pub(crate) fn adopted(pid: u32, started: Instant) -> Self {
+        Self {
+            child: None,
+            backend: None,
+            pid,
+            status: None,
+            started,
+            watchdog: None,
+            output: OutputTail::default(),
+            #[cfg(all(feature = "cgroup", target_os = "linux"))]
+            cgroup: None,
+            #[cfg(target_os = "linux")]
+            oom_kills: crate::linux::oom_kills(),
+            scratch_dir: None,
+        }
+    }
+
+    pub(crate) fn is_adopted(&self) -> bool {
+        self.child.is_none() && self.backend.is_none()
+    }
+
+    pub(crate) fn id(&self) -> u32 {
+        self.pid
+    }
+
+    // The exit status, once the child exited and got reaped. Never known for
+    // adopted processes.
+    pub(crate) fn try_wait(&mut self) -> Option<ExitStatus> {
+        if self.status.is_none() {
+            self.status = match (&self.backend, &mut self.child) {
+                (Some(backend), _) => backend.lock().unwrap().try_wait(self.pid).ok().flatten(),
+                (None, Some(child)) => child.try_wait().ok().flatten(),
+                (None, None) => None,
+            };
+        }
+        self.status
+    }
+
+    // Stops the child, first asking nicely with a SIGTERM when `grace` isn't
+    // zero, then with a SIGKILL, waiting up to `timeout` for it to exit. Only
+    // gives up when it is still running after that. On Windows it is asked
+    // with a CTRL_BREAK_EVENT instead, and its job object is terminated
+    // afterwards. The exit status is only known for children we spawned
+    // ourselves. Children of a backend are left to how it kills them.
+    pub(crate) fn kill(
+        &mut self,
+        grace: Duration,
+        timeout: Duration,
+    ) -> io::Result<Option<ExitStatus>> {
+        #[cfg(unix)]
+        if self.backend.is_none() && !grace.is_zero() && !self.has_exited() {
+            crate::unix::signal(self.pid, libc::SIGTERM);
+            self.wait_exit(grace);
+        }
+        #[cfg(windows)]
+        if self.backend.is_none()
+            && !grace.is_zero()
+            && !self.has_exited()
+            && crate::windows::ctrl_break(self.pid)
+        {
+            self.wait_exit(grace);
+        }
+        // Also takes down what the child left running
+        #[cfg(windows)]
+        if let Some(job) = &self.job {
+            job.terminate();
+        }
+        #[cfg(not(any(unix, windows)))]
+        let _ = grace;
+
+        if !self.has_exited() {
+            match (&mut self.child, &self.backend) {
+                (Some(child), _) => drop(child.kill()),
+                (None, Some(backend)) => drop(backend.lock().unwrap().kill(self.pid)),
+                #[cfg(unix)]
+                (None, None) => crate::unix::kill(self.pid),
+                #[cfg(not(unix))]
+                (None, None) => {}
+            }
+            if !self.wait_exit(timeout) {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "still running after being killed",
+                ));
+            }
+        }
+        Ok(self.try_wait())
+    }
+
+    // Polls until the child exited, for at most `timeout`
+    fn wait_exit(&mut self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if self.has_exited() {
+                return true;
+            }
+            if Instant::now() >= deadline {
+                return false;
+            }
+            thread::sleep(EXIT_POLL_INTERVAL);
+        }
+    }
+
+    // Sends `signal` to the child, unless it already exited
+    #[cfg(unix)]
+    pub(crate) fn signal(&mut self, signal: i32) {
+        if let Some(backend) = &self.backend {
+            let _ = backend.lock().unwrap().signal(self.pid, signal);
+        } else if !self.has_exited() {
+            crate::unix::signal(self.pid, signal);
+        }
+    }
+
+    // Whether the child was killed for running out of memory, going by how it
+    // ended on its own. The memory events of its cgroup tell when it has one.
+    // Otherwise it takes a SIGKILL along with an OOM kill somewhere on the
+    // system since it started, so that a `kill -9` isn't taken for one.
+    pub(crate) fn was_oom_killed(&self, exit_status: Option<ExitStatus>) -> bool {
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            if exit_status.and_then(|status| status.signal()) != Some(libc::SIGKILL) {
+                return false;
+            }
+            #[cfg(all(feature = "cgroup", target_os = "linux"))]
+            if let Some(cgroup) = &self.cgroup {
+                return cgroup.oom_kills() > 0;
+            }
+            #[cfg(target_os = "linux")]
+            return self.oom_kills.is_some_and(|before| {
+                crate::linux::oom_kills().is_some_and(|oom_kills| oom_kills > before)
+            });
+            #[cfg(not(target_os = "linux"))]
+            false
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = exit_status;
+            false
+        }
+    }
+
+    // Also known for adopted processes, unlike the exit status
+    pub(crate) fn has_exited(&mut self) -> bool {
+        #[cfg(unix)]
+        if self.is_adopted() {
+            return !crate::unix::is_alive(self.pid);
+        }
+        self.try_wait().is_some()
+    }
+
+    // What tests get to see, nothing for adopted processes
+    // `now` is the time of the check, from the clock of the supervisor
+    pub(crate) fn info<'c>(
+        &'c mut self,
+        now: Instant,
+        clock: Option<&'c dyn Clock>,
+    ) -> Option<ChildInfo<'c>> {
+        if self.is_adopted() {
+            return None;
+        }
+        Some(ChildInfo {
+            pid: self.pid,
+            child: self.child.as_mut(),
+            backend: self.backend.as_ref(),
+            started: self.started,
+            now,
+            clock,
+            output: &self.output,
+            #[cfg(all(feature = "cgroup", target_os = "linux"))]
+            cgroup: self.cgroup.as_ref(),
+        })
+    }
+}
+
+// What tests get to see of the child. Operations that would change its state
+// behind the supervisor's back, like `kill` or `wait`, are only reachable
+// through `raw`.
+pub struct ChildInfo<'c> {
+    pid: u32,
+    child: Option<&'c mut Child>,
+    backend: Option<&'c SharedBackend>,
+    started: Instant,
+    now: Instant,
+    clock: Option<&'c dyn Clock>,
+    output: &'c OutputTail,
+    #[cfg(all(feature = "cgroup", target_os = "linux"))]
+    cgroup: Option<&'c crate::cgroup::ChildCgroup>,
+}
+
+impl<'c> ChildInfo<'c> {
+    #[cfg(test)]
+    pub(crate) fn new(child: &'c mut Child, started: Instant, output: &'c OutputTail) -> Self {
+        Self {
+            pid: child.id(),
+            child: Some(child),
+            backend: None,
+            started,
+            now: Instant::now(),
+            clock: None,
+            output,
+            #[cfg(all(feature = "cgroup", target_os = "linux"))]
+            cgroup: None,
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn with_clock(self, clock: &'c dyn Clock) -> Self {
+        Self {
+            now: clock.now(),
+            clock: Some(clock),
+            ..self
+        }
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        match (&mut self.child, self.backend) {
+            (Some(child), _) => child.try_wait(),
+            (None, Some(backend)) => backend.lock().unwrap().try_wait(self.pid),
+            (None, None) => Ok(None),
+        }
+    }
+
+    // As of the check, going by the clock of the supervisor
+    pub fn uptime(&self) -> Duration {
+        self.now.saturating_duration_since(self.started)
+    }
+
+    // The current time, going by the clock of the supervisor
+    pub fn now(&self) -> Instant {
+        match self.clock {
+            Some(clock) => clock.now(),
+            None => Instant::now(),
+        }
+    }
+
+    // The last lines of output, when capturing it with `with_output_tail`
+    pub fn output_tail(&self) -> Vec<String> {
+        self.output.snapshot()
+    }
+
+    // What the cgroup of the child uses, when it has one of its own with
+    // `with_cgroup`
+    #[cfg(all(feature = "cgroup", target_os = "linux"))]
+    pub fn memory_usage(&self) -> Option<u64> {
+        self.cgroup?.memory_usage()
+    }
+
+    #[cfg(all(feature = "cgroup", target_os = "linux"))]
+    pub fn cpu_usage(&self) -> Option<Duration> {
+        self.cgroup?.cpu_usage()
+    }
+
+    // Escape hatch for tests that need the underlying `Child`, e.g. to talk
+    // to it through its stdio. None when the backend kept the process to
+    // itself.
+    pub fn raw(&mut self) -> Option<&mut Child> {
+        self.child.as_deref_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use super::*;
+
+    #[test]
+    fn child_info_reports_the_child() {
+        let mut child = Command::new("sleep").arg("5").spawn().unwrap();
+        let pid = child.id();
+        let started = Instant::now() - Duration::from_secs(2);
+
+        let output = OutputTail::new(1);
+        output.push("ready".to_string());
+        let mut info = ChildInfo::new(&mut child, started, &output);
+        assert_eq!(info.pid(), pid);
+        assert!(info.uptime() >= Duration::from_secs(2));
+        assert_eq!(info.output_tail(), vec!["ready"]);
+        assert!(matches!(info.try_wait(), Ok(None)));
+        info.raw().unwrap().kill().unwrap();
+        assert!(info.raw().unwrap().wait().is_ok());
+        assert!(matches!(info.try_wait(), Ok(Some(_))));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn killing_escalates_to_sigkill() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let spawn = |script: &str| {
+            let child = Command::new("sh").args(["-c", script]).spawn().unwrap();
+            RunningChild::new(child, None, Instant::now())
+        };
+        let grace = Duration::from_millis(200);
+
+        let mut polite = spawn("exec sleep 5");
+        let status = polite.kill(grace, grace).unwrap().unwrap();
+        assert_eq!(status.signal(), Some(libc::SIGTERM));
+
+        let mut stubborn = spawn("trap '' TERM; while :; do sleep 0.01; done");
+        thread::sleep(Duration::from_millis(100));
+        let started = Instant::now();
+        let status = stubborn.kill(grace, grace).unwrap().unwrap();
+        assert_eq!(status.signal(), Some(libc::SIGKILL));
+        assert!(started.elapsed() >= grace);
+    }
+
+    #[cfg(all(feature = "cgroup", target_os = "linux"))]
+    #[test]
+    fn only_the_cgroup_tells_about_oom_kills() {
+        use std::{env, fs, process};
+
+        let child = Command::new("sh")
+            .args(["-c", "kill -9 $$"])
+            .spawn()
+            .unwrap();
+        let mut child = RunningChild::new(child, None, Instant::now());
+        let status = child.child.as_mut().unwrap().wait().unwrap();
+        let parent = env::temp_dir().join(format!("oom-cgroup-{}", process::id()));
+        let limits = crate::CgroupLimits::new();
+        child.cgroup = Some(crate::cgroup::ChildCgroup::create(&parent, "web", &limits).unwrap());
+        assert!(!child.was_oom_killed(Some(status)));
+
+        fs::write(parent.join("web/memory.events"), "oom 1\noom_kill 1\n").unwrap();
+        assert!(child.was_oom_killed(Some(status)));
+        assert!(!child.was_oom_killed(None));
+
+        // Only a real cgroup can be removed with the files still in it
+        std::mem::forget(child.cgroup.take());
+        fs::remove_dir_all(&parent).unwrap();
+    }
+}
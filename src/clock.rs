@@ -0,0 +1,96 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+
+// Where a supervisor gets the time from, and how it waits for intervals and
+// backoffs to pass. Supervisors without one use the system clock.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+    fn sleep(&self, duration: Duration);
+
+    // The wall clock time, for checks aligned to it
+    fn system_time(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+// A clock only moving forward when slept on or advanced, so tests can go
+// through hours of checks and backoffs instantly. Clones share their time.
+#[derive(Clone)]
+pub struct MockClock {
+    start: Instant,
+    start_time: SystemTime,
+    elapsed: Arc<Mutex<Duration>>,
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+            start_time: SystemTime::now(),
+            elapsed: Arc::default(),
+        }
+    }
+}
+
+impl MockClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // A clock whose wall clock time starts at `start_time`
+    pub fn starting_at(start_time: SystemTime) -> Self {
+        Self {
+            start_time,
+            ..Self::default()
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.elapsed.lock().unwrap() += duration;
+    }
+
+    // The virtual time passed since the clock was created
+    pub fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().unwrap()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+
+    fn system_time(&self) -> SystemTime {
+        self.start_time + self.elapsed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mock_time_only_passes_when_told() {
+        let clock = MockClock::new();
+        let shared = clock.clone();
+        let before = clock.now();
+
+        shared.sleep(Duration::from_secs(3600));
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(clock.now() - before, Duration::from_secs(3660));
+        assert_eq!(shared.elapsed(), Duration::from_secs(3660));
+
+        let clock = MockClock::starting_at(SystemTime::UNIX_EPOCH);
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(
+            clock.system_time(),
+            SystemTime::UNIX_EPOCH + Duration::from_secs(60)
+        );
+    }
+}
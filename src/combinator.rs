@@ -0,0 +1,355 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{ChildInfo, HealthCheck, SubResult, TestError, TestOutcome};
+
+// Wraps checks to compose robust probes, e.g.
+// `check.retries(3).slow_is_unhealthy(Duration::from_secs(2)).every(Duration::from_secs(10))`
+pub trait HealthCheckExt: HealthCheck + Sized {
+    // Runs the check again, up to `retries` more times, while it fails or its
+    // probe errors. Outcomes like `RestartNow` are never retried.
+    fn retries(self, retries: u32) -> Retries<Self> {
+        Retries {
+            check: self,
+            retries,
+            delay: Duration::ZERO,
+        }
+    }
+
+    // Fails the check when it took longer than `limit`. This is no timeout:
+    // the check isn't interrupted, so a hung probe still holds up the
+    // supervisor. Concurrent and async tests get a real deadline instead,
+    // see `with_test_deadline`.
+    fn slow_is_unhealthy(self, limit: Duration) -> SlowIsUnhealthy<Self> {
+        SlowIsUnhealthy { check: self, limit }
+    }
+
+    // Only runs the check once every `every`, repeating its last result in
+    // between
+    fn every(self, every: Duration) -> Every<Self> {
+        Every {
+            check: self,
+            every,
+            last: None,
+        }
+    }
+}
+
+impl<C: HealthCheck> HealthCheckExt for C {}
+
+pub struct Retries<C> {
+    check: C,
+    retries: u32,
+    delay: Duration,
+}
+
+impl<C> Retries<C> {
+    // How long to wait before each retry, none by default
+    pub fn with_delay(self, delay: Duration) -> Self {
+        Self { delay, ..self }
+    }
+}
+
+impl<C: HealthCheck> HealthCheck for Retries<C> {
+    fn name(&self) -> &str {
+        self.check.name()
+    }
+
+    fn sub_results(&self) -> Vec<SubResult> {
+        self.check.sub_results()
+    }
+
+    fn check(&mut self, child: &mut ChildInfo) -> TestOutcome {
+        self.try_check(child).unwrap_or(TestOutcome::Ignore)
+    }
+
+    fn try_check(&mut self, child: &mut ChildInfo) -> Result<TestOutcome, TestError> {
+        let mut result = self.check.try_check(child);
+        for _ in 0..self.retries {
+            if !matches!(result, Ok(TestOutcome::Unhealthy) | Err(_)) {
+                break;
+            }
+            thread::sleep(self.delay);
+            result = self.check.try_check(child);
+        }
+        result
+    }
+}
+
+pub struct SlowIsUnhealthy<C> {
+    check: C,
+    limit: Duration,
+}
+
+impl<C: HealthCheck> HealthCheck for SlowIsUnhealthy<C> {
+    fn name(&self) -> &str {
+        self.check.name()
+    }
+
+    fn sub_results(&self) -> Vec<SubResult> {
+        self.check.sub_results()
+    }
+
+    fn check(&mut self, child: &mut ChildInfo) -> TestOutcome {
+        self.try_check(child).unwrap_or(TestOutcome::Ignore)
+    }
+
+    fn try_check(&mut self, child: &mut ChildInfo) -> Result<TestOutcome, TestError> {
+        let started = child.now();
+        let result = self.check.try_check(child);
+        match child.now().saturating_duration_since(started) > self.limit {
+            true => Ok(TestOutcome::Unhealthy),
+            false => result,
+        }
+    }
+}
+
+pub struct Every<C> {
+    check: C,
+    every: Duration,
+    // When the check last ran, and what the probe errored with as a message
+    last: Option<(Instant, Result<TestOutcome, String>)>,
+}
+
+impl<C: HealthCheck> HealthCheck for Every<C> {
+    fn name(&self) -> &str {
+        self.check.name()
+    }
+
+    fn sub_results(&self) -> Vec<SubResult> {
+        self.check.sub_results()
+    }
+
+    fn check(&mut self, child: &mut ChildInfo) -> TestOutcome {
+        self.try_check(child).unwrap_or(TestOutcome::Ignore)
+    }
+
+    fn try_check(&mut self, child: &mut ChildInfo) -> Result<TestOutcome, TestError> {
+        let now = child.now();
+        match &self.last {
+            Some((ran, result)) if now < *ran + self.every => {
+                result.clone().map_err(TestError::new)
+            }
+            _ => {
+                let result = self.check.try_check(child);
+                let kept = result.as_ref().copied().map_err(ToString::to_string);
+                self.last = Some((now, kept));
+                result
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Composition {
+    AllOf,
+    AnyOf,
+    Not,
+}
+
+// A single named check made of others, e.g. "port open and HTTP healthy, or
+// maintenance file present" as
+// `any_of("up", vec![Box::new(all_of("serving", ..)), Box::new(maintenance)])`.
+// What each of them found is reported through `on_sub_results`.
+pub struct CompositeCheck {
+    name: String,
+    composition: Composition,
+    checks: Vec<Box<dyn HealthCheck>>,
+    last: Vec<SubResult>,
+}
+
+impl CompositeCheck {
+    // Healthy when every check is. Stops at the first one that fails.
+    pub fn all_of(name: &str, checks: Vec<Box<dyn HealthCheck>>) -> Self {
+        Self::new(name, Composition::AllOf, checks)
+    }
+
+    // Healthy when any check is. Stops at the first one that passes.
+    pub fn any_of(name: &str, checks: Vec<Box<dyn HealthCheck>>) -> Self {
+        Self::new(name, Composition::AnyOf, checks)
+    }
+
+    // Healthy when the check is unhealthy and the other way around. Other
+    // outcomes and probe errors are kept as they are.
+    pub fn not(name: &str, check: impl HealthCheck + 'static) -> Self {
+        Self::new(name, Composition::Not, vec![Box::new(check)])
+    }
+
+    fn new(name: &str, composition: Composition, checks: Vec<Box<dyn HealthCheck>>) -> Self {
+        Self {
+            name: name.into(),
+            composition,
+            checks,
+            last: vec![],
+        }
+    }
+}
+
+impl HealthCheck for CompositeCheck {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn check(&mut self, child: &mut ChildInfo) -> TestOutcome {
+        self.try_check(child).unwrap_or(TestOutcome::Ignore)
+    }
+
+    // Probe errors only decide the outcome when nothing else does, e.g. when
+    // no check of `all_of` failed but one couldn't tell
+    fn try_check(&mut self, child: &mut ChildInfo) -> Result<TestOutcome, TestError> {
+        self.last.clear();
+        let mut worst = None;
+        let mut error = None;
+        for check in &mut self.checks {
+            let result = check.try_check(child);
+            self.last.push(SubResult {
+                name: check.name().into(),
+                result: result.as_ref().copied().map_err(ToString::to_string),
+                sub_results: check.sub_results(),
+            });
+            let outcome = match result {
+                Ok(outcome) => outcome,
+                Err(e) => {
+                    error.get_or_insert(format!("{}: {}", check.name(), e));
+                    continue;
+                }
+            };
+            match self.composition {
+                Composition::AllOf if outcome.is_failure() => return Ok(outcome),
+                Composition::AnyOf if outcome == TestOutcome::Healthy => return Ok(outcome),
+                Composition::Not => {
+                    return Ok(match outcome {
+                        TestOutcome::Healthy => TestOutcome::Unhealthy,
+                        TestOutcome::Unhealthy => TestOutcome::Healthy,
+                        outcome => outcome,
+                    })
+                }
+                _ => worst = Some(worst.unwrap_or(outcome).worst(outcome)),
+            }
+        }
+        match (self.composition, error) {
+            (_, Some(error)) => Err(TestError::new(error)),
+            (Composition::AnyOf, None) => {
+                Ok(worst.map_or(TestOutcome::Unhealthy, |w| w.worst(TestOutcome::Unhealthy)))
+            }
+            (_, None) => Ok(worst.unwrap_or(TestOutcome::Healthy)),
+        }
+    }
+
+    fn sub_results(&self) -> Vec<SubResult> {
+        self.last.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use super::*;
+    use crate::{output::OutputTail, MockClock};
+
+    // Its probe errors on the first `failures` runs
+    fn flaky(failures: u32) -> impl HealthCheck + 'static {
+        let mut runs = 0;
+        let check = move |_: &mut ChildInfo| {
+            runs += 1;
+            match runs <= failures {
+                true => Err(TestError::new(format!("run {}", runs))),
+                false => Ok(true),
+            }
+        };
+        ("flaky".to_string(), check)
+    }
+
+    #[test]
+    fn combinators_wrap_checks() {
+        let mut child = Command::new("true").spawn().unwrap();
+        let output = OutputTail::default();
+        let mut info = ChildInfo::new(&mut child, Instant::now(), &output);
+
+        let mut check = flaky(2).retries(2);
+        assert_eq!(check.name(), "flaky");
+        assert_eq!(check.check(&mut info), TestOutcome::Healthy);
+        let mut check = flaky(3).retries(2);
+        assert_eq!(check.try_check(&mut info).unwrap_err().to_string(), "run 3");
+
+        child.wait().unwrap();
+    }
+
+    #[test]
+    fn combinators_go_by_the_clock() {
+        let mut child = Command::new("true").spawn().unwrap();
+        let output = OutputTail::default();
+        let clock = MockClock::new();
+        let mut info = ChildInfo::new(&mut child, Instant::now(), &output).with_clock(&clock);
+
+        let slow_clock = clock.clone();
+        let slow = move |_: &mut ChildInfo| {
+            slow_clock.advance(Duration::from_secs(3));
+            true
+        };
+        let mut check = ("slow".to_string(), slow).slow_is_unhealthy(Duration::from_secs(2));
+        assert_eq!(check.check(&mut info), TestOutcome::Unhealthy);
+        let mut check = ("slow".to_string(), |_: &mut ChildInfo| true)
+            .slow_is_unhealthy(Duration::from_secs(2));
+        assert_eq!(check.check(&mut info), TestOutcome::Healthy);
+
+        let mut check = flaky(1).every(Duration::from_secs(60));
+        for _ in 0..3 {
+            assert_eq!(check.try_check(&mut info).unwrap_err().to_string(), "run 1");
+            clock.advance(Duration::from_secs(20));
+        }
+        assert!(check.try_check(&mut info).is_ok());
+        child.wait().unwrap();
+    }
+
+    fn fixed(name: &str, outcome: TestOutcome) -> Box<dyn HealthCheck> {
+        Box::new((name.to_string(), move |_: &mut ChildInfo| outcome))
+    }
+
+    #[test]
+    fn composite_checks_combine_others() {
+        use TestOutcome::*;
+
+        let mut child = Command::new("true").spawn().unwrap();
+        let output = OutputTail::default();
+        let mut info = ChildInfo::new(&mut child, Instant::now(), &output);
+
+        let serving = CompositeCheck::all_of(
+            "serving",
+            vec![fixed("port", Healthy), fixed("http", Unhealthy)],
+        );
+        let mut up = CompositeCheck::any_of(
+            "up",
+            vec![
+                Box::new(serving),
+                Box::new(CompositeCheck::not(
+                    "no maintenance",
+                    fixed("maintenance", Unhealthy),
+                )),
+            ],
+        );
+        assert_eq!(up.check(&mut info), Healthy);
+        let sub_results = up.sub_results();
+        assert_eq!(sub_results.len(), 2);
+        assert_eq!(sub_results[0].result, Ok(Unhealthy));
+        assert_eq!(sub_results[0].sub_results[1].name, "http");
+        assert_eq!(sub_results[1].result, Ok(Healthy));
+
+        let mut check =
+            CompositeCheck::all_of("all", vec![Box::new(flaky(1)), fixed("ok", Healthy)]);
+        assert_eq!(
+            check.try_check(&mut info).unwrap_err().to_string(),
+            "flaky: run 1"
+        );
+        let mut check = CompositeCheck::any_of("any", vec![fixed("a", Ignore), fixed("b", Fatal)]);
+        assert_eq!(check.check(&mut info), Fatal);
+        assert_eq!(
+            CompositeCheck::all_of("none", vec![]).check(&mut info),
+            Healthy
+        );
+        child.wait().unwrap();
+    }
+}
@@ -0,0 +1,96 @@
+use std::{
+    ffi::{OsStr, OsString},
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{ChildInfo, HealthCheck, TestOutcome};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+// Runs an external command on every check and passes if it exits with 0,
+// like Docker's HEALTHCHECK. Commands running past the timeout are killed
+// and count as a failure.
+pub struct CommandCheck {
+    name: String,
+    program: OsString,
+    args: Vec<OsString>,
+    timeout: Duration,
+}
+
+impl CommandCheck {
+    pub fn new(
+        program: impl AsRef<OsStr>,
+        args: impl IntoIterator<Item = impl AsRef<OsStr>>,
+    ) -> Self {
+        let program = program.as_ref().to_os_string();
+        Self {
+            name: format!("command {}", program.to_string_lossy()),
+            program,
+            args: args.into_iter().map(|arg| arg.as_ref().into()).collect(),
+            timeout: Duration::from_secs(30),
+        }
+    }
+
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+
+    fn run(&self) -> bool {
+        let Ok(mut child) = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        else {
+            return false;
+        };
+
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            match child.try_wait() {
+                Ok(Some(status)) => return status.success(),
+                Ok(None) if Instant::now() < deadline => thread::sleep(POLL_INTERVAL),
+                _ => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return false;
+                }
+            }
+        }
+    }
+}
+
+impl HealthCheck for CommandCheck {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn check(&mut self, _: &mut ChildInfo) -> TestOutcome {
+        TestOutcome::from(self.run())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_passes_on_exit_code_zero_within_the_timeout() {
+        let check = |program: &str, args: &[&str]| {
+            CommandCheck::new(program, args)
+                .with_timeout(Duration::from_millis(200))
+                .run()
+        };
+        assert!(check("true", &[]));
+        assert!(!check("false", &[]));
+        assert!(!check("sleep", &["5"]));
+        assert!(!check("does-not-exist", &[]));
+        assert_eq!(
+            CommandCheck::new("pg_isready", ["-q"]).name(),
+            "command pg_isready"
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        mpsc::{self, Receiver},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{check, TestOutcome};
+
+pub type ConcurrentTest = Box<dyn FnMut(u32) -> TestOutcome + Send>;
+
+#[derive(Default)]
+pub(crate) struct ConcurrentTests {
+    tests: Vec<(String, Arc<Mutex<ConcurrentTest>>)>,
+}
+
+impl ConcurrentTests {
+    pub(crate) fn push(&mut self, name: String, test: ConcurrentTest) {
+        self.tests.push((name, Arc::new(Mutex::new(test))));
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.tests.is_empty()
+    }
+
+    pub(crate) fn names(&self) -> impl Iterator<Item = &str> {
+        self.tests.iter().map(|(name, _)| name.as_str())
+    }
+
+    // Runs every test on its own thread against the child with the given pid.
+    // Threads are detached, so a hanging test can't hold up the supervisor.
+    pub(crate) fn start(&self, pid: u32) -> Batch {
+        let (sender, receiver) = mpsc::channel();
+        for (index, (_, test)) in self.tests.iter().enumerate() {
+            let test = test.clone();
+            let sender = sender.clone();
+            thread::spawn(move || {
+                // A test that is still busy from a previous tick has missed
+                // its deadline again. Panics are caught while holding the
+                // lock, so they don't poison it for the next ticks.
+                let started = Instant::now();
+                let result = match test.try_lock() {
+                    Ok(mut test) => match panic::catch_unwind(AssertUnwindSafe(|| test(pid))) {
+                        Ok(outcome) => (outcome, Some(started.elapsed()), None),
+                        Err(payload) => (
+                            TestOutcome::Unhealthy,
+                            Some(started.elapsed()),
+                            Some(check::panic_message(&*payload)),
+                        ),
+                    },
+                    Err(_) => (TestOutcome::Unhealthy, None, None),
+                };
+                let _ = sender.send((index, result));
+            });
+        }
+
+        Batch {
+            receiver,
+            results: vec![(TestOutcome::Unhealthy, None, None); self.tests.len()],
+        }
+    }
+}
+
+// How a test ended, how long it took and what it panicked with, if it did
+pub(crate) type TimedOutcome = (TestOutcome, Option<Duration>, Option<String>);
+
+pub(crate) struct Batch {
+    receiver: Receiver<(usize, TimedOutcome)>,
+    results: Vec<TimedOutcome>,
+}
+
+impl Batch {
+    // Waits for the results until the deadline, along with how long each test
+    // took. Tests that didn't finish in time count as failed.
+    pub(crate) fn collect(mut self, deadline: Instant) -> Vec<TimedOutcome> {
+        let mut pending = self.results.len();
+        while pending > 0 {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            match self.receiver.recv_timeout(timeout) {
+                Ok((index, result)) => {
+                    self.results[index] = result;
+                    pending -= 1;
+                }
+                Err(_) => break,
+            }
+        }
+        self.results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outcomes(results: Vec<TimedOutcome>) -> Vec<TestOutcome> {
+        results.into_iter().map(|(outcome, ..)| outcome).collect()
+    }
+
+    #[test]
+    fn it_runs_tests_concurrently() {
+        let mut tests = ConcurrentTests::default();
+        for name in ["a", "b", "c"] {
+            tests.push(
+                name.to_string(),
+                Box::new(|_| {
+                    thread::sleep(Duration::from_millis(50));
+                    TestOutcome::Healthy
+                }),
+            );
+        }
+
+        let started = Instant::now();
+        let results = tests.start(0).collect(started + Duration::from_secs(1));
+        assert!(results.iter().all(|(_, latency, _)| latency.is_some()));
+        assert_eq!(outcomes(results), vec![TestOutcome::Healthy; 3]);
+        assert!(started.elapsed() < Duration::from_millis(140));
+    }
+
+    #[test]
+    fn it_fails_tests_missing_the_deadline() {
+        let mut tests = ConcurrentTests::default();
+        tests.push("fast".to_string(), Box::new(|_| TestOutcome::Healthy));
+        tests.push(
+            "hanging".to_string(),
+            Box::new(|_| {
+                thread::sleep(Duration::from_millis(200));
+                TestOutcome::Healthy
+            }),
+        );
+
+        let results = tests
+            .start(0)
+            .collect(Instant::now() + Duration::from_millis(50));
+        assert_eq!(results[1], (TestOutcome::Unhealthy, None, None));
+        assert_eq!(
+            outcomes(results),
+            vec![TestOutcome::Healthy, TestOutcome::Unhealthy]
+        );
+
+        // Still hanging from the previous tick
+        let results = tests
+            .start(0)
+            .collect(Instant::now() + Duration::from_millis(50));
+        assert_eq!(
+            outcomes(results),
+            vec![TestOutcome::Healthy, TestOutcome::Unhealthy]
+        );
+    }
+
+    #[test]
+    fn it_passes_the_pid_to_tests() {
+        let mut tests = ConcurrentTests::default();
+        tests.push("pid".to_string(), Box::new(|pid| (pid == 42).into()));
+
+        let results = tests
+            .start(42)
+            .collect(Instant::now() + Duration::from_secs(1));
+        assert_eq!(outcomes(results), vec![TestOutcome::Healthy]);
+    }
+}
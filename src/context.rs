@@ -0,0 +1,107 @@
+use std::{
+    path::PathBuf,
+    process::ExitStatus,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, SystemTime},
+};
+
+static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+// Numbers events across every supervisor in the process
+pub(crate) fn next_sequence() -> u64 {
+    SEQUENCE.fetch_add(1, Ordering::SeqCst) + 1
+}
+
+// Why the child is being restarted, or would have been
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestartReason {
+    FailedTest,
+    // A job or worker exiting unsuccessfully
+    Exited,
+    // Through a handle, a reload or watched files, or after the max uptime
+    Requested,
+    // Another child of the group failed
+    SiblingFailed,
+    // The child was killed by the kernel for running out of memory
+    OutOfMemory,
+}
+
+impl RestartReason {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::FailedTest => "failed_test",
+            Self::Exited => "exited",
+            Self::Requested => "requested",
+            Self::SiblingFailed => "sibling_failed",
+            Self::OutOfMemory => "out_of_memory",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RestartContext {
+    pub name: Option<String>,
+    pub attempt: u64,
+    pub uptime: Duration,
+    pub reason: RestartReason,
+    pub failed_test: Option<String>,
+    // What the failed test panicked with, if it did
+    pub test_panic: Option<String>,
+    pub pid: u32,
+    // How the child ended, once reaped. Unknown for adopted processes.
+    pub exit_status: Option<ExitStatus>,
+    // Where the core dump of the crashed child was put, when collecting them
+    pub core_dump: Option<PathBuf>,
+    // How long the supervisor waited before restarting
+    pub backoff: Duration,
+    // Last lines of output before the restart, when capturing it
+    pub output_tail: Vec<String>,
+    // When the event was emitted, and its place among the events of every
+    // supervisor in the process
+    pub timestamp: SystemTime,
+    pub sequence: u64,
+}
+
+impl RestartContext {
+    pub(crate) fn new(name: Option<String>, attempt: u64, uptime: Duration, pid: u32) -> Self {
+        Self {
+            name,
+            attempt,
+            uptime,
+            reason: RestartReason::Requested,
+            failed_test: None,
+            test_panic: None,
+            pid,
+            exit_status: None,
+            core_dump: None,
+            backoff: Duration::ZERO,
+            output_tail: vec![],
+            timestamp: SystemTime::UNIX_EPOCH,
+            sequence: 0,
+        }
+    }
+
+    // A copy stamped as emitted right now
+    pub(crate) fn emitted(&self) -> Self {
+        Self {
+            timestamp: SystemTime::now(),
+            sequence: next_sequence(),
+            ..self.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emitted_events_are_numbered_in_order() {
+        let context = RestartContext::new(Some("web".into()), 1, Duration::ZERO, 42);
+        let first = context.emitted();
+        let second = context.emitted();
+        assert!(second.sequence > first.sequence);
+        assert!(second.timestamp >= first.timestamp);
+        assert_eq!(first.pid, 42);
+    }
+}
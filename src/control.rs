@@ -0,0 +1,151 @@
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use serde_json::{json, Value};
+
+use crate::{SupervisorError, SupervisorHandle};
+
+// Serves a line-based JSON protocol on a Unix socket, for driving a running
+// supervisor from outside like `supervisorctl`. Each request is an object
+// with a `command` of status, stop, start, restart, shutdown or tail-logs,
+// answered by an object with `ok` set.
+pub(crate) struct ControlSocket {
+    path: PathBuf,
+    closed: Arc<AtomicBool>,
+}
+
+impl ControlSocket {
+    pub(crate) fn bind(path: &Path, handle: SupervisorHandle) -> Result<Self, SupervisorError> {
+        // A socket left behind by a previous run would make binding fail
+        if UnixStream::connect(path).is_err() {
+            let _ = fs::remove_file(path);
+        }
+        let listener = UnixListener::bind(path).map_err(SupervisorError::ControlSocket)?;
+        let closed = Arc::new(AtomicBool::new(false));
+        let serving = closed.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if serving.load(Ordering::SeqCst) {
+                    break;
+                }
+                if let Ok(stream) = stream {
+                    let handle = handle.clone();
+                    thread::spawn(move || serve(stream, &handle));
+                }
+            }
+        });
+
+        Ok(Self {
+            path: path.into(),
+            closed,
+        })
+    }
+}
+
+impl Drop for ControlSocket {
+    fn drop(&mut self) {
+        // Wakes the listener up so it notices it was closed
+        self.closed.store(true, Ordering::SeqCst);
+        let _ = UnixStream::connect(&self.path);
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn serve(stream: UnixStream, handle: &SupervisorHandle) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let response = match serde_json::from_str::<Value>(&line?) {
+            Ok(request) => respond(&request, handle),
+            Err(e) => error(&format!("invalid request: {}", e)),
+        };
+        writeln!(writer, "{}", response)?;
+    }
+    Ok(())
+}
+
+fn error(message: &str) -> Value {
+    json!({ "ok": false, "error": message })
+}
+
+fn respond(request: &Value, handle: &SupervisorHandle) -> Value {
+    match request["command"].as_str() {
+        Some("status") => {
+            let mut status = handle.status();
+            status["ok"] = json!(true);
+            status
+        }
+        Some("stop") => {
+            handle.pause();
+            json!({ "ok": true })
+        }
+        Some("start") => {
+            handle.resume();
+            json!({ "ok": true })
+        }
+        Some("restart") => {
+            handle.restart();
+            json!({ "ok": true })
+        }
+        Some("shutdown") => {
+            handle.stop();
+            json!({ "ok": true })
+        }
+        Some("tail-logs") => {
+            let mut lines = handle.output_tail();
+            if let Some(count) = request["lines"].as_u64() {
+                lines.drain(..lines.len().saturating_sub(count as usize));
+            }
+            json!({ "ok": true, "lines": lines })
+        }
+        Some(command) => error(&format!("unknown command '{}'", command)),
+        None => error("missing command"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+    use crate::handle::Control;
+
+    #[test]
+    fn it_answers_commands() {
+        let path = env::temp_dir().join(format!("control-{}.sock", process::id()));
+        let handle = SupervisorHandle::new(Arc::new(Control::default()));
+        let socket = ControlSocket::bind(&path, handle.clone()).unwrap();
+
+        let stream = UnixStream::connect(&path).unwrap();
+        let mut writer = stream.try_clone().unwrap();
+        let mut responses = BufReader::new(stream).lines();
+        let mut send = |request: &str| {
+            writeln!(writer, "{}", request).unwrap();
+            serde_json::from_str::<Value>(&responses.next().unwrap().unwrap()).unwrap()
+        };
+
+        assert_eq!(send(r#"{"command": "status"}"#)["state"], "starting");
+        assert_eq!(send(r#"{"command": "stop"}"#)["ok"], true);
+        assert!(handle.is_paused());
+        assert_eq!(send(r#"{"command": "status"}"#)["state"], "stopped");
+        assert_eq!(send(r#"{"command": "start"}"#)["ok"], true);
+        assert!(!handle.is_paused());
+        assert_eq!(
+            send(r#"{"command": "tail-logs", "lines": 5}"#)["lines"],
+            json!([])
+        );
+        assert_eq!(send(r#"{"command": "bogus"}"#)["ok"], false);
+        assert_eq!(send("not json")["ok"], false);
+
+        drop(socket);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,223 @@
+use std::{
+    ffi::OsStr,
+    fmt::Write as _,
+    fs, io,
+    os::unix::process::{CommandExt, ExitStatusExt},
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// Stands for the parts of the pattern that can't be known, like the time of
+// the crash
+const ANY: char = '\0';
+
+// Lets the child dump core, up to the hard limit it inherits
+pub(crate) fn enable(command: &mut Command) {
+    let enable = || {
+        let mut limit = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        if unsafe { libc::getrlimit(libc::RLIMIT_CORE, &mut limit) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        limit.rlim_cur = limit.rlim_max;
+        if unsafe { libc::setrlimit(libc::RLIMIT_CORE, &limit) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    };
+    unsafe { command.pre_exec(enable) };
+}
+
+// What is known about a crashed child, written next to its core dump
+pub(crate) struct Crash<'c> {
+    pub(crate) name: Option<&'c str>,
+    pub(crate) program: &'c OsStr,
+    pub(crate) pid: u32,
+    pub(crate) status: ExitStatus,
+    pub(crate) working_dir: &'c Path,
+}
+
+impl Crash<'_> {
+    fn executable(&self) -> String {
+        let program = Path::new(self.program).file_name().unwrap_or(self.program);
+        program.to_string_lossy().into_owned()
+    }
+
+    fn metadata(&self, core: &Path) -> String {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let mut metadata = String::new();
+        if let Some(name) = self.name {
+            let _ = writeln!(metadata, "name={}", name);
+        }
+        let _ = writeln!(metadata, "program={}", self.program.to_string_lossy());
+        let _ = writeln!(metadata, "pid={}", self.pid);
+        if let Some(signal) = self.status.signal() {
+            let _ = writeln!(metadata, "signal={}", signal);
+        }
+        let _ = writeln!(metadata, "time={}", time.as_secs());
+        let _ = writeln!(metadata, "original_path={}", core.display());
+        metadata
+    }
+}
+
+// Moves the core dump of a crashed child into `dir`, along with a `.txt` file
+// describing the crash. None when it didn't dump core, or when the core was
+// handed to a program like systemd-coredump instead of written to a file.
+pub(crate) fn collect(dir: &Path, crash: &Crash<'_>) -> Option<PathBuf> {
+    if !crash.status.core_dumped() {
+        return None;
+    }
+    let core = locate(&core_pattern(), crash)?;
+    store(&core, dir, crash)
+}
+
+fn store(core: &Path, dir: &Path, crash: &Crash<'_>) -> Option<PathBuf> {
+    let file_name = format!("core.{}.{}", crash.name.unwrap_or("child"), crash.pid);
+    let target = dir.join(&file_name);
+    fs::create_dir_all(dir).ok()?;
+    if fs::rename(core, &target).is_err() {
+        // Across file systems
+        fs::copy(core, &target).ok()?;
+        let _ = fs::remove_file(core);
+    }
+    let _ = fs::write(dir.join(format!("{}.txt", file_name)), crash.metadata(core));
+    Some(target)
+}
+
+#[cfg(target_os = "linux")]
+fn core_pattern() -> String {
+    let pattern = fs::read_to_string("/proc/sys/kernel/core_pattern").unwrap_or_default();
+    let pattern = pattern.trim_end();
+    let uses_pid = fs::read_to_string("/proc/sys/kernel/core_uses_pid")
+        .is_ok_and(|uses_pid| uses_pid.trim() != "0");
+    match pattern {
+        "" => "core".into(),
+        pattern if uses_pid && !pattern.contains("%p") => format!("{}.%p", pattern),
+        pattern => pattern.into(),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn core_pattern() -> String {
+    "/cores/core.%P".into()
+}
+
+// Finds the core written for the crash, going by the pattern of core_pattern(5)
+fn locate(pattern: &str, crash: &Crash<'_>) -> Option<PathBuf> {
+    if pattern.starts_with('|') {
+        return None;
+    }
+    let path = crash.working_dir.join(expand(pattern, crash));
+    let (parent, name) = (path.parent()?, path.file_name()?.to_str()?);
+    if !name.contains(ANY) {
+        return Some(path).filter(|path| path.is_file());
+    }
+    // The newest of the files matching
+    fs::read_dir(parent)
+        .ok()?
+        .flatten()
+        .filter(|entry| entry.file_name().to_str().is_some_and(|n| matches(name, n)))
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .max()
+        .map(|(_, path)| path)
+}
+
+fn expand(pattern: &str, crash: &Crash<'_>) -> String {
+    let mut expanded = String::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            expanded.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('%') => expanded.push('%'),
+            Some('p' | 'P' | 'i' | 'I') => expanded.push_str(&crash.pid.to_string()),
+            Some('s') => match crash.status.signal() {
+                Some(signal) => expanded.push_str(&signal.to_string()),
+                None => expanded.push(ANY),
+            },
+            // The kernel truncates the name to 15 bytes
+            Some('e') => expanded.extend(crash.executable().chars().take(15)),
+            Some(_) => expanded.push(ANY),
+            None => {}
+        }
+    }
+    expanded
+}
+
+fn matches(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split(ANY);
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    let last = parts.pop();
+    for part in parts {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    last.is_none_or(|last| rest.ends_with(last))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+
+    fn crash(status: ExitStatus, working_dir: &Path) -> Crash<'_> {
+        Crash {
+            name: Some("web"),
+            program: OsStr::new("/usr/bin/a-rather-long-name"),
+            pid: 42,
+            status,
+            working_dir,
+        }
+    }
+
+    #[test]
+    fn it_locates_cores_by_pattern() {
+        let dir = env::temp_dir().join(format!("cores-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let status = ExitStatus::from_raw(libc::SIGSEGV | 0x80);
+        let crash = crash(status, &dir);
+        assert_eq!(
+            expand("core.%e.%p.%s.%%", &crash),
+            "core.a-rather-long-n.42.11.%"
+        );
+        assert_eq!(expand("core.%t", &crash), "core.\0");
+
+        assert_eq!(
+            locate("|/usr/lib/systemd/systemd-coredump %P", &crash),
+            None
+        );
+        assert_eq!(locate("core", &crash), None);
+        fs::write(dir.join("core.42.1700000000"), "").unwrap();
+        assert_eq!(
+            locate("core.%p.%t", &crash),
+            Some(dir.join("core.42.1700000000"))
+        );
+        assert!(!matches("core.\0.42", "core.1.43"));
+
+        let cores = dir.join("collected");
+        let core = dir.join("core.42.1700000000");
+        assert_eq!(
+            store(&core, &cores, &crash),
+            Some(cores.join("core.web.42"))
+        );
+        assert!(!core.exists());
+        let metadata = fs::read_to_string(cores.join("core.web.42.txt")).unwrap();
+        assert!(metadata
+            .starts_with("name=web\nprogram=/usr/bin/a-rather-long-name\npid=42\nsignal=11\n"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,150 @@
+use std::{
+    io::{self, Read, Write},
+    os::unix::net::UnixStream,
+    path::PathBuf,
+    time::Duration,
+};
+
+use serde_json::Value;
+
+use crate::{ChildInfo, HealthCheck, TestOutcome};
+
+// Asks the Docker or Podman engine for the health of a container, for
+// supervising `docker run`. Podman serves the same API on its own socket.
+pub struct DockerCheck {
+    name: String,
+    container: String,
+    socket: PathBuf,
+    timeout: Duration,
+}
+
+impl DockerCheck {
+    pub fn new(container: &str) -> Self {
+        Self {
+            name: format!("container {}", container),
+            container: container.into(),
+            socket: "/var/run/docker.sock".into(),
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    pub fn with_socket(self, socket: impl Into<PathBuf>) -> Self {
+        Self {
+            socket: socket.into(),
+            ..self
+        }
+    }
+
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+
+    fn inspect(&self) -> io::Result<(u16, Vec<u8>)> {
+        let mut stream = UnixStream::connect(&self.socket)?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+        // HTTP/1.0 keeps the engine from chunking the body and closes the
+        // connection once it is sent
+        write!(
+            stream,
+            "GET /containers/{}/json HTTP/1.0\r\nHost: docker\r\n\r\n",
+            self.container
+        )?;
+        let mut response = vec![];
+        stream.read_to_end(&mut response)?;
+
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid HTTP response");
+        let split = response
+            .windows(4)
+            .position(|window| window == b"\r\n\r\n")
+            .ok_or_else(invalid)?;
+        let status = std::str::from_utf8(&response[..split])
+            .ok()
+            .and_then(|head| head.split(' ').nth(1))
+            .and_then(|code| code.parse().ok())
+            .ok_or_else(invalid)?;
+        Ok((status, response[split + 4..].to_vec()))
+    }
+}
+
+// Containers without a health check are healthy as long as they run, and
+// the engine's own checks are given time while it reports them as starting
+fn outcome(state: &Value) -> TestOutcome {
+    if state["Running"] != true {
+        return TestOutcome::Unhealthy;
+    }
+    match state["Health"]["Status"].as_str() {
+        Some("starting") => TestOutcome::Ignore,
+        Some(status) => TestOutcome::from(status == "healthy"),
+        None => TestOutcome::Healthy,
+    }
+}
+
+impl HealthCheck for DockerCheck {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn check(&mut self, _: &mut ChildInfo) -> TestOutcome {
+        match self.inspect() {
+            Ok((200, body)) => serde_json::from_slice::<Value>(&body)
+                .map(|container| outcome(&container["State"]))
+                .unwrap_or(TestOutcome::Unhealthy),
+            _ => TestOutcome::Unhealthy,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env, fs,
+        io::{BufRead, BufReader},
+        os::unix::net::UnixListener,
+        process::{self, Command},
+        thread,
+        time::Instant,
+    };
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::output::OutputTail;
+
+    #[test]
+    fn it_maps_the_container_state() {
+        let health = |status: &str| json!({"Running": true, "Health": {"Status": status}});
+        assert_eq!(outcome(&health("healthy")), TestOutcome::Healthy);
+        assert_eq!(outcome(&health("starting")), TestOutcome::Ignore);
+        assert_eq!(outcome(&health("unhealthy")), TestOutcome::Unhealthy);
+        assert_eq!(outcome(&json!({"Running": true})), TestOutcome::Healthy);
+        assert_eq!(outcome(&json!({"Running": false})), TestOutcome::Unhealthy);
+    }
+
+    #[test]
+    fn it_queries_the_engine() {
+        let socket = env::temp_dir().join(format!("docker-check-{}.sock", process::id()));
+        let _ = fs::remove_file(&socket);
+        let listener = UnixListener::bind(&socket).unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = String::new();
+            BufReader::new(&stream).read_line(&mut request).unwrap();
+            let body = r#"{"State": {"Running": true, "Health": {"Status": "unhealthy"}}}"#;
+            write!(stream, "HTTP/1.0 200 OK\r\n\r\n{}", body).unwrap();
+            request
+        });
+
+        let mut check = DockerCheck::new("web").with_socket(&socket);
+        let mut child = Command::new("true").spawn().unwrap();
+        let output = OutputTail::default();
+        let mut info = ChildInfo::new(&mut child, Instant::now(), &output);
+        assert_eq!(check.check(&mut info), TestOutcome::Unhealthy);
+        assert_eq!(
+            server.join().unwrap(),
+            "GET /containers/web/json HTTP/1.0\r\n"
+        );
+        child.wait().unwrap();
+        fs::remove_file(socket).unwrap();
+    }
+}
@@ -0,0 +1,64 @@
+use std::time::Duration;
+
+use crate::BuilderError;
+
+// Parses durations like "30s", "250ms", "1.5m" or "1h 30m", with units from
+// milliseconds to days. A bare number is in seconds.
+pub fn parse_duration(value: &str) -> Result<Duration, BuilderError> {
+    let invalid = || BuilderError::InvalidDuration(value.to_string());
+    let mut rest = value.trim();
+    if rest.is_empty() {
+        return Err(invalid());
+    }
+    if let Ok(seconds) = rest.parse::<f64>() {
+        return Duration::try_from_secs_f64(seconds).map_err(|_| invalid());
+    }
+
+    let mut total = Duration::ZERO;
+    while !rest.is_empty() {
+        let split = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        let (number, after) = rest.split_at(split);
+        let number: f64 = number.parse().map_err(|_| invalid())?;
+        let after = after.trim_start();
+        let split = after
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(after.len());
+        let (unit, after) = after.split_at(split);
+        let seconds = match unit {
+            "ms" | "msec" | "millis" => number / 1000.0,
+            "s" | "sec" | "secs" | "second" | "seconds" => number,
+            "m" | "min" | "mins" | "minute" | "minutes" => number * 60.0,
+            "h" | "hr" | "hrs" | "hour" | "hours" => number * 3600.0,
+            "d" | "day" | "days" => number * 86400.0,
+            _ => return Err(invalid()),
+        };
+        total += Duration::try_from_secs_f64(seconds).map_err(|_| invalid())?;
+        rest = after.trim_start();
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_human_friendly_durations() {
+        assert_eq!(parse_duration("10s"), Ok(Duration::from_secs(10)));
+        assert_eq!(parse_duration("250ms"), Ok(Duration::from_millis(250)));
+        assert_eq!(parse_duration("1.5m"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("2"), Ok(Duration::from_secs(2)));
+        assert_eq!(parse_duration("1h 30min"), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("1d2h"), Ok(Duration::from_secs(93600)));
+        assert_eq!(parse_duration(" 5 seconds "), Ok(Duration::from_secs(5)));
+
+        for invalid in ["", "s", "10y", "1h 30", "-5s", "1..5s"] {
+            assert_eq!(
+                parse_duration(invalid),
+                Err(BuilderError::InvalidDuration(invalid.to_string()))
+            );
+        }
+    }
+}
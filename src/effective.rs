@@ -0,0 +1,53 @@
+use std::{env, ffi::OsString, fmt, path::PathBuf, process::Command};
+
+use crate::{backend, secrets::Secrets};
+
+// How the next child will be started, with secrets masked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EffectiveCommand {
+    // Found in the PATH like on spawn, else as given
+    pub program: PathBuf,
+    pub args: Vec<OsString>,
+    // The variables differing from our environment
+    pub env: Vec<(OsString, OsString)>,
+    pub working_dir: Option<PathBuf>,
+}
+
+impl EffectiveCommand {
+    pub(crate) fn new(command: &Command, secrets: &Secrets) -> Self {
+        let program =
+            backend::resolve(command).unwrap_or_else(|_| PathBuf::from(command.get_program()));
+        let env = command
+            .get_envs()
+            .filter_map(|(key, value)| Some((key, value?)))
+            .filter(|(key, value)| env::var_os(key).as_deref() != Some(*value))
+            .map(|(key, value)| (key.to_os_string(), secrets.redact_os(value)))
+            .collect();
+        Self {
+            program: secrets.redact_os(program.as_os_str()).into(),
+            args: command
+                .get_args()
+                .map(|arg| secrets.redact_os(arg))
+                .collect(),
+            env,
+            working_dir: command.get_current_dir().map(PathBuf::from),
+        }
+    }
+}
+
+// Like a shell command line, without quoting
+impl fmt::Display for EffectiveCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(working_dir) = &self.working_dir {
+            write!(f, "cd {} && ", working_dir.display())?;
+        }
+        for (key, value) in &self.env {
+            write!(f, "{}={} ", key.to_string_lossy(), value.to_string_lossy())?;
+        }
+        write!(f, "{}", self.program.display())?;
+        for arg in &self.args {
+            write!(f, " {}", arg.to_string_lossy())?;
+        }
+        Ok(())
+    }
+}
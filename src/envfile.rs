@@ -0,0 +1,132 @@
+use std::{fs, io, path::Path};
+
+// Reads `KEY=VALUE` lines, quoted like in systemd's `EnvironmentFile=` and
+// in .env files
+pub(crate) fn read(path: &Path) -> io::Result<Vec<(String, String)>> {
+    let contents = fs::read_to_string(path)?;
+    parse(&contents).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+// Empty lines and lines starting with `#` or `;` are skipped, and `export`
+// before a key is allowed. Values in single quotes are taken as they are,
+// while double quotes allow backslash escapes. Quoted values can span lines,
+// and unquoted ones continue on the next line after a trailing backslash.
+fn parse(contents: &str) -> Result<Vec<(String, String)>, String> {
+    let mut vars = vec![];
+    let mut lines = contents.lines().enumerate();
+    while let Some((number, line)) = lines.next() {
+        let invalid = |what: &str| format!("line {}: {}", number + 1, what);
+        let line = line.trim_start();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| invalid("expected KEY=VALUE"))?;
+        let key = key.trim();
+        if !is_valid_key(key) {
+            return Err(invalid(&format!("invalid variable name '{}'", key)));
+        }
+
+        let mut value = value.trim_start().to_string();
+        let value = loop {
+            if let Some(value) = unquote(&value) {
+                break value;
+            }
+            match lines.next() {
+                Some((_, next)) => {
+                    value.push('\n');
+                    value.push_str(next);
+                }
+                None => return Err(invalid("unterminated value")),
+            }
+        };
+        vars.push((key.to_string(), value));
+    }
+    Ok(vars)
+}
+
+fn is_valid_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// None while the value continues on the next line
+fn unquote(value: &str) -> Option<String> {
+    if let Some(quoted) = value.strip_prefix('\'') {
+        return quoted.find('\'').map(|end| quoted[..end].to_string());
+    }
+    if let Some(quoted) = value.strip_prefix('"') {
+        let mut unquoted = String::new();
+        let mut chars = quoted.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => return Some(unquoted),
+                '\\' => match chars.next()? {
+                    'n' => unquoted.push('\n'),
+                    '\n' => {}
+                    c @ ('"' | '\\' | '$' | '`') => unquoted.push(c),
+                    c => {
+                        unquoted.push('\\');
+                        unquoted.push(c);
+                    }
+                },
+                c => unquoted.push(c),
+            }
+        }
+        return None;
+    }
+    if value.ends_with('\\') {
+        return None;
+    }
+    Some(value.replace("\\\n", "").trim_end().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_quoted_values() {
+        let contents = "\
+# a comment
+; another one
+
+PLAIN = some value
+export EXPORTED=1
+SINGLE='$not \\expanded'
+DOUBLE=\"say \\\"hi\\\"\\n\"
+MULTI=\"one
+two\"
+CONTINUED=one \\
+two
+EMPTY=
+";
+        let vars = parse(contents).unwrap();
+        let expected = [
+            ("PLAIN", "some value"),
+            ("EXPORTED", "1"),
+            ("SINGLE", "$not \\expanded"),
+            ("DOUBLE", "say \"hi\"\n"),
+            ("MULTI", "one\ntwo"),
+            ("CONTINUED", "one two"),
+            ("EMPTY", ""),
+        ];
+        let expected: Vec<(String, String)> = expected
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        assert_eq!(vars, expected);
+
+        assert_eq!(parse("NOPE"), Err("line 1: expected KEY=VALUE".into()));
+        assert_eq!(
+            parse("\n1KEY=x"),
+            Err("line 2: invalid variable name '1KEY'".into())
+        );
+        assert_eq!(parse("KEY='open"), Err("line 1: unterminated value".into()));
+    }
+}
@@ -0,0 +1,180 @@
+use std::{error::Error, ffi::OsString, fmt, io, path::PathBuf, process::ExitStatus};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuilderError {
+    EmptyProcess,
+    EmptyFallbackCommand,
+    ZeroCheckInterval,
+    ZeroWatchdogInterval,
+    StdinWithWatchdog,
+    ZeroTestDeadline,
+    ZeroCircuitBreakerFailures,
+    ZeroMaxAttempts,
+    InvalidDuration(String),
+    InvalidMaintenanceWindow(String),
+    DuplicateTest(String),
+    DuplicateChild(String),
+    UnknownDependency { child: String, dependency: String },
+    DependencyCycle(Vec<String>),
+}
+
+impl fmt::Display for BuilderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EmptyProcess => write!(f, "process name is empty"),
+            Self::EmptyFallbackCommand => write!(f, "fallback command is empty"),
+            Self::ZeroCheckInterval => write!(f, "check interval must be greater than zero"),
+            Self::ZeroWatchdogInterval => {
+                write!(f, "stdio watchdog interval must be greater than zero")
+            }
+            Self::StdinWithWatchdog => {
+                write!(f, "stdin can't be set when using the stdio watchdog")
+            }
+            Self::ZeroTestDeadline => write!(f, "test deadline must be greater than zero"),
+            Self::ZeroMaxAttempts => write!(f, "job max attempts must be greater than zero"),
+            Self::InvalidDuration(value) => write!(f, "invalid duration '{}'", value),
+            Self::InvalidMaintenanceWindow(value) => {
+                write!(f, "invalid maintenance window '{}'", value)
+            }
+            Self::ZeroCircuitBreakerFailures => {
+                write!(f, "circuit breaker failures must be greater than zero")
+            }
+            Self::DuplicateTest(name) => write!(f, "test '{}' is registered more than once", name),
+            Self::DuplicateChild(name) => {
+                write!(f, "child '{}' is added to the group more than once", name)
+            }
+            Self::UnknownDependency { child, dependency } => {
+                write!(f, "child '{}' depends on unknown '{}'", child, dependency)
+            }
+            Self::DependencyCycle(names) => {
+                write!(f, "dependency cycle between {}", names.join(", "))
+            }
+        }
+    }
+}
+
+impl Error for BuilderError {}
+
+#[derive(Debug)]
+pub enum SupervisorError {
+    Builder(BuilderError),
+    Spawn {
+        name: Option<String>,
+        program: OsString,
+        args: Vec<OsString>,
+        source: io::Error,
+    },
+    EnvFile {
+        path: PathBuf,
+        source: io::Error,
+    },
+    ExitedOnSpawn {
+        name: Option<String>,
+        status: ExitStatus,
+    },
+    Unkillable {
+        name: Option<String>,
+        pid: u32,
+    },
+    JobObject(io::Error),
+    Cgroup(io::Error),
+    Subreaper(io::Error),
+    Watch(String),
+    StateFile(io::Error),
+    ControlSocket(io::Error),
+    StatusServer(io::Error),
+    PidFile(io::Error),
+    DynamicPort(io::Error),
+    ScratchDir(io::Error),
+    ResetDir(io::Error),
+    Start(io::Error),
+    JsonLog(io::Error),
+    Syslog(io::Error),
+    SignalForwarding(io::Error),
+}
+
+impl fmt::Display for SupervisorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Builder(e) => write!(f, "invalid configuration: {}", e),
+            Self::Spawn {
+                name,
+                program,
+                args,
+                source,
+            } => {
+                if let Some(name) = name {
+                    write!(f, "{}: ", name)?;
+                }
+                write!(f, "failed to start `{}", program.to_string_lossy())?;
+                for arg in args {
+                    write!(f, " {}", arg.to_string_lossy())?;
+                }
+                write!(f, "`: {}", source)
+            }
+            Self::EnvFile { path, source } => write!(
+                f,
+                "failed to read environment file {}: {}",
+                path.display(),
+                source
+            ),
+            Self::ExitedOnSpawn { name, status } => {
+                if let Some(name) = name {
+                    write!(f, "{}: ", name)?;
+                }
+                write!(f, "process exited right after starting ({})", status)
+            }
+            Self::Unkillable { name, pid } => {
+                if let Some(name) = name {
+                    write!(f, "{}: ", name)?;
+                }
+                write!(f, "process {} is still running after being killed", pid)
+            }
+            Self::JobObject(e) => write!(f, "failed to assign process to job object: {}", e),
+            Self::Cgroup(e) => write!(f, "failed to set up cgroup: {}", e),
+            Self::Subreaper(e) => write!(f, "failed to become a subreaper: {}", e),
+            Self::Watch(message) => write!(f, "failed to watch paths: {}", message),
+            Self::StateFile(e) => write!(f, "failed to access state file: {}", e),
+            Self::ControlSocket(e) => write!(f, "failed to open control socket: {}", e),
+            Self::StatusServer(e) => write!(f, "failed to start status server: {}", e),
+            Self::PidFile(e) => write!(f, "failed to write pid file: {}", e),
+            Self::DynamicPort(e) => write!(f, "failed to find a free port: {}", e),
+            Self::ScratchDir(e) => write!(f, "failed to create scratch directory: {}", e),
+            Self::ResetDir(e) => write!(f, "failed to reset directory: {}", e),
+            Self::Start(e) => write!(f, "failed to start: {}", e),
+            Self::JsonLog(e) => write!(f, "failed to open JSON log: {}", e),
+            Self::Syslog(e) => write!(f, "failed to connect to syslog: {}", e),
+            Self::SignalForwarding(e) => write!(f, "failed to forward signals: {}", e),
+        }
+    }
+}
+
+impl Error for SupervisorError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Builder(e) => Some(e),
+            Self::Spawn { source, .. } | Self::EnvFile { source, .. } => Some(source),
+            Self::JobObject(e)
+            | Self::Cgroup(e)
+            | Self::Subreaper(e)
+            | Self::StateFile(e)
+            | Self::ControlSocket(e)
+            | Self::StatusServer(e)
+            | Self::PidFile(e)
+            | Self::DynamicPort(e)
+            | Self::ScratchDir(e)
+            | Self::ResetDir(e)
+            | Self::Start(e)
+            | Self::JsonLog(e)
+            | Self::Syslog(e)
+            | Self::SignalForwarding(e) => Some(e),
+            Self::ExitedOnSpawn { .. } | Self::Unkillable { .. } | Self::Watch(_) => None,
+        }
+    }
+}
+
+impl From<BuilderError> for SupervisorError {
+    fn from(e: BuilderError) -> Self {
+        Self::Builder(e)
+    }
+}
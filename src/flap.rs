@@ -0,0 +1,97 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+// What to do about a child going back and forth between healthy and
+// unhealthy, besides reporting it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlapAction {
+    Notify,
+    // Waits this much longer than the backoff time before the next restart
+    ExtendBackoff(Duration),
+    // Pauses the supervisor at the next failure instead of restarting, until
+    // resumed through a handle
+    PauseRestarts,
+}
+
+// Notices a child flapping, i.e. switching between healthy and unhealthy more
+// than `max_transitions` times within a window, across restarts
+#[derive(Debug, Clone)]
+pub struct FlapDetector {
+    max_transitions: u32,
+    window: Duration,
+    action: FlapAction,
+    healthy: Option<bool>,
+    transitions: VecDeque<Instant>,
+}
+
+impl FlapDetector {
+    pub fn new(max_transitions: u32, window: Duration) -> Self {
+        Self {
+            max_transitions,
+            window,
+            action: FlapAction::Notify,
+            healthy: None,
+            transitions: VecDeque::new(),
+        }
+    }
+
+    pub fn with_action(self, action: FlapAction) -> Self {
+        Self { action, ..self }
+    }
+
+    pub(crate) fn action(&self) -> FlapAction {
+        self.action
+    }
+
+    // Records the health seen by a check, returning whether the child started
+    // flapping
+    pub(crate) fn record(&mut self, healthy: bool, now: Instant) -> bool {
+        if self
+            .healthy
+            .replace(healthy)
+            .is_none_or(|was| was == healthy)
+        {
+            return false;
+        }
+        while self
+            .transitions
+            .front()
+            .is_some_and(|&transition| now.duration_since(transition) > self.window)
+        {
+            self.transitions.pop_front();
+        }
+        self.transitions.push_back(now);
+
+        if self.transitions.len() > self.max_transitions as usize {
+            self.transitions.clear();
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_counts_transitions_within_the_window() {
+        let mut detector = FlapDetector::new(2, Duration::from_secs(10));
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert!(!detector.record(true, at(0)));
+        assert!(!detector.record(true, at(1)));
+        assert!(!detector.record(false, at(2)));
+        assert!(!detector.record(true, at(3)));
+        assert!(detector.record(false, at(4)));
+        // Transitions before it started flapping don't count anymore
+        assert!(!detector.record(true, at(5)));
+
+        assert!(!detector.record(false, at(20)));
+        assert!(!detector.record(true, at(31)));
+    }
+}
@@ -0,0 +1,785 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    child::RunningChild,
+    error::{BuilderError, SupervisorError},
+    handle::{Control, Request},
+    Local, RestartContext, RestartReason, RunOutcome, SupervisedProcess, SupervisorHandle,
+    TestOutcome, Threading,
+};
+#[cfg(unix)]
+use crate::{signals::SignalForwarder, SHUTDOWN_POLL_INTERVAL};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RestartStrategy {
+    #[default]
+    OneForOne,
+    OneForAll,
+    RestForOne,
+}
+
+pub struct SupervisorGroup<'a, S: Threading = Local> {
+    children: Vec<(String, SupervisedProcess<'a, S>)>,
+    strategy: RestartStrategy,
+    check_interval: Duration,
+    backoff_time: Duration,
+    cascade_restarts: bool,
+    shutdown_deadline: Option<Duration>,
+    #[cfg(unix)]
+    signal_forwarder: Option<SignalForwarder>,
+    control: Arc<Control>,
+}
+
+impl<'a, S: Threading> Default for SupervisorGroup<'a, S> {
+    fn default() -> Self {
+        Self {
+            children: vec![],
+            strategy: RestartStrategy::default(),
+            check_interval: Duration::from_secs(30),
+            backoff_time: Duration::from_secs(30),
+            cascade_restarts: false,
+            shutdown_deadline: None,
+            #[cfg(unix)]
+            signal_forwarder: None,
+            control: Arc::default(),
+        }
+    }
+}
+
+impl<'a, S: Threading> SupervisorGroup<'a, S> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_strategy(self, strategy: RestartStrategy) -> Self {
+        Self { strategy, ..self }
+    }
+
+    pub fn with_check_interval(self, check_interval: Duration) -> Self {
+        Self {
+            check_interval,
+            ..self
+        }
+    }
+
+    pub fn with_backoff_time(self, backoff_time: Duration) -> Self {
+        Self {
+            backoff_time,
+            ..self
+        }
+    }
+
+    pub fn with_cascading_restarts(self, cascade_restarts: bool) -> Self {
+        Self {
+            cascade_restarts,
+            ..self
+        }
+    }
+
+    // Bounds the time it takes to stop the whole group. The children still
+    // running once it passed are killed without waiting for them to exit.
+    pub fn with_shutdown_deadline(self, shutdown_deadline: Duration) -> Self {
+        Self {
+            shutdown_deadline: Some(shutdown_deadline),
+            ..self
+        }
+    }
+
+    // Relays these signals to every running child when this process receives
+    // them. After a SIGTERM, SIGINT or SIGQUIT the group stops once all of
+    // its children exited.
+    #[cfg(unix)]
+    pub fn with_signal_forwarding(self, signals: impl IntoIterator<Item = i32>) -> Self {
+        Self {
+            signal_forwarder: Some(SignalForwarder::new(signals.into_iter().collect())),
+            ..self
+        }
+    }
+
+    pub fn add(self, name: &str, process: SupervisedProcess<'a, S>) -> Self {
+        let process = match process.name {
+            Some(_) => process,
+            None => process.with_name(name),
+        };
+        process
+            .control
+            .streams
+            .forward_to(self.control.streams.clone(), name);
+        let mut children = self.children;
+        children.push((name.into(), process));
+
+        Self { children, ..self }
+    }
+
+    pub fn handle(&self) -> SupervisorHandle {
+        SupervisorHandle::new(self.control.clone())
+    }
+
+    pub fn build(self) -> Result<Self, BuilderError> {
+        self.validate()?;
+        Ok(self)
+    }
+
+    fn validate(&self) -> Result<(), BuilderError> {
+        for (index, (name, process)) in self.children.iter().enumerate() {
+            if self.children[..index]
+                .iter()
+                .any(|(other, _)| other == name)
+            {
+                return Err(BuilderError::DuplicateChild(name.clone()));
+            }
+            process.validate()?;
+        }
+        if self.check_interval.is_zero() {
+            return Err(BuilderError::ZeroCheckInterval);
+        }
+        self.start_order()?;
+        Ok(())
+    }
+
+    // Start order of the children, in which every child comes after its
+    // dependencies while keeping insertion order where possible.
+    fn start_order(&self) -> Result<Vec<usize>, BuilderError> {
+        for (name, process) in &self.children {
+            if let Some(unknown) = process
+                .dependencies
+                .iter()
+                .find(|dependency| !self.children.iter().any(|(other, _)| other == *dependency))
+            {
+                return Err(BuilderError::UnknownDependency {
+                    child: name.clone(),
+                    dependency: unknown.clone(),
+                });
+            }
+        }
+
+        let mut order: Vec<usize> = Vec::with_capacity(self.children.len());
+        let mut pending: Vec<usize> = (0..self.children.len()).collect();
+        while !pending.is_empty() {
+            let next = pending.iter().position(|&index| {
+                self.children[index]
+                    .1
+                    .dependencies
+                    .iter()
+                    .all(|dependency| {
+                        order
+                            .iter()
+                            .any(|&started| self.children[started].0 == *dependency)
+                    })
+            });
+            match next {
+                Some(position) => order.push(pending.remove(position)),
+                None => {
+                    let names = pending
+                        .iter()
+                        .map(|&index| self.children[index].0.clone())
+                        .collect();
+                    return Err(BuilderError::DependencyCycle(names));
+                }
+            }
+        }
+        Ok(order)
+    }
+
+    // Reorders the children in start order. Returns the dependencies of each
+    // child as indexes in the new order.
+    fn sort_by_dependencies(&mut self) -> Result<Vec<Vec<usize>>, BuilderError> {
+        let order = self.start_order()?;
+        let mut children: Vec<_> = std::mem::take(&mut self.children)
+            .into_iter()
+            .map(Some)
+            .collect();
+        self.children = order
+            .into_iter()
+            .filter_map(|index| children[index].take())
+            .collect();
+
+        Ok(self
+            .children
+            .iter()
+            .map(|(_, process)| {
+                process
+                    .dependencies
+                    .iter()
+                    .filter_map(|dependency| {
+                        self.children
+                            .iter()
+                            .position(|(name, _)| name == dependency)
+                    })
+                    .collect()
+            })
+            .collect())
+    }
+
+    // Indexes of the children to restart, in start order, given the ones
+    // whose tests failed.
+    fn affected(&self, failed: &[usize], dependencies: &[Vec<usize>]) -> Vec<usize> {
+        let mut affected = vec![false; self.children.len()];
+        match self.strategy {
+            RestartStrategy::OneForOne => failed.iter().for_each(|&index| affected[index] = true),
+            RestartStrategy::OneForAll => affected.fill(true),
+            RestartStrategy::RestForOne => affected[failed[0]..].fill(true),
+        }
+
+        // Dependents always come after their dependencies, so a single pass
+        // is enough to follow transitive dependencies
+        if self.cascade_restarts {
+            for index in 0..affected.len() {
+                if dependencies[index]
+                    .iter()
+                    .any(|&dependency| affected[dependency])
+                {
+                    affected[index] = true;
+                }
+            }
+        }
+
+        (0..affected.len())
+            .filter(|&index| affected[index])
+            .collect()
+    }
+
+    // Children are always stopped in reverse start order. The ones that were
+    // running keep the context of their restart until they are started again.
+    // Every child is stopped even when one of them can't be killed.
+    fn stop(
+        &mut self,
+        running: &mut [Option<RunningChild>],
+        restarting: &mut [Option<RestartContext>],
+        indexes: &[usize],
+        failed: &[usize],
+    ) -> Result<(), SupervisorError> {
+        let mut result = Ok(());
+        for &index in indexes.iter().rev() {
+            if let Some(mut child) = running[index].take() {
+                let process = &mut self.children[index].1;
+                let failed_test = if failed.contains(&index) {
+                    process.last_failure.clone()
+                } else {
+                    None
+                };
+                let mut context = process.restart_context(&child, failed_test);
+                if !failed.is_empty() {
+                    context.backoff = self.backoff_time;
+                    if !failed.contains(&index) {
+                        context.reason = RestartReason::SiblingFailed;
+                    }
+                }
+                match process.terminate(&mut child) {
+                    Ok(status) => context.exit_status = status,
+                    Err(e) => result = result.and(Err(e)),
+                }
+                restarting[index] = Some(context);
+                process.publish_started(None);
+            }
+        }
+        result
+    }
+
+    // Dependents are stopped before their dependencies, all within the
+    // shutdown deadline
+    fn stop_all(&mut self, running: &mut [Option<RunningChild>]) -> Result<(), SupervisorError> {
+        let deadline = self
+            .shutdown_deadline
+            .map(|shutdown_deadline| Instant::now() + shutdown_deadline);
+        let mut result = Ok(());
+        for ((_, process), child) in self.children.iter_mut().zip(running).rev() {
+            if let Some(mut child) = child.take() {
+                let grace = match deadline {
+                    Some(deadline) => deadline.saturating_duration_since(Instant::now()),
+                    None => Duration::MAX,
+                };
+                result = result.and(process.terminate_within(&mut child, grace).map(drop));
+                process.publish_started(None);
+            }
+        }
+        result
+    }
+
+    // Spawns every stopped child whose dependencies passed their tests since
+    // they were last started.
+    fn start_ready(
+        &mut self,
+        running: &mut [Option<RunningChild>],
+        ready: &[bool],
+        restarting: &mut [Option<RestartContext>],
+        dependencies: &[Vec<usize>],
+    ) -> Result<(), SupervisorError> {
+        for (index, (_, process)) in self.children.iter_mut().enumerate() {
+            if running[index].is_some() || !dependencies[index].iter().all(|&d| ready[d]) {
+                continue;
+            }
+            let child = process.spawn()?;
+            process.notify_spawn(&child);
+            process.publish_started(Some(&child));
+            running[index] = Some(child);
+            if let Some(context) = restarting[index].take() {
+                process.notify_restart(&context);
+            }
+        }
+        Ok(())
+    }
+
+    // Waits for a request, relaying the signals received meanwhile to the
+    // running children
+    #[cfg(unix)]
+    fn wait(
+        &mut self,
+        duration: Duration,
+        running: &mut [Option<RunningChild>],
+    ) -> Option<Request> {
+        let Some(forwarder) = &mut self.signal_forwarder else {
+            return self.control.wait_timeout(duration);
+        };
+        let deadline = Instant::now() + duration;
+        let mut terminating = false;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let request = match terminating {
+                true => self.control.wait_timeout(SHUTDOWN_POLL_INTERVAL),
+                false => self
+                    .control
+                    .wait_timeout(SHUTDOWN_POLL_INTERVAL.min(remaining)),
+            };
+            terminating |= forwarder.forward(|signal| {
+                running
+                    .iter_mut()
+                    .flatten()
+                    .for_each(|child| child.signal(signal))
+            });
+            if request == Some(Request::Stop)
+                || terminating && running.iter_mut().flatten().all(RunningChild::has_exited)
+            {
+                return Some(Request::Stop);
+            }
+            if !terminating && (request.is_some() || Instant::now() >= deadline) {
+                return request;
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn wait(
+        &mut self,
+        duration: Duration,
+        _running: &mut [Option<RunningChild>],
+    ) -> Option<Request> {
+        self.control.wait_timeout(duration)
+    }
+
+    // Only a handle or a forwarded terminating signal stop a group
+    fn stopped(&self) -> RunOutcome {
+        match self.control.is_stopped() {
+            true => RunOutcome::StoppedByHandle,
+            false => RunOutcome::StoppedBySignal,
+        }
+    }
+
+    pub fn run(&mut self) -> Result<RunOutcome, SupervisorError> {
+        self.control.update_stats(|stats| stats.finished = false);
+        let outcome = self.supervise();
+        self.control.update_stats(|stats| stats.finished = true);
+        self.control.streams.close();
+        outcome
+    }
+
+    fn supervise(&mut self) -> Result<RunOutcome, SupervisorError> {
+        self.validate()?;
+        #[cfg(unix)]
+        let _forwarded_handlers = match &mut self.signal_forwarder {
+            Some(forwarder) => Some(
+                forwarder
+                    .install()
+                    .map_err(SupervisorError::SignalForwarding)?,
+            ),
+            None => None,
+        };
+        let dependencies = self.sort_by_dependencies()?;
+        for (_, process) in &mut self.children {
+            process.preflight()?;
+            process.allocate_port()?;
+        }
+        let count = self.children.len();
+        let mut running: Vec<Option<RunningChild>> = (0..count).map(|_| None).collect();
+        let mut ready = vec![false; count];
+        let mut restarting: Vec<Option<RestartContext>> = (0..count).map(|_| None).collect();
+
+        loop {
+            let all_ready = ready.iter().all(|&ready| ready);
+            self.control.update_stats(|stats| stats.ready = all_ready);
+            if let Err(e) = self.start_ready(&mut running, &ready, &mut restarting, &dependencies) {
+                let _ = self.stop_all(&mut running);
+                return Err(e);
+            }
+
+            let affected = match self.wait(self.check_interval, &mut running) {
+                Some(Request::Stop) => {
+                    self.stop_all(&mut running)?;
+                    return Ok(self.stopped());
+                }
+                Some(Request::Restart) => {
+                    let all: Vec<usize> = (0..count).collect();
+                    self.stop(&mut running, &mut restarting, &all, &[])?;
+                    all
+                }
+                // Only the group's own timing applies to a group
+                Some(Request::Reload) => {
+                    if let Some(config) = self.control.take_reload() {
+                        self.check_interval = config.check_interval.unwrap_or(self.check_interval);
+                        self.backoff_time = config.backoff_time.unwrap_or(self.backoff_time);
+                    }
+                    vec![]
+                }
+                Some(Request::Pause) => {
+                    self.stop_all(&mut running)?;
+                    while self.control.wait_paused(self.check_interval) {
+                        if self.control.is_stopped() {
+                            return Ok(RunOutcome::StoppedByHandle);
+                        }
+                    }
+                    (0..count).collect()
+                }
+                None => {
+                    let mut failed = vec![];
+                    let mut fatal = vec![];
+                    for (index, ((_, process), child)) in
+                        self.children.iter_mut().zip(running.iter_mut()).enumerate()
+                    {
+                        if let Some(child) = child {
+                            match process.run_tests(child, true) {
+                                TestOutcome::Fatal => {
+                                    failed.push(index);
+                                    fatal.push(index);
+                                }
+                                outcome if process.is_failure_tolerated(child, outcome) => {}
+                                outcome if outcome.is_failure() => failed.push(index),
+                                _ => ready[index] = process.is_ready(child),
+                            }
+                        }
+                    }
+                    if failed.is_empty() {
+                        continue;
+                    }
+
+                    // Like an OTP supervisor, the whole group gives up as soon
+                    // as any of its children runs out of restarts
+                    for &index in &failed {
+                        let process = &mut self.children[index].1;
+                        if fatal.contains(&index) || !process.should_restart() {
+                            if let Some(child) = &running[index] {
+                                let context =
+                                    process.restart_context(child, process.last_failure.clone());
+                                process.notify_no_restart(&context, !fatal.contains(&index));
+                            }
+                            self.stop_all(&mut running)?;
+                            return Ok(match fatal.contains(&index) {
+                                true => RunOutcome::FatalTest,
+                                false => RunOutcome::RestartsExhausted,
+                            });
+                        }
+                    }
+
+                    let affected = self.affected(&failed, &dependencies);
+                    self.stop(&mut running, &mut restarting, &affected, &failed)?;
+                    // Signals are still relayed to the children left running
+                    if self.wait(self.backoff_time, &mut running) == Some(Request::Stop) {
+                        self.stop_all(&mut running)?;
+                        return Ok(self.stopped());
+                    }
+                    affected
+                }
+            };
+
+            for index in affected {
+                ready[index] = false;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::RefCell,
+        sync::{Arc, Mutex},
+        thread,
+    };
+
+    use super::*;
+    use crate::ChildInfo;
+
+    fn fails_on(check: usize) -> Box<dyn FnMut(&mut ChildInfo) -> bool> {
+        let mut checks = 0;
+        Box::from(move |_: &mut ChildInfo| {
+            checks += 1;
+            checks != check
+        })
+    }
+
+    fn restart_counts(strategy: RestartStrategy) -> [i32; 3] {
+        let restart_counts = RefCell::new([0; 3]);
+        let restart_a = |_: &RestartContext| restart_counts.borrow_mut()[0] += 1;
+        let restart_b = |_: &RestartContext| restart_counts.borrow_mut()[1] += 1;
+        let restart_c = |_: &RestartContext| restart_counts.borrow_mut()[2] += 1;
+
+        let sleeper = || SupervisedProcess::new("sleep").with_args(vec!["5"]);
+        let mut group = SupervisorGroup::new()
+            .with_strategy(strategy)
+            .with_check_interval(Duration::from_millis(10))
+            .with_backoff_time(Duration::from_millis(10))
+            .add(
+                "a",
+                sleeper()
+                    .add_test("always true", Box::from(|_: &mut ChildInfo| true))
+                    .on_restart(restart_a),
+            )
+            .add(
+                "b",
+                sleeper()
+                    .add_test("fails once", fails_on(1))
+                    .on_restart(restart_b),
+            )
+            .add(
+                "c",
+                sleeper()
+                    .add_test("always true", Box::from(|_: &mut ChildInfo| true))
+                    .on_restart(restart_c),
+            );
+        let handle = group.handle();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            handle.stop();
+        });
+
+        assert_eq!(group.run().unwrap(), RunOutcome::StoppedByHandle);
+        drop(group);
+        restart_counts.into_inner()
+    }
+
+    #[test]
+    fn it_restarts_only_the_failed_child_with_one_for_one() {
+        assert_eq!(restart_counts(RestartStrategy::OneForOne), [0, 1, 0]);
+    }
+
+    #[test]
+    fn it_restarts_all_children_with_one_for_all() {
+        assert_eq!(restart_counts(RestartStrategy::OneForAll), [1, 1, 1]);
+    }
+
+    #[test]
+    fn it_restarts_later_children_with_rest_for_one() {
+        assert_eq!(restart_counts(RestartStrategy::RestForOne), [0, 1, 1]);
+    }
+
+    #[test]
+    fn it_stops_when_a_child_runs_out_of_restarts() {
+        let mut group = SupervisorGroup::new()
+            .with_check_interval(Duration::from_millis(1))
+            .with_backoff_time(Duration::from_millis(1))
+            .add(
+                "a",
+                SupervisedProcess::new("sleep")
+                    .with_args(vec!["5"])
+                    .add_test("always false", Box::from(|_: &mut ChildInfo| false))
+                    .with_restart_times(1),
+            );
+        assert_eq!(group.run().unwrap(), RunOutcome::RestartsExhausted);
+    }
+
+    #[test]
+    fn group_handles_stream_the_stdout_of_every_member() {
+        let mut group = SupervisorGroup::new()
+            .with_check_interval(Duration::from_millis(10))
+            .with_backoff_time(Duration::from_millis(1))
+            .add(
+                "a",
+                SupervisedProcess::new("sh")
+                    .with_args(vec!["-c", "echo out; sleep 0.1"])
+                    .add_test("still running", |child: &mut ChildInfo| {
+                        matches!(child.try_wait(), Ok(None))
+                    })
+                    .with_output_tail(0)
+                    .with_restart_times(0),
+            );
+        let lines = group.handle().stdout_lines();
+
+        assert_eq!(group.run().unwrap(), RunOutcome::RestartsExhausted);
+        let lines: Vec<(Option<String>, String)> =
+            lines.map(|output| (output.member, output.line)).collect();
+        assert_eq!(lines, vec![(Some("a".to_string()), "out".to_string())]);
+    }
+
+    fn logging(
+        log: &Arc<Mutex<Vec<&'static str>>>,
+        name: &'static str,
+    ) -> Box<dyn FnMut(&mut ChildInfo) -> bool> {
+        let log = log.clone();
+        Box::from(move |_: &mut ChildInfo| {
+            log.lock().unwrap().push(name);
+            true
+        })
+    }
+
+    #[test]
+    fn it_starts_dependents_once_dependencies_are_ready() {
+        let log = Arc::new(Mutex::new(vec![]));
+        let mut group = SupervisorGroup::new()
+            .with_check_interval(Duration::from_millis(10))
+            .add(
+                "api",
+                SupervisedProcess::new("sleep")
+                    .with_args(vec!["5"])
+                    .depends_on("db")
+                    .add_test("api", logging(&log, "api")),
+            )
+            .add(
+                "db",
+                SupervisedProcess::new("sleep")
+                    .with_args(vec!["5"])
+                    .add_test("db", logging(&log, "db")),
+            );
+        let handle = group.handle();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            handle.stop();
+        });
+
+        assert!(group.run().is_ok());
+        let log = log.lock().unwrap();
+        assert_eq!(log[0], "db");
+        assert!(log.contains(&"api"));
+    }
+
+    #[test]
+    fn it_stops_dependents_first_within_the_shutdown_deadline() {
+        let exits = RefCell::new(vec![]);
+        let stubborn = || {
+            SupervisedProcess::new("sh")
+                .with_args(vec!["-c", "trap '' TERM; sleep 5 & wait"])
+                .add_test("always true", |_: &mut ChildInfo| true)
+        };
+        let mut group = SupervisorGroup::new()
+            .with_check_interval(Duration::from_millis(10))
+            .with_shutdown_deadline(Duration::from_millis(200))
+            .add(
+                "api",
+                stubborn()
+                    .depends_on("db")
+                    .on_exit(|_| exits.borrow_mut().push("api")),
+            )
+            .add("db", stubborn().on_exit(|_| exits.borrow_mut().push("db")));
+        let handle = group.handle();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(300));
+            handle.stop();
+        });
+
+        let started = Instant::now();
+        assert!(group.run().is_ok());
+        assert!(started.elapsed() < Duration::from_secs(1));
+        drop(group);
+        assert_eq!(exits.into_inner(), vec!["api", "db"]);
+    }
+
+    #[test]
+    fn it_rejects_unknown_dependencies() {
+        let group =
+            SupervisorGroup::new().add("api", SupervisedProcess::new("sleep").depends_on("db"));
+        assert_eq!(
+            group.build().err(),
+            Some(BuilderError::UnknownDependency {
+                child: "api".to_string(),
+                dependency: "db".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn it_rejects_dependency_cycles() {
+        let group = SupervisorGroup::new()
+            .add("a", SupervisedProcess::new("sleep").depends_on("b"))
+            .add("b", SupervisedProcess::new("sleep").depends_on("a"));
+        assert_eq!(
+            group.build().err(),
+            Some(BuilderError::DependencyCycle(vec![
+                "a".to_string(),
+                "b".to_string()
+            ]))
+        );
+    }
+
+    fn dependent_restarts(cascade_restarts: bool) -> i32 {
+        let restart_count = RefCell::new(0);
+        let restart_fn = |_: &RestartContext| *restart_count.borrow_mut() += 1;
+
+        let mut group = SupervisorGroup::new()
+            .with_check_interval(Duration::from_millis(10))
+            .with_backoff_time(Duration::from_millis(10))
+            .with_cascading_restarts(cascade_restarts)
+            .add(
+                "db",
+                SupervisedProcess::new("sleep")
+                    .with_args(vec!["5"])
+                    .add_test("fails once", fails_on(2)),
+            )
+            .add(
+                "api",
+                SupervisedProcess::new("sleep")
+                    .with_args(vec!["5"])
+                    .depends_on("db")
+                    .add_test("always true", Box::from(|_: &mut ChildInfo| true))
+                    .on_restart(restart_fn),
+            );
+        let handle = group.handle();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            handle.stop();
+        });
+
+        assert!(group.run().is_ok());
+        drop(group);
+        restart_count.into_inner()
+    }
+
+    #[test]
+    fn it_cascades_restarts_to_dependents() {
+        assert_eq!(dependent_restarts(true), 1);
+        assert_eq!(dependent_restarts(false), 0);
+    }
+
+    #[test]
+    fn it_rejects_duplicate_children() {
+        let group = SupervisorGroup::new()
+            .add("a", SupervisedProcess::new("sleep"))
+            .add("a", SupervisedProcess::new("sleep"));
+        assert_eq!(
+            group.build().err(),
+            Some(BuilderError::DuplicateChild("a".to_string()))
+        );
+    }
+
+    #[test]
+    fn it_names_children_after_their_group_name() {
+        let group = SupervisorGroup::new()
+            .add("a", SupervisedProcess::new("sleep"))
+            .add("b", SupervisedProcess::new("sleep").with_name("worker-b"));
+        assert_eq!(group.children[0].1.name(), Some("a"));
+        assert_eq!(group.children[1].1.name(), Some("worker-b"));
+    }
+
+    #[test]
+    fn it_stops_on_fatal_test_outcome() {
+        let mut group = SupervisorGroup::new()
+            .with_check_interval(Duration::from_millis(1))
+            .add(
+                "a",
+                SupervisedProcess::new("sleep")
+                    .with_args(vec!["5"])
+                    .add_test("fatal", |_: &mut ChildInfo| TestOutcome::Fatal),
+            );
+        assert_eq!(group.run().unwrap(), RunOutcome::FatalTest);
+    }
+}
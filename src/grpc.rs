@@ -0,0 +1,98 @@
+use std::time::Duration;
+
+use tokio::runtime::{Builder, Runtime};
+use tonic::transport::Endpoint;
+use tonic_health::pb::{
+    health_check_response::ServingStatus, health_client::HealthClient, HealthCheckRequest,
+};
+
+use crate::{ChildInfo, HealthCheck, TestOutcome};
+
+// Probes a service with the standard gRPC Health Checking Protocol
+// (grpc.health.v1). An empty service asks about the server as a whole.
+pub struct GrpcCheck {
+    name: String,
+    endpoint: String,
+    service: String,
+    timeout: Duration,
+    runtime: Option<Runtime>,
+}
+
+impl GrpcCheck {
+    pub fn new(endpoint: &str) -> Self {
+        Self {
+            name: format!("grpc {}", endpoint),
+            endpoint: endpoint.into(),
+            service: String::new(),
+            timeout: Duration::from_secs(5),
+            runtime: None,
+        }
+    }
+
+    pub fn with_service(self, service: &str) -> Self {
+        Self {
+            service: service.into(),
+            ..self
+        }
+    }
+
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+
+    fn status(&mut self) -> Option<ServingStatus> {
+        let endpoint = Endpoint::from_shared(self.endpoint.clone())
+            .ok()?
+            .connect_timeout(self.timeout)
+            .timeout(self.timeout);
+        let request = HealthCheckRequest {
+            service: self.service.clone(),
+        };
+        // The runtime is kept between checks, the connection is not
+        if self.runtime.is_none() {
+            self.runtime = Some(Builder::new_current_thread().enable_all().build().ok()?);
+        }
+        self.runtime.as_ref()?.block_on(async {
+            let channel = endpoint.connect().await.ok()?;
+            let response = HealthClient::new(channel).check(request).await.ok()?;
+            Some(response.into_inner().status())
+        })
+    }
+}
+
+impl HealthCheck for GrpcCheck {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn check(&mut self, _: &mut ChildInfo) -> TestOutcome {
+        TestOutcome::from(self.status() == Some(ServingStatus::Serving))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::TcpListener, process::Command, time::Instant};
+
+    use super::*;
+    use crate::output::OutputTail;
+
+    #[test]
+    fn it_fails_when_nothing_answers() {
+        // Grab a free port and release it, so nothing listens there
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let mut check = GrpcCheck::new(&format!("http://127.0.0.1:{}", port))
+            .with_timeout(Duration::from_millis(200));
+        assert_eq!(check.name(), format!("grpc http://127.0.0.1:{}", port));
+
+        let mut child = Command::new("true").spawn().unwrap();
+        let output = OutputTail::default();
+        let mut info = ChildInfo::new(&mut child, Instant::now(), &output);
+        assert_eq!(check.check(&mut info), TestOutcome::Unhealthy);
+        child.wait().unwrap();
+    }
+}
@@ -0,0 +1,417 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Condvar, Mutex,
+    },
+    time::{Duration, Instant},
+};
+#[cfg(feature = "tokio")]
+use std::{
+    future::{self, Future},
+    task::{Poll, Waker},
+};
+
+use crate::{
+    output::{LineStreams, OutputTail},
+    BuilderError, Clock, OutputLine, ReloadConfig, RestartRecord, TestStats,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Request {
+    Stop,
+    Restart,
+    // Stop the child but keep supervising, until resumed
+    Pause,
+    Reload,
+}
+
+// Counters published by the supervisor for the handles to read
+#[derive(Default, Clone)]
+pub(crate) struct Stats {
+    pub(crate) restarts: u64,
+    pub(crate) restarts_remaining: Option<u64>,
+    pub(crate) started: Option<Instant>,
+    pub(crate) pid: Option<u32>,
+    pub(crate) port: Option<u16>,
+    pub(crate) last_failure: Option<String>,
+    pub(crate) tests: Vec<TestStats>,
+    // Names of the tests a reload can't replace
+    pub(crate) fixed_tests: Vec<String>,
+    pub(crate) output: OutputTail,
+    pub(crate) history: VecDeque<RestartRecord>,
+    // Whether the running child passed its tests since it was started
+    pub(crate) ready: bool,
+    // Whether `run` returned, so that no child is left running
+    pub(crate) finished: bool,
+}
+
+#[derive(Default)]
+pub(crate) struct Control {
+    request: Mutex<Option<Request>>,
+    condvar: Condvar,
+    paused: AtomicBool,
+    reload: Mutex<Option<ReloadConfig>>,
+    stats: Mutex<Stats>,
+    // The clock of the supervisor, which the uptime is measured with
+    clock: Mutex<Option<Arc<dyn Clock>>>,
+    pub(crate) streams: LineStreams,
+    #[cfg(feature = "tokio")]
+    wakers: Mutex<Vec<Waker>>,
+}
+
+impl Control {
+    fn send(&self, request: Request) {
+        let mut pending = self.request.lock().unwrap();
+        // A pending stop always wins over a restart
+        if *pending != Some(Request::Stop) {
+            *pending = Some(request);
+        }
+        self.condvar.notify_all();
+    }
+
+    pub(crate) fn set_paused(&self, paused: bool) {
+        let _pending = self.request.lock().unwrap();
+        self.paused.store(paused, Ordering::SeqCst);
+        self.condvar.notify_all();
+    }
+
+    fn send_reload(&self, config: ReloadConfig) {
+        let _pending = self.request.lock().unwrap();
+        *self.reload.lock().unwrap() = Some(config);
+        self.condvar.notify_all();
+    }
+
+    pub(crate) fn take_reload(&self) -> Option<ReloadConfig> {
+        self.reload.lock().unwrap().take()
+    }
+
+    pub(crate) fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    // Blocks until resumed, a stop request arrives or `timeout` elapses.
+    // Returns whether the supervisor is still paused.
+    pub(crate) fn wait_paused(&self, timeout: Duration) -> bool {
+        let pending = self.request.lock().unwrap();
+        let (_pending, _) = self
+            .condvar
+            .wait_timeout_while(pending, timeout, |pending| {
+                self.is_paused() && *pending != Some(Request::Stop)
+            })
+            .unwrap();
+        self.is_paused()
+    }
+
+    pub(crate) fn is_stopped(&self) -> bool {
+        *self.request.lock().unwrap() == Some(Request::Stop)
+    }
+
+    // Blocks until a request arrives or `timeout` elapses. Stop requests and
+    // pausing are sticky, restart requests are consumed by the waiter that
+    // receives them and reloads by the one taking the new configuration.
+    pub(crate) fn wait_timeout(&self, timeout: Duration) -> Option<Request> {
+        let deadline = Instant::now() + timeout;
+        let mut pending = self.request.lock().unwrap();
+        loop {
+            match *pending {
+                Some(Request::Stop) => return Some(Request::Stop),
+                Some(Request::Restart) => return pending.take(),
+                _ if self.reload.lock().unwrap().is_some() => return Some(Request::Reload),
+                _ if self.is_paused() => return Some(Request::Pause),
+                _ => {}
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return None;
+            }
+            pending = self
+                .condvar
+                .wait_timeout(pending, deadline - now)
+                .unwrap()
+                .0;
+        }
+    }
+
+    pub(crate) fn update_stats(&self, update: impl FnOnce(&mut Stats)) {
+        update(&mut self.stats.lock().unwrap());
+        #[cfg(feature = "tokio")]
+        for waker in self.wakers.lock().unwrap().drain(..) {
+            waker.wake();
+        }
+    }
+
+    // Resolves once the published stats satisfy `done`. The waker is
+    // registered before the stats are unlocked, so no update is missed.
+    #[cfg(feature = "tokio")]
+    fn until(&self, done: fn(&Stats) -> Option<bool>) -> impl Future<Output = bool> + '_ {
+        future::poll_fn(move |cx| {
+            let stats = self.stats.lock().unwrap();
+            match done(&stats) {
+                Some(value) => Poll::Ready(value),
+                None => {
+                    self.wakers.lock().unwrap().push(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        })
+    }
+
+    pub(crate) fn stats(&self) -> Stats {
+        self.stats.lock().unwrap().clone()
+    }
+
+    pub(crate) fn set_clock(&self, clock: Arc<dyn Clock>) {
+        *self.clock.lock().unwrap() = Some(clock);
+    }
+
+    fn now(&self) -> Instant {
+        match &*self.clock.lock().unwrap() {
+            Some(clock) => clock.now(),
+            None => Instant::now(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SupervisorHandle {
+    control: Arc<Control>,
+}
+
+impl SupervisorHandle {
+    pub(crate) fn new(control: Arc<Control>) -> Self {
+        Self { control }
+    }
+
+    pub fn stop(&self) {
+        self.control.send(Request::Stop);
+    }
+
+    pub fn restart(&self) {
+        self.control.send(Request::Restart);
+    }
+
+    // Stops the child without stopping the supervisor, which starts it again
+    // once resumed
+    pub fn pause(&self) {
+        self.control.set_paused(true);
+    }
+
+    pub fn resume(&self) {
+        self.control.set_paused(false);
+    }
+
+    // Applies the new settings to the running supervisor, restarting the
+    // child if it has to be started differently
+    pub fn reload(&self, config: ReloadConfig) -> Result<(), BuilderError> {
+        config.validate(&self.control.stats().fixed_tests)?;
+        self.control.send_reload(config);
+        Ok(())
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.control.is_paused()
+    }
+
+    // The port given to the child by `with_dynamic_port`, once allocated
+    pub fn port(&self) -> Option<u16> {
+        self.control.stats().port
+    }
+
+    // None when no child is running
+    pub fn pid(&self) -> Option<u32> {
+        self.control.stats().pid
+    }
+
+    // The last lines of output of the running child, when capturing it with
+    // `with_output_tail`
+    pub fn output_tail(&self) -> Vec<String> {
+        self.control.stats().output.snapshot()
+    }
+
+    // The lines the children write to stdout from now on, when capturing it
+    // with `with_output_tail`. Ends once the supervisor returned. Those of a
+    // group come from all its members. Lines are dropped rather than queued
+    // without bounds when the iterator isn't kept up with.
+    pub fn stdout_lines(&self) -> impl Iterator<Item = OutputLine> {
+        self.control.streams.subscribe().into_iter()
+    }
+
+    // Lines `stdout_lines` iterators missed, for not keeping up with them
+    pub fn dropped_stdout_lines(&self) -> u64 {
+        self.control.streams.dropped()
+    }
+
+    // None when the number of restarts is unlimited
+    pub fn restarts_remaining(&self) -> Option<u64> {
+        self.control.stats().restarts_remaining
+    }
+
+    pub fn total_restarts(&self) -> u64 {
+        self.control.stats().restarts
+    }
+
+    // None when no child is running
+    pub fn uptime(&self) -> Option<Duration> {
+        let now = self.control.now();
+        self.control
+            .stats()
+            .started
+            .map(|started| now.saturating_duration_since(started))
+    }
+
+    pub fn last_failure(&self) -> Option<String> {
+        self.control.stats().last_failure
+    }
+
+    pub fn test_stats(&self) -> Vec<TestStats> {
+        self.control.stats().tests
+    }
+
+    // The last restarts, oldest first
+    pub fn history(&self) -> Vec<RestartRecord> {
+        self.control.stats().history.into()
+    }
+
+    // Whether a child is running, with none of its tests failing lately
+    pub fn is_healthy(&self) -> bool {
+        let stats = self.control.stats();
+        !stats.finished
+            && !self.is_paused()
+            && stats.pid.is_some()
+            && stats
+                .tests
+                .iter()
+                .all(|test| !test.last_outcome.is_failure())
+    }
+
+    // Whether the running child passed its tests since it was started. A
+    // group is ready once all of its children are.
+    pub fn is_ready(&self) -> bool {
+        self.control.stats().ready
+    }
+
+    // Whether the supervisor returned, after stopping its child
+    pub fn is_finished(&self) -> bool {
+        self.control.stats().finished
+    }
+
+    // Stops the supervisor, resolving once its child is terminated and the
+    // supervisor returned
+    #[cfg(feature = "tokio")]
+    pub async fn stop_async(&self) {
+        self.stop();
+        self.control
+            .until(|stats| stats.finished.then_some(true))
+            .await;
+    }
+
+    // Resolves once the child passed its tests, to false when the supervisor
+    // returned before that
+    #[cfg(feature = "tokio")]
+    pub async fn wait_ready(&self) -> bool {
+        self.control
+            .until(|stats| match (stats.ready, stats.finished) {
+                (true, _) => Some(true),
+                (false, true) => Some(false),
+                (false, false) => None,
+            })
+            .await
+    }
+
+    // A snapshot of the supervisor, as served by the control socket and the
+    // status server
+    #[cfg(any(all(feature = "control", unix), feature = "status-server"))]
+    pub(crate) fn status(&self) -> serde_json::Value {
+        let state = match (self.is_finished(), self.is_paused(), self.pid()) {
+            (true, _, _) => "finished",
+            (false, true, _) => "stopped",
+            (false, false, Some(_)) => "running",
+            (false, false, None) => "starting",
+        };
+        serde_json::json!({
+            "state": state,
+            "pid": self.pid(),
+            "uptime": self.uptime().map(|uptime| uptime.as_secs_f64()),
+            "restarts": self.total_restarts(),
+            "restarts_remaining": self.restarts_remaining(),
+            "last_failure": self.last_failure(),
+            "tests": self.test_stats().iter().map(|test| serde_json::json!({
+                "name": test.name,
+                "healthy": !test.last_outcome.is_failure(),
+                "consecutive_failures": test.consecutive_failures,
+                "latency": test.latency.map(|latency| latency.as_secs_f64()),
+            })).collect::<Vec<_>>(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn it_wakes_up_waiters_on_request() {
+        let control = Arc::new(Control::default());
+        let handle = SupervisorHandle::new(control.clone());
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            handle.restart();
+        });
+
+        let started = Instant::now();
+        assert_eq!(
+            control.wait_timeout(Duration::from_secs(10)),
+            Some(Request::Restart)
+        );
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert_eq!(control.wait_timeout(Duration::from_millis(1)), None);
+    }
+
+    #[test]
+    fn stop_is_sticky_and_wins_over_restart() {
+        let control = Arc::new(Control::default());
+        let handle = SupervisorHandle::new(control.clone());
+        handle.stop();
+        handle.restart();
+
+        assert_eq!(control.wait_timeout(Duration::ZERO), Some(Request::Stop));
+        assert_eq!(control.wait_timeout(Duration::ZERO), Some(Request::Stop));
+        assert!(control.is_stopped());
+    }
+
+    #[test]
+    fn pausing_is_sticky_until_resumed() {
+        let control = Arc::new(Control::default());
+        let handle = SupervisorHandle::new(control.clone());
+        handle.pause();
+        assert_eq!(control.wait_timeout(Duration::ZERO), Some(Request::Pause));
+        assert_eq!(control.wait_timeout(Duration::ZERO), Some(Request::Pause));
+
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            handle.resume();
+        });
+        assert!(!control.wait_paused(Duration::from_secs(10)));
+        assert_eq!(control.wait_timeout(Duration::ZERO), None);
+    }
+
+    #[test]
+    fn reloads_are_kept_until_taken() {
+        let control = Arc::new(Control::default());
+        let handle = SupervisorHandle::new(control.clone());
+        assert_eq!(
+            handle.reload(ReloadConfig::new().with_check_interval(Duration::ZERO)),
+            Err(BuilderError::ZeroCheckInterval)
+        );
+        handle
+            .reload(ReloadConfig::new().with_check_interval(Duration::from_secs(1)))
+            .unwrap();
+
+        assert_eq!(control.wait_timeout(Duration::ZERO), Some(Request::Reload));
+        assert!(control.take_reload().is_some());
+        assert_eq!(control.wait_timeout(Duration::ZERO), None);
+    }
+}
@@ -0,0 +1,107 @@
+use std::{
+    collections::VecDeque,
+    fmt::Write as _,
+    fs::OpenOptions,
+    io::{self, Write},
+    path::Path,
+    process::ExitStatus,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{RestartContext, RestartReason};
+
+pub(crate) const DEFAULT_CAPACITY: usize = 100;
+
+// A restart as remembered by the supervisor, see `SupervisorHandle::history`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RestartRecord {
+    pub timestamp: SystemTime,
+    pub attempt: u64,
+    pub reason: RestartReason,
+    pub failed_test: Option<String>,
+    pub exit_status: Option<ExitStatus>,
+    pub backoff: Duration,
+}
+
+impl From<&RestartContext> for RestartRecord {
+    fn from(context: &RestartContext) -> Self {
+        Self {
+            timestamp: context.timestamp,
+            attempt: context.attempt,
+            reason: context.reason,
+            failed_test: context.failed_test.clone(),
+            exit_status: context.exit_status,
+            backoff: context.backoff,
+        }
+    }
+}
+
+impl RestartRecord {
+    // A `key=value` line, with the failed test quoted
+    fn line(&self) -> String {
+        let timestamp = self
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let mut line = format!(
+            "timestamp={}.{:03} attempt={} reason={}",
+            timestamp.as_secs(),
+            timestamp.subsec_millis(),
+            self.attempt,
+            self.reason.as_str()
+        );
+        if let Some(test) = &self.failed_test {
+            let _ = write!(line, " failed_test={:?}", test);
+        }
+        if let Some(code) = self.exit_status.and_then(|status| status.code()) {
+            let _ = write!(line, " exit_code={}", code);
+        }
+        let _ = write!(line, " backoff={:.3}", self.backoff.as_secs_f64());
+        line
+    }
+}
+
+// Keeps the last `capacity` records
+pub(crate) fn push(history: &mut VecDeque<RestartRecord>, record: RestartRecord, capacity: usize) {
+    history.push_back(record);
+    while history.len() > capacity {
+        history.pop_front();
+    }
+}
+
+pub(crate) fn append(path: &Path, record: &RestartRecord) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", record.line())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(attempt: u64) -> RestartRecord {
+        RestartRecord {
+            timestamp: UNIX_EPOCH + Duration::from_millis(1_700_000_000_250),
+            attempt,
+            reason: RestartReason::FailedTest,
+            failed_test: Some("say \"hi\"".into()),
+            exit_status: None,
+            backoff: Duration::from_secs(30),
+        }
+    }
+
+    #[test]
+    fn it_keeps_the_last_records() {
+        let mut history = VecDeque::new();
+        for attempt in 1..=5 {
+            push(&mut history, record(attempt), 3);
+        }
+        let attempts: Vec<u64> = history.iter().map(|record| record.attempt).collect();
+        assert_eq!(attempts, vec![3, 4, 5]);
+
+        assert_eq!(
+            record(2).line(),
+            "timestamp=1700000000.250 attempt=2 reason=failed_test failed_test=\"say \\\"hi\\\"\" \
+             backoff=30.000"
+        );
+    }
+}
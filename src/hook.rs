@@ -0,0 +1,98 @@
+use std::{process::Command, thread};
+
+use crate::{template, RestartContext};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HookEvent {
+    Spawn,
+    Restart,
+    NoRestart,
+}
+
+// Shell commands run on lifecycle events, with {name}, {pid}, {attempt},
+// {uptime}, {failed_test} and {core_dump} replaced by the event's values
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ScriptHooks {
+    hooks: Vec<(HookEvent, String)>,
+}
+
+impl ScriptHooks {
+    pub(crate) fn push(&mut self, event: HookEvent, command: &str) {
+        self.hooks.push((event, command.into()));
+    }
+
+    pub(crate) fn run_spawn(&self, name: Option<&str>, pid: u32, attempt: u64) {
+        let vars = [
+            ("name", name.unwrap_or_default().to_string()),
+            ("pid", pid.to_string()),
+            ("attempt", attempt.to_string()),
+        ];
+        self.run(HookEvent::Spawn, |command| template::expand(command, &vars));
+    }
+
+    pub(crate) fn run_restart(&self, event: HookEvent, context: &RestartContext) {
+        self.run(event, |template| expand(template, context));
+    }
+
+    fn run(&self, event: HookEvent, expand: impl Fn(&str) -> String) {
+        for (_, template) in self.hooks.iter().filter(|(hook, _)| *hook == event) {
+            spawn_shell(expand(template));
+        }
+    }
+}
+
+fn expand(command: &str, context: &RestartContext) -> String {
+    let vars = [
+        ("name", context.name.clone().unwrap_or_default()),
+        ("pid", context.pid.to_string()),
+        ("attempt", context.attempt.to_string()),
+        ("uptime", context.uptime.as_secs().to_string()),
+        (
+            "failed_test",
+            context.failed_test.clone().unwrap_or_default(),
+        ),
+        (
+            "core_dump",
+            context
+                .core_dump
+                .as_ref()
+                .map(|path| path.display().to_string())
+                .unwrap_or_default(),
+        ),
+    ];
+    template::expand(command, &vars)
+}
+
+// Hooks run in the background, so a slow one doesn't hold up the supervisor
+fn spawn_shell(command: String) {
+    #[cfg(unix)]
+    let child = Command::new("sh").arg("-c").arg(command).spawn();
+    #[cfg(windows)]
+    let child = Command::new("cmd").arg("/C").arg(command).spawn();
+    if let Ok(mut child) = child {
+        thread::spawn(move || child.wait());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn it_expands_the_template() {
+        let context = RestartContext {
+            failed_test: Some("http".into()),
+            output_tail: vec![],
+            ..RestartContext::new(Some("web".into()), 2, Duration::from_secs(61), 42)
+        };
+        assert_eq!(
+            expand(
+                "notify-send '{name} restarted ({attempt}, {pid}, {uptime}s, {failed_test})'",
+                &context
+            ),
+            "notify-send 'web restarted (2, 42, 61s, http)'"
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use std::{
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+use crate::{ChildInfo, HealthCheck, TestOutcome};
+
+// GETs a plain `http://` URL and passes on any 2xx status
+pub struct HttpCheck {
+    name: String,
+    host: String,
+    path: String,
+    timeout: Duration,
+}
+
+impl HttpCheck {
+    pub fn new(url: &str) -> Self {
+        let rest = url.strip_prefix("http://").unwrap_or(url);
+        let (host, path) = match rest.find('/') {
+            Some(index) => rest.split_at(index),
+            None => (rest, "/"),
+        };
+        Self {
+            name: format!("http {}", url),
+            host: host.into(),
+            path: path.into(),
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+
+    fn status(&self) -> io::Result<u16> {
+        let address = match self.host.contains(':') {
+            true => self.host.clone(),
+            false => format!("{}:80", self.host),
+        };
+        let address = address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no address"))?;
+        let mut stream = TcpStream::connect_timeout(&address, self.timeout)?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+        write!(
+            stream,
+            "GET {} HTTP/1.0\r\nHost: {}\r\nConnection: close\r\n\r\n",
+            self.path, self.host
+        )?;
+
+        // Only the status line matters
+        let mut head = [0; 32];
+        let mut read = 0;
+        while read < head.len() {
+            match stream.read(&mut head[read..])? {
+                0 => break,
+                n => read += n,
+            }
+        }
+        String::from_utf8_lossy(&head[..read])
+            .split(' ')
+            .nth(1)
+            .and_then(|code| code.parse().ok())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid HTTP response"))
+    }
+}
+
+impl HealthCheck for HttpCheck {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn check(&mut self, _: &mut ChildInfo) -> TestOutcome {
+        TestOutcome::from(
+            self.status()
+                .is_ok_and(|status| (200..300).contains(&status)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::BufRead, io::BufReader, net::TcpListener, thread};
+
+    use super::*;
+
+    #[test]
+    fn it_passes_on_success_statuses() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                // Reads the whole request, closing with unread data would
+                // reset the connection
+                let mut lines = BufReader::new(&stream).lines().map_while(Result::ok);
+                let request = lines.next().unwrap();
+                lines.take_while(|line| !line.is_empty()).for_each(drop);
+                let status = match request.as_str() {
+                    "GET /health HTTP/1.0" => "204 No Content",
+                    _ => "404 Not Found",
+                };
+                write!(stream, "HTTP/1.0 {}\r\n\r\n", status).unwrap();
+            }
+        });
+
+        let check = |path: &str| HttpCheck::new(&format!("http://{}{}", address, path)).status();
+        assert_eq!(check("/health").unwrap(), 204);
+        assert_eq!(check("").unwrap(), 404);
+        assert_eq!(
+            HttpCheck::new("http://localhost:8080/health").name(),
+            "http http://localhost:8080/health"
+        );
+    }
+}
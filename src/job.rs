@@ -0,0 +1,12 @@
+// How the supervisor treats the child exiting by itself
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JobPolicy {
+    // A long-running service, kept alive and checked by its tests
+    #[default]
+    Daemon,
+    // A short-lived job that is retried until it exits successfully, running
+    // at most `max_attempts` times in total
+    RestartUntilSuccess {
+        max_attempts: u64,
+    },
+}
@@ -0,0 +1,216 @@
+use std::{
+    fmt::Write as _,
+    fs::OpenOptions,
+    io::{self, LineWriter, Write},
+    path::PathBuf,
+    process::ExitStatus,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{context, RestartContext, TestOutcome, TestStats};
+
+pub(crate) enum LogEvent<'e> {
+    Spawn(u32),
+    Exit(ExitStatus),
+    Test(&'e TestStats),
+    // The name of the test and what it panicked with
+    TestPanic(&'e str, &'e str),
+    // The name of the test and the error of its probe
+    ProbeError(&'e str, &'e str),
+    Restart(&'e RestartContext),
+    NoRestart(&'e RestartContext),
+    Flapping(&'e RestartContext),
+    // How long the supervisor waits before the restart with this attempt
+    // number
+    Backoff(Duration, u64),
+}
+
+// Writes every event as a line of JSON, to a file opened in append mode when
+// the supervisor starts or to any writer
+pub(crate) struct JsonLog {
+    path: Option<PathBuf>,
+    writer: Option<Box<dyn Write + Send>>,
+}
+
+impl JsonLog {
+    pub(crate) fn file(path: PathBuf) -> Self {
+        Self {
+            path: Some(path),
+            writer: None,
+        }
+    }
+
+    pub(crate) fn writer(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            path: None,
+            writer: Some(Box::new(writer)),
+        }
+    }
+
+    pub(crate) fn open(&mut self) -> io::Result<()> {
+        if let (Some(path), None) = (&self.path, &self.writer) {
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            self.writer = Some(Box::new(LineWriter::new(file)));
+        }
+        Ok(())
+    }
+
+    // Failing to log doesn't stop the supervision
+    pub(crate) fn write(&mut self, name: Option<&str>, event: LogEvent) {
+        if let Some(writer) = &mut self.writer {
+            let _ = writeln!(writer, "{}", line(name, event, SystemTime::now()));
+        }
+    }
+}
+
+fn line(name: Option<&str>, event: LogEvent, now: SystemTime) -> String {
+    let (timestamp, sequence) = match &event {
+        LogEvent::Restart(context) | LogEvent::NoRestart(context) | LogEvent::Flapping(context) => {
+            (context.timestamp, context.sequence)
+        }
+        _ => (now, context::next_sequence()),
+    };
+    let timestamp = timestamp.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let mut line = format!(
+        "{{\"timestamp\":{}.{:03},\"sequence\":{}",
+        timestamp.as_secs(),
+        timestamp.subsec_millis(),
+        sequence
+    );
+    if let Some(name) = name {
+        let _ = write!(line, ",\"service\":{}", string(name));
+    }
+    let _ = match event {
+        LogEvent::Spawn(pid) => write!(line, ",\"event\":\"spawn\",\"pid\":{}", pid),
+        LogEvent::Exit(status) => match status.code() {
+            Some(code) => write!(line, ",\"event\":\"exit\",\"code\":{}", code),
+            None => write!(line, ",\"event\":\"exit\",\"code\":null"),
+        },
+        LogEvent::Test(stats) => test(&mut line, stats),
+        LogEvent::TestPanic(name, message) => write!(
+            line,
+            ",\"event\":\"test_panicked\",\"test\":{},\"message\":{}",
+            string(name),
+            string(message)
+        ),
+        LogEvent::ProbeError(name, message) => write!(
+            line,
+            ",\"event\":\"probe_error\",\"test\":{},\"message\":{}",
+            string(name),
+            string(message)
+        ),
+        LogEvent::Restart(context) => restart(&mut line, "restart", context),
+        LogEvent::NoRestart(context) => restart(&mut line, "no_restart", context),
+        LogEvent::Flapping(context) => restart(&mut line, "flapping", context),
+        LogEvent::Backoff(delay, attempt) => write!(
+            line,
+            ",\"event\":\"backoff\",\"attempt\":{},\"delay\":{:.3}",
+            attempt,
+            delay.as_secs_f64()
+        ),
+    };
+    line.push('}');
+    line
+}
+
+fn test(line: &mut String, stats: &TestStats) -> std::fmt::Result {
+    let event = match stats.last_outcome {
+        TestOutcome::Healthy => "test_ok",
+        _ => "test_error",
+    };
+    write!(
+        line,
+        ",\"event\":\"{}\",\"test\":{},\"consecutive_failures\":{}",
+        event,
+        string(&stats.name),
+        stats.consecutive_failures
+    )?;
+    match stats.latency {
+        Some(latency) => write!(line, ",\"latency\":{:.3}", latency.as_secs_f64()),
+        None => write!(line, ",\"latency\":null"),
+    }
+}
+
+fn restart(line: &mut String, event: &str, context: &RestartContext) -> std::fmt::Result {
+    write!(
+        line,
+        ",\"event\":\"{}\",\"pid\":{},\"attempt\":{},\"uptime\":{:.3}",
+        event,
+        context.pid,
+        context.attempt,
+        context.uptime.as_secs_f64()
+    )?;
+    match &context.failed_test {
+        Some(test) => write!(line, ",\"failed_test\":{}", string(test))?,
+        None => write!(line, ",\"failed_test\":null")?,
+    }
+    match &context.test_panic {
+        Some(message) => write!(line, ",\"test_panic\":{}", string(message))?,
+        None => write!(line, ",\"test_panic\":null")?,
+    }
+    match context.exit_status.and_then(|status| status.code()) {
+        Some(code) => write!(line, ",\"exit_code\":{}", code)?,
+        None => write!(line, ",\"exit_code\":null")?,
+    }
+    match &context.core_dump {
+        Some(path) => write!(line, ",\"core_dump\":{}", string(&path.to_string_lossy())),
+        None => write!(line, ",\"core_dump\":null"),
+    }
+}
+
+fn string(value: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_are_json_lines() {
+        let now = UNIX_EPOCH + Duration::from_millis(1_700_000_000_250);
+        let spawn = line(Some("web"), LogEvent::Spawn(42), now);
+        assert!(spawn.starts_with("{\"timestamp\":1700000000.250,\"sequence\":"));
+        assert!(spawn.ends_with(",\"service\":\"web\",\"event\":\"spawn\",\"pid\":42}"));
+        let backoff = line(None, LogEvent::Backoff(Duration::from_millis(1500), 3), now);
+        assert!(backoff.ends_with(",\"event\":\"backoff\",\"attempt\":3,\"delay\":1.500}"));
+
+        let stats = TestStats {
+            name: "say \"hi\"\n".into(),
+            last_outcome: TestOutcome::Unhealthy,
+            consecutive_failures: 2,
+            latency: Some(Duration::from_millis(20)),
+        };
+        assert!(line(None, LogEvent::Test(&stats), now).ends_with(
+            ",\"event\":\"test_error\",\"test\":\"say \\\"hi\\\"\\n\",\
+             \"consecutive_failures\":2,\"latency\":0.020}"
+        ));
+
+        let context = RestartContext {
+            failed_test: Some("http".into()),
+            sequence: 7,
+            timestamp: now,
+            ..RestartContext::new(None, 2, Duration::from_millis(1500), 42)
+        };
+        assert_eq!(
+            line(None, LogEvent::Restart(&context), SystemTime::now()),
+            "{\"timestamp\":1700000000.250,\"sequence\":7,\"event\":\"restart\",\"pid\":42,\
+             \"attempt\":2,\"uptime\":1.500,\"failed_test\":\"http\",\"test_panic\":null,\"exit_code\":null,\"core_dump\":null}"
+        );
+    }
+}
@@ -1,71 +1,507 @@
+#[cfg(feature = "tokio")]
+mod async_tests;
+mod backend;
+mod backoff;
+mod breaker;
+mod budget;
+#[cfg(all(feature = "cgroup", target_os = "linux"))]
+mod cgroup;
+mod check;
+mod child;
+mod clock;
+mod combinator;
+mod command;
+mod concurrent;
+mod context;
+#[cfg(all(feature = "control", unix))]
+mod control;
+#[cfg(unix)]
+mod coredump;
+#[cfg(all(feature = "docker", unix))]
+mod docker;
+mod duration;
+mod effective;
+#[cfg(feature = "email")]
+mod email;
+mod envfile;
+mod error;
+mod flap;
+mod group;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod handle;
+mod history;
+mod hook;
+mod http;
+mod job;
+mod jsonlog;
+#[cfg(target_os = "linux")]
+mod linux;
+mod maintenance;
+#[cfg(feature = "output-match")]
+mod matcher;
+mod notify;
+mod outcome;
+mod output;
+mod reload;
+mod replicas;
+mod resetdir;
+mod scratch;
+mod secrets;
+mod send;
+#[cfg(unix)]
+mod signals;
+mod spec;
+mod standby;
+mod state;
+#[cfg(feature = "status-server")]
+mod status;
+mod stdin;
+mod supervisable;
+#[cfg(all(feature = "syslog", unix))]
+mod syslog;
+mod tcp;
+mod template;
+#[cfg(unix)]
+mod uds;
+#[cfg(unix)]
+mod unix;
+#[cfg(feature = "watch")]
+mod watch;
+mod watchdog;
+#[cfg(feature = "webhook")]
+mod webhook;
+#[cfg(windows)]
+mod windows;
+
+#[cfg(feature = "tokio")]
+pub use async_tests::Probe;
+pub use backend::{MockBackend, ProcessBackend, StdBackend};
+pub use backoff::BackoffPolicy;
+pub use breaker::CircuitBreaker;
+pub use budget::RestartBudget;
+#[cfg(all(feature = "cgroup", target_os = "linux"))]
+pub use cgroup::{CgroupLimits, CgroupMemoryCheck};
+use check::ScheduledCheck;
+pub use check::{HealthCheck, IntoTestResult, SubResult, TestError};
+pub use child::ChildInfo;
+pub use clock::{Clock, MockClock};
+pub use combinator::{CompositeCheck, Every, HealthCheckExt, Retries, SlowIsUnhealthy};
+pub use command::CommandCheck;
+pub use concurrent::ConcurrentTest;
+pub use context::{RestartContext, RestartReason};
+#[cfg(all(feature = "docker", unix))]
+pub use docker::DockerCheck;
+pub use duration::parse_duration;
+pub use effective::EffectiveCommand;
+#[cfg(feature = "email")]
+pub use email::{EmailError, EmailNotifier};
+pub use error::{BuilderError, SupervisorError};
+pub use flap::{FlapAction, FlapDetector};
+pub use group::{RestartStrategy, SupervisorGroup};
+#[cfg(feature = "grpc")]
+pub use grpc::GrpcCheck;
+pub use handle::SupervisorHandle;
+pub use history::RestartRecord;
+pub use http::HttpCheck;
+pub use job::JobPolicy;
+#[cfg(target_os = "linux")]
+pub use linux::{IoPriorityClass, NamespaceFlags};
+pub use maintenance::MaintenanceWindow;
+#[cfg(feature = "output-match")]
+pub use matcher::OutputAction;
+pub use notify::{Notifier, NotifyEvent};
+pub use outcome::{RunOutcome, SingleRun, TestAggregation, TestOutcome, TestStats};
+pub use output::OutputLine;
+#[cfg(feature = "output-match")]
+pub use regex::Regex;
+pub use reload::ReloadConfig;
+pub use replicas::ReplicatedSupervisor;
+pub use send::{Local, MaybeSend, Threaded, Threading};
+pub use spec::SupervisorSpec;
+pub use standby::StandbyPair;
+pub use stdin::StdinSource;
+pub use supervisable::{FnWorker, Instance, Supervisable, Supervisor, ThreadWorker, WorkerThread};
+#[cfg(all(feature = "syslog", unix))]
+pub use syslog::SyslogFacility;
+pub use tcp::TcpCheck;
+#[cfg(unix)]
+pub use uds::UdsCheck;
+#[cfg(feature = "webhook")]
+pub use webhook::WebhookNotifier;
+
+#[cfg(feature = "tokio")]
+use async_tests::AsyncTests;
+use backend::SharedBackend;
+use child::RunningChild;
+use concurrent::ConcurrentTests;
+use handle::{Control, Request};
+use hook::{HookEvent, ScriptHooks};
+use jsonlog::{JsonLog, LogEvent};
+use output::OutputTail;
+use reload::ReloadCheck;
+use scratch::ScratchDir;
+#[cfg(unix)]
+use signals::SignalForwarder;
+use state::State;
 use std::{
-    process::{Child, Command},
-    thread,
-    time::Duration,
+    ffi::{OsStr, OsString},
+    io::{self, Write},
+    marker::PhantomData,
+    net::TcpListener,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
+use watchdog::{StdioWatchdog, STDIO_WATCHDOG_TEST};
+
+#[cfg(feature = "status-server")]
+use std::net::SocketAddr;
+
+pub(crate) const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(50);
+#[cfg(unix)]
+const GRACEFUL_STOP_TIMEOUT: Duration = Duration::from_secs(10);
+const SPAWN_POLL_INTERVAL: Duration = Duration::from_millis(10);
+const SCRATCH_DIR_ENV: &str = "SCRATCH_DIR";
 
 enum Operation {
     Restart,
-    NoRestart,
+    NoRestart(RunOutcome),
+    // The child was stopped until the supervisor is resumed
+    Pause,
 }
 
-pub type SupervisorTest = Box<dyn FnMut(&mut Child) -> bool>;
+pub type SupervisorTest = Box<dyn FnMut(&mut ChildInfo) -> TestOutcome>;
 
-pub struct SupervisedProcess<'a> {
-    process: String,
-    args: Vec<String>,
+pub struct SupervisedProcess<'a, S: Threading = Local> {
+    name: Option<String>,
+    process: OsString,
+    args: Vec<OsString>,
+    working_dir: Option<PathBuf>,
+    env: Vec<(OsString, OsString)>,
+    env_files: Vec<PathBuf>,
+    secrets: secrets::Secrets,
+    template_vars: Vec<(String, String)>,
+    scratch_dir: Option<String>,
+    reset_dirs: Vec<(PathBuf, PathBuf)>,
+    pid_file: Option<PathBuf>,
+    dynamic_port: Option<OsString>,
+    port: Option<u16>,
+    stdin: Option<StdinSource>,
+    backend: SharedBackend,
+    clock: Option<Arc<dyn Clock>>,
     restart_times: Option<u64>,
+    restart_limit: Option<u64>,
+    backoff_reset_after: Option<Duration>,
+    min_uptime: Option<Duration>,
+    // Children in a row that died before the minimum uptime
+    early_exits: u32,
+    circuit_breaker: Option<CircuitBreaker>,
+    flap_detector: Option<FlapDetector>,
+    // Set by a flap detector, for the next failure
+    flap_backoff: Duration,
+    pause_on_failure: bool,
+    fallback_command: Option<(Vec<OsString>, u32)>,
+    consecutive_failures: u32,
+    using_fallback: bool,
     check_interval: Duration,
+    startup_checks: Option<(Duration, Duration)>,
+    initial_delay: Option<Duration>,
     backoff_time: Duration,
-    tests: Vec<(String, SupervisorTest)>,
-    on_test_start: Option<&'a dyn Fn()>,
-    on_tests_passing: Option<&'a dyn Fn()>,
-    on_test_ok: Option<&'a dyn Fn(&str)>,
-    on_test_error: Option<&'a dyn Fn(&str)>,
-    on_restart: Option<&'a dyn Fn()>,
-    on_no_restart: Option<&'a dyn Fn()>,
+    backoff_policy: BackoffPolicy,
+    oom_backoff_time: Option<Duration>,
+    maintenance_windows: Vec<MaintenanceWindow>,
+    max_uptime: Option<Duration>,
+    total_timeout: Option<Duration>,
+    job_policy: JobPolicy,
+    run_deadline: Option<Instant>,
+    fail_fast_on_spawn: bool,
+    spawn_grace_period: Duration,
+    post_restart_grace: Option<Duration>,
+    aligned_checks: bool,
+    history_capacity: usize,
+    history_file: Option<PathBuf>,
+    kill_timeout: Duration,
+    tests: Vec<ScheduledCheck<S>>,
+    concurrent_tests: ConcurrentTests,
+    #[cfg(feature = "tokio")]
+    async_tests: AsyncTests<S>,
+    test_deadline: Option<Duration>,
+    test_aggregation: TestAggregation,
+    restarts: u64,
+    last_failure: Option<String>,
+    last_panic: Option<String>,
+    // Why the supervisor gave up on the child, for `run_once`
+    given_up: Option<RestartContext>,
+    test_stats: Vec<TestStats>,
+    shutdown: Option<Arc<AtomicBool>>,
+    #[cfg(feature = "tokio")]
+    cancellation_token: Option<tokio_util::sync::CancellationToken>,
+    #[cfg(unix)]
+    signal_forwarder: Option<SignalForwarder>,
+    #[cfg(unix)]
+    ctrlc_shutdown: Option<SignalForwarder>,
+    control: Arc<Control>,
+    dependencies: Vec<String>,
+    stdio_watchdog: Option<Duration>,
+    output_tail: Option<usize>,
+    #[cfg(feature = "tracing")]
+    output_tracing: bool,
+    #[cfg(feature = "output-match")]
+    output_matchers: matcher::OutputMatchers,
+    #[cfg(windows)]
+    job_object: bool,
+    #[cfg(target_os = "linux")]
+    reap_orphans: bool,
+    #[cfg(target_os = "linux")]
+    chroot: Option<PathBuf>,
+    #[cfg(target_os = "linux")]
+    namespaces: NamespaceFlags,
+    #[cfg(all(feature = "cgroup", target_os = "linux"))]
+    cgroup: Option<(PathBuf, CgroupLimits)>,
+    #[cfg(unix)]
+    core_dumps: Option<PathBuf>,
+    #[cfg(unix)]
+    nice: Option<i8>,
+    #[cfg(unix)]
+    umask: Option<u32>,
+    #[cfg(target_os = "linux")]
+    cpu_affinity: Vec<usize>,
+    #[cfg(target_os = "linux")]
+    io_priority: Option<(IoPriorityClass, u8)>,
+    #[cfg(unix)]
+    adopted: Option<u32>,
+    state_file: Option<PathBuf>,
+    #[cfg(unix)]
+    readopt_on_resume: bool,
+    #[cfg(all(feature = "control", unix))]
+    control_socket: Option<PathBuf>,
+    #[cfg(feature = "status-server")]
+    status_server: Option<SocketAddr>,
+    #[cfg(feature = "watch")]
+    watch_paths: Vec<PathBuf>,
+    script_hooks: ScriptHooks,
+    json_log: Option<JsonLog>,
+    #[cfg(all(feature = "syslog", unix))]
+    syslog: Option<syslog::Syslog>,
+    notifiers: Vec<Box<S::Notifier<'a>>>,
+    on_test_start: Option<Box<S::Handler<'a>>>,
+    on_tests_passing: Option<Box<S::Handler<'a>>>,
+    on_test_ok: Option<Box<S::TestHandler<'a>>>,
+    on_test_error: Option<Box<S::TestHandler<'a>>>,
+    on_test_panic: Option<Box<S::PanicHandler<'a>>>,
+    on_probe_error: Option<Box<S::PanicHandler<'a>>>,
+    on_sub_results: Option<Box<S::SubResultsHandler<'a>>>,
+    on_restart: Option<Box<S::RestartHandler<'a>>>,
+    on_no_restart: Option<Box<S::RestartHandler<'a>>>,
+    on_restart_exhausted: Option<Box<S::RestartHandler<'a>>>,
+    on_flapping: Option<Box<S::RestartHandler<'a>>>,
+    on_spawn: Option<Box<S::SpawnHandler<'a>>>,
+    on_exit: Option<Box<S::ExitHandler<'a>>>,
+    on_backoff: Option<Box<S::BackoffHandler<'a>>>,
+    on_backoff_end: Option<Box<S::BackoffEndHandler<'a>>>,
+    marker: PhantomData<S>,
 }
 
-impl<'a> Default for SupervisedProcess<'a> {
+impl<'a, S: Threading> Default for SupervisedProcess<'a, S> {
     fn default() -> Self {
         Self {
-            process: "".to_string(),
+            name: None,
+            process: OsString::new(),
             args: vec![],
+            working_dir: None,
+            env: vec![],
+            env_files: vec![],
+            secrets: secrets::Secrets::default(),
+            template_vars: vec![],
+            scratch_dir: None,
+            reset_dirs: vec![],
+            pid_file: None,
+            dynamic_port: None,
+            port: None,
+            stdin: None,
+            backend: Arc::new(Mutex::new(StdBackend::default())),
+            clock: None,
             restart_times: None,
+            restart_limit: None,
+            backoff_reset_after: None,
+            min_uptime: None,
+            early_exits: 0,
+            circuit_breaker: None,
+            flap_detector: None,
+            flap_backoff: Duration::ZERO,
+            pause_on_failure: false,
+            fallback_command: None,
+            consecutive_failures: 0,
+            using_fallback: false,
             check_interval: Duration::from_secs(30),
+            startup_checks: None,
+            initial_delay: None,
             backoff_time: Duration::from_secs(30),
+            backoff_policy: BackoffPolicy::Always,
+            oom_backoff_time: None,
+            maintenance_windows: vec![],
+            max_uptime: None,
+            total_timeout: None,
+            job_policy: JobPolicy::Daemon,
+            run_deadline: None,
+            fail_fast_on_spawn: false,
+            spawn_grace_period: Duration::from_secs(1),
+            post_restart_grace: None,
+            aligned_checks: false,
+            history_capacity: history::DEFAULT_CAPACITY,
+            history_file: None,
+            kill_timeout: Duration::from_secs(5),
             tests: vec![],
+            concurrent_tests: ConcurrentTests::default(),
+            #[cfg(feature = "tokio")]
+            async_tests: AsyncTests::default(),
+            test_deadline: None,
+            test_aggregation: TestAggregation::AllMustPass,
+            restarts: 0,
+            last_failure: None,
+            last_panic: None,
+            given_up: None,
+            test_stats: vec![],
+            shutdown: None,
+            #[cfg(feature = "tokio")]
+            cancellation_token: None,
+            #[cfg(unix)]
+            signal_forwarder: None,
+            #[cfg(unix)]
+            ctrlc_shutdown: None,
+            control: Arc::default(),
+            dependencies: vec![],
+            stdio_watchdog: None,
+            output_tail: None,
+            #[cfg(feature = "tracing")]
+            output_tracing: false,
+            #[cfg(feature = "output-match")]
+            output_matchers: Default::default(),
+            #[cfg(windows)]
+            job_object: false,
+            #[cfg(target_os = "linux")]
+            reap_orphans: false,
+            #[cfg(target_os = "linux")]
+            chroot: None,
+            #[cfg(target_os = "linux")]
+            namespaces: NamespaceFlags::default(),
+            #[cfg(all(feature = "cgroup", target_os = "linux"))]
+            cgroup: None,
+            #[cfg(unix)]
+            core_dumps: None,
+            #[cfg(unix)]
+            nice: None,
+            #[cfg(unix)]
+            umask: None,
+            #[cfg(target_os = "linux")]
+            cpu_affinity: vec![],
+            #[cfg(target_os = "linux")]
+            io_priority: None,
+            #[cfg(unix)]
+            adopted: None,
+            state_file: None,
+            #[cfg(unix)]
+            readopt_on_resume: false,
+            #[cfg(all(feature = "control", unix))]
+            control_socket: None,
+            #[cfg(feature = "status-server")]
+            status_server: None,
+            #[cfg(feature = "watch")]
+            watch_paths: vec![],
+            script_hooks: ScriptHooks::default(),
+            json_log: None,
+            #[cfg(all(feature = "syslog", unix))]
+            syslog: None,
+            notifiers: vec![],
             on_test_start: None,
             on_tests_passing: None,
             on_test_ok: None,
             on_test_error: None,
+            on_test_panic: None,
+            on_probe_error: None,
+            on_sub_results: None,
             on_restart: None,
             on_no_restart: None,
+            on_restart_exhausted: None,
+            on_flapping: None,
+            on_spawn: None,
+            on_exit: None,
+            on_backoff: None,
+            on_backoff_end: None,
+            marker: PhantomData,
         }
     }
 }
 
 macro_rules! event {
     ($handler:expr) => {
-        if let Some(handler) = $handler {
+        if let Some(handler) = &mut $handler {
             handler();
         }
     };
 
     ($handler:expr, $($arg:expr),+) => {
-        if let Some(handler) = $handler {
+        if let Some(handler) = &mut $handler {
             handler($($arg),+);
         }
     };
 }
 
 impl<'a> SupervisedProcess<'a> {
-    pub fn new(process: String) -> Self {
+    pub fn new(process: impl AsRef<OsStr>) -> Self {
+        Self {
+            process: process.as_ref().to_os_string(),
+            ..Self::default()
+        }
+    }
+}
+
+impl<'a> SupervisedProcess<'a, Threaded> {
+    // A supervisor that can be moved to another thread, which requires its
+    // tests and handlers to be `Send`
+    pub fn new_send(process: impl AsRef<OsStr>) -> Self {
         Self {
-            process,
+            process: process.as_ref().to_os_string(),
             ..Self::default()
         }
     }
+}
+
+impl SupervisedProcess<'static, Threaded> {
+    // Runs the supervisor on a thread of its own, named after it
+    pub fn start(mut self) -> io::Result<JoinHandle<Result<RunOutcome, SupervisorError>>> {
+        thread::Builder::new()
+            .name(self.thread_name())
+            .spawn(move || self.run())
+    }
+}
+
+impl<'a, S: Threading> SupervisedProcess<'a, S> {
+    pub fn with_name(self, name: &str) -> Self {
+        Self {
+            name: Some(name.into()),
+            ..self
+        }
+    }
+
+    // Replaces the program given to `new`
+    pub fn with_program(self, program: impl Into<PathBuf>) -> Self {
+        Self {
+            process: program.into().into_os_string(),
+            ..self
+        }
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
 
     pub fn with_check_interval(self, check_interval: Duration) -> Self {
         Self {
@@ -74,6 +510,41 @@ impl<'a> SupervisedProcess<'a> {
         }
     }
 
+    // Like `with_check_interval`, with a duration like "30s" or "1h 30m"
+    pub fn with_check_interval_str(self, check_interval: &str) -> Result<Self, BuilderError> {
+        Ok(self.with_check_interval(parse_duration(check_interval)?))
+    }
+
+    // Checks every `interval` during the first `period` after each start, so
+    // early failures are noticed quickly, then relaxes to the check interval
+    pub fn with_startup_check_interval(self, interval: Duration, period: Duration) -> Self {
+        Self {
+            startup_checks: Some((interval, period)),
+            ..self
+        }
+    }
+
+    // Runs the checks on multiples of the check interval in wall clock time,
+    // e.g. every minute on :00 for a one minute interval, rather than counting
+    // from when the child started. An initial delay still counts from there.
+    pub fn with_aligned_checks(self, aligned_checks: bool) -> Self {
+        Self {
+            aligned_checks,
+            ..self
+        }
+    }
+
+    pub fn with_initial_delay(self, initial_delay: Duration) -> Self {
+        Self {
+            initial_delay: Some(initial_delay),
+            ..self
+        }
+    }
+
+    pub fn with_initial_delay_str(self, initial_delay: &str) -> Result<Self, BuilderError> {
+        Ok(self.with_initial_delay(parse_duration(initial_delay)?))
+    }
+
     pub fn with_backoff_time(self, backoff_time: Duration) -> Self {
         Self {
             backoff_time,
@@ -81,293 +552,4906 @@ impl<'a> SupervisedProcess<'a> {
         }
     }
 
-    pub fn with_restart_times(self, restart_times: u64) -> Self {
+    pub fn with_backoff_time_str(self, backoff_time: &str) -> Result<Self, BuilderError> {
+        Ok(self.with_backoff_time(parse_duration(backoff_time)?))
+    }
+
+    pub fn with_backoff_policy(self, backoff_policy: BackoffPolicy) -> Self {
         Self {
-            restart_times: Some(restart_times),
+            backoff_policy,
             ..self
         }
     }
 
-    pub fn with_args(self, args: impl IntoIterator<Item = impl ToString>) -> Self {
-        let args = args.into_iter().map(|a| a.to_string()).collect();
-        Self { args, ..self }
+    // Waits this long instead before restarting a child killed for running
+    // out of memory, as restarting it right away would likely end the same
+    pub fn with_oom_backoff_time(self, oom_backoff_time: Duration) -> Self {
+        Self {
+            oom_backoff_time: Some(oom_backoff_time),
+            ..self
+        }
     }
 
-    pub fn add_test(self, name: &str, test: SupervisorTest) -> Self {
-        let mut tests = self.tests;
-        tests.push((name.into(), test));
+    // Failing tests don't restart the child during the window, they are only
+    // reported. A child that exits is still restarted. Can be called more
+    // than once.
+    pub fn with_maintenance_window(self, window: MaintenanceWindow) -> Self {
+        let mut maintenance_windows = self.maintenance_windows;
+        maintenance_windows.push(window);
+        Self {
+            maintenance_windows,
+            ..self
+        }
+    }
 
-        Self { tests, ..self }
+    pub fn with_maintenance_window_str(self, window: &str) -> Result<Self, BuilderError> {
+        Ok(self.with_maintenance_window(MaintenanceWindow::parse(window)?))
     }
 
-    pub fn should_restart(&mut self) -> bool {
-        match self.restart_times {
-            None => true,
-            Some(0) => false,
-            Some(times) => {
-                self.restart_times = Some(times - 1);
-                true
-            }
+    pub fn with_max_uptime(self, max_uptime: Duration) -> Self {
+        Self {
+            max_uptime: Some(max_uptime),
+            ..self
         }
     }
 
-    pub fn on_restart(self, on_restart: &'a dyn Fn()) -> Self {
+    // Stops the child and returns from `run` once this much time has passed
+    pub fn with_total_timeout(self, total_timeout: Duration) -> Self {
         Self {
-            on_restart: Some(on_restart),
+            total_timeout: Some(total_timeout),
             ..self
         }
     }
 
-    pub fn on_no_restart(self, on_no_restart: &'a dyn Fn()) -> Self {
+    pub fn with_job_policy(self, job_policy: JobPolicy) -> Self {
+        Self { job_policy, ..self }
+    }
+
+    pub fn fail_fast_on_spawn(self, fail_fast_on_spawn: bool) -> Self {
         Self {
-            on_no_restart: Some(on_no_restart),
+            fail_fast_on_spawn,
             ..self
         }
     }
 
-    pub fn on_test_start(self, on_test_start: &'a dyn Fn()) -> Self {
+    pub fn with_spawn_grace_period(self, spawn_grace_period: Duration) -> Self {
         Self {
-            on_test_start: Some(on_test_start),
+            spawn_grace_period,
             ..self
         }
     }
 
-    pub fn on_tests_passing(self, on_tests_passing: &'a dyn Fn()) -> Self {
+    // Failing checks during this time after a restart are reported but don't
+    // restart the child again, for services needing to warm up. Children
+    // exiting are still restarted, and fatal tests still end supervision.
+    pub fn with_post_restart_grace(self, post_restart_grace: Duration) -> Self {
         Self {
-            on_tests_passing: Some(on_tests_passing),
+            post_restart_grace: Some(post_restart_grace),
             ..self
         }
     }
 
-    pub fn on_test_ok(self, on_test_ok: &'a dyn Fn(&str)) -> Self {
+    // How many restarts `SupervisorHandle::history` remembers, 100 unless
+    // told otherwise
+    pub fn with_restart_history(self, history_capacity: usize) -> Self {
         Self {
-            on_test_ok: Some(on_test_ok),
+            history_capacity,
             ..self
         }
     }
 
-    pub fn on_test_error(self, on_test_error: &'a dyn Fn(&str)) -> Self {
+    // Also appends every restart to a file, as a line of `key=value` pairs
+    pub fn with_restart_history_file(self, path: impl Into<PathBuf>) -> Self {
         Self {
-            on_test_error: Some(on_test_error),
+            history_file: Some(path.into()),
             ..self
         }
     }
 
-    fn test_loop(&mut self, child: &mut Child) -> Result<Operation, String> {
-        loop {
-            thread::sleep(self.check_interval);
+    // How long the child gets to exit after a SIGTERM before being sent a
+    // SIGKILL, and then to die before supervision fails, so that a new child
+    // never runs next to the old one. On Windows, children run in a process
+    // group of their own and get a CTRL_BREAK_EVENT instead of the SIGTERM,
+    // which only reaches them when they share the console of the supervisor.
+    // Otherwise they are terminated right away.
+    pub fn with_kill_timeout(self, kill_timeout: Duration) -> Self {
+        Self {
+            kill_timeout,
+            ..self
+        }
+    }
 
-            event!(self.on_test_start);
+    // Unlimited by default, see also `run_once`
+    pub fn with_restart_budget(self, budget: RestartBudget) -> Self {
+        Self {
+            restart_times: budget.remaining(),
+            restart_limit: budget.remaining(),
+            ..self
+        }
+    }
 
-            if !self.tests.iter_mut().all(|test| {
-                if test.1(child) {
-                    event!(self.on_test_ok, test.0.as_str());
-                    true
-                } else {
-                    event!(self.on_test_error, &test.0);
-                    false
-                }
-            }) {
-                let _ = child.kill();
+    // Same as `with_restart_budget(RestartBudget::times(restart_times))`, so
+    // zero means the child is never restarted
+    pub fn with_restart_times(self, restart_times: u64) -> Self {
+        self.with_restart_budget(RestartBudget::times(restart_times))
+    }
 
-                if self.should_restart() {
-                    thread::sleep(self.backoff_time);
-                    event!(self.on_restart);
-                    return Ok(Operation::Restart);
-                } else {
-                    event!(self.on_no_restart);
-                    return Ok(Operation::NoRestart);
-                }
-            } else {
-                event!(self.on_tests_passing);
-            }
+    // Gives the restart budget back once a child has been healthy for this
+    // long, so occasional crashes spread over time never exhaust it. The
+    // backoff after early exits and the circuit breaker start over too.
+    pub fn with_backoff_reset_after(self, backoff_reset_after: Duration) -> Self {
+        Self {
+            backoff_reset_after: Some(backoff_reset_after),
+            ..self
         }
     }
 
-    pub fn run(&mut self) -> Result<(), String> {
-        loop {
-            let process = Command::new(self.process.clone())
-                .args(self.args.clone())
-                .spawn();
-            let mut child = process.map_err(|_| String::from("Failed to start process"))?;
-            match self.test_loop(&mut child) {
-                Ok(Operation::Restart) => continue,
-                Ok(Operation::NoRestart) => return Ok(()),
-                Err(e) => return Err(e),
-            }
+    // A child dying before `min_uptime` didn't start successfully. The
+    // backoff time doubles with each one in a row, whatever the backoff
+    // policy.
+    pub fn with_min_uptime(self, min_uptime: Duration) -> Self {
+        Self {
+            min_uptime: Some(min_uptime),
+            ..self
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::cell::RefCell;
+    pub fn with_flap_detection(self, flap_detector: FlapDetector) -> Self {
+        Self {
+            flap_detector: Some(flap_detector),
+            ..self
+        }
+    }
 
-    use super::*;
+    pub fn with_circuit_breaker(self, circuit_breaker: CircuitBreaker) -> Self {
+        Self {
+            circuit_breaker: Some(circuit_breaker),
+            ..self
+        }
+    }
 
-    #[test]
-    fn it_builds_a_process_with_check_interval() {
-        let process =
-            SupervisedProcess::new("test".to_string()).with_check_interval(Duration::from_secs(15));
-        assert_eq!(process.check_interval, Duration::from_secs(15));
+    // Switches to another command, e.g. an older release, once the child
+    // failed `after_failures` times in a row. The first item is the program.
+    pub fn with_fallback_command(
+        self,
+        command: impl IntoIterator<Item = impl AsRef<OsStr>>,
+        after_failures: u32,
+    ) -> Self {
+        let command = command
+            .into_iter()
+            .map(|a| a.as_ref().to_os_string())
+            .collect();
+        Self {
+            fallback_command: Some((command, after_failures)),
+            ..self
+        }
     }
 
-    #[test]
-    fn it_builds_a_process_with_backoff_time() {
-        let process =
-            SupervisedProcess::new("test".to_string()).with_backoff_time(Duration::from_secs(15));
-        assert_eq!(process.backoff_time, Duration::from_secs(15));
+    pub fn with_args(self, args: impl IntoIterator<Item = impl AsRef<OsStr>>) -> Self {
+        let args = args
+            .into_iter()
+            .map(|a| a.as_ref().to_os_string())
+            .collect();
+        Self { args, ..self }
     }
 
-    #[test]
-    fn it_builds_a_process_adding_a_test() {
-        let process = SupervisedProcess::new("test".to_string())
-            .add_test("always false", Box::from(|_child: &mut Child| false));
-        assert_eq!(process.tests.len(), 1);
+    pub fn with_working_dir(self, working_dir: impl AsRef<Path>) -> Self {
+        Self {
+            working_dir: Some(working_dir.as_ref().to_path_buf()),
+            ..self
+        }
     }
 
-    #[test]
-    fn event_on_restart() {
-        let restart_count: RefCell<i32> = RefCell::new(0);
-        let restart_fn = || {
-            (*restart_count.borrow_mut()) += 1;
-        };
+    pub fn with_env(self, key: impl Into<OsString>, value: impl Into<OsString>) -> Self {
+        let mut env = self.env;
+        env.push((key.into(), value.into()));
+        Self { env, ..self }
+    }
 
-        let mut process = SupervisedProcess::new("echo".to_string())
-            .with_args(vec!["-n"])
-            .add_test("always false", Box::from(|_: &mut Child| false))
-            .with_check_interval(Duration::from_millis(1))
-            .with_backoff_time(Duration::from_millis(1))
-            .with_restart_times(1)
-            .on_restart(&restart_fn);
+    // Like `with_env`, but the value is masked in events, logs, the restart
+    // history, errors and the spec
+    pub fn with_secret_env(self, key: impl Into<OsString>, value: impl Into<OsString>) -> Self {
+        let value = value.into();
+        let mut secrets = self.secrets;
+        secrets.push(&value);
+        Self { secrets, ..self }.with_env(key, value)
+    }
 
-        assert!(process.run().is_ok());
-        let guard = restart_count.borrow();
+    // Adds an argument masked like the values of `with_secret_env`
+    pub fn with_secret_arg(self, arg: impl AsRef<OsStr>) -> Self {
+        let mut secrets = self.secrets;
+        secrets.push(arg.as_ref());
+        let mut args = self.args;
+        args.push(arg.as_ref().to_os_string());
+        Self {
+            secrets,
+            args,
+            ..self
+        }
+    }
 
-        assert_eq!(*guard, 1)
+    // Reads `KEY=VALUE` lines from the file before every spawn, so that
+    // changes apply from the next restart. Variables set with `with_env`
+    // take precedence.
+    pub fn with_env_file(self, path: impl AsRef<Path>) -> Self {
+        let mut env_files = self.env_files;
+        env_files.push(path.as_ref().to_path_buf());
+        Self { env_files, ..self }
     }
 
-    #[test]
-    fn event_on_no_restart() {
-        let no_restart_count: RefCell<i32> = RefCell::new(0);
-        let no_restart_fn = || {
-            (*no_restart_count.borrow_mut()) += 1;
-        };
+    // Adds a value for {key} in the arguments and environment. {name},
+    // {restart_count} and {pid_file} are always known.
+    pub fn with_template_var(self, key: &str, value: impl ToString) -> Self {
+        let mut template_vars = self.template_vars;
+        template_vars.push((key.into(), value.to_string()));
+        Self {
+            template_vars,
+            ..self
+        }
+    }
 
-        let mut process = SupervisedProcess::new("echo".to_string())
-            .with_args(vec!["-n"])
-            .add_test("always false", Box::from(|_: &mut Child| false))
-            .with_check_interval(Duration::from_millis(1))
-            .with_backoff_time(Duration::from_millis(1))
-            .with_restart_times(1)
-            .on_no_restart(&no_restart_fn);
+    // Gives every child an empty directory of its own, removed once it exits.
+    // The template is expanded like the arguments, e.g.
+    // "worker-{restart_count}", and relative to the temporary directory. The
+    // child finds it in SCRATCH_DIR, and in TMPDIR unless set with `with_env`.
+    pub fn with_scratch_dir(self, template: &str) -> Self {
+        Self {
+            scratch_dir: Some(template.into()),
+            ..self
+        }
+    }
 
-        assert!(process.run().is_ok());
+    // Replaces `dst` by a copy of `src` before every child starts, e.g. to
+    // start a database used as a test fixture from a pristine snapshot
+    // whenever it crashes. Can be called more than once.
+    pub fn with_reset_dir(self, src: impl Into<PathBuf>, dst: impl Into<PathBuf>) -> Self {
+        let mut reset_dirs = self.reset_dirs;
+        reset_dirs.push((src.into(), dst.into()));
+        Self { reset_dirs, ..self }
+    }
 
-        assert_eq!(*no_restart_count.borrow(), 1);
+    // Writes the pid of every child to `path`, which is also {pid_file} in
+    // the arguments and environment
+    pub fn with_pid_file(self, path: impl Into<PathBuf>) -> Self {
+        Self {
+            pid_file: Some(path.into()),
+            ..self
+        }
     }
 
-    #[test]
-    fn event_on_test_error() {
-        let error_fn = |name: &str| assert_eq!("always false", name);
+    // Picks a free port once, then gives it to every child in the `env_var`
+    // environment variable and as {port}. A test checks that the child
+    // accepts connections on it.
+    pub fn with_dynamic_port(self, env_var: impl Into<OsString>) -> Self {
+        Self {
+            dynamic_port: Some(env_var.into()),
+            ..self
+        }
+    }
 
-        let mut process = SupervisedProcess::new("echo".to_string())
-            .with_args(vec!["-n"])
-            .add_test("always false", Box::from(|_: &mut Child| false))
-            .with_check_interval(Duration::from_millis(1))
-            .with_backoff_time(Duration::from_millis(1))
-            .with_restart_times(0)
-            .on_test_error(&error_fn);
+    // Tests can return a `TestOutcome`, a bool, or a `Result` of either whose
+    // `TestError` tells that the probe itself failed. Such errors are
+    // reported through `on_probe_error` and neither pass nor fail the test.
+    pub fn add_test<O: IntoTestResult + 'static>(
+        self,
+        name: &str,
+        test: impl FnMut(&mut ChildInfo) -> O + MaybeSend<S> + 'static,
+    ) -> Self {
+        self.push_check(test.boxed_test(name.to_string()), None, false)
+    }
 
-        assert!(process.run().is_ok());
+    // Runs the test, reporting its outcome through the events, stats and logs,
+    // without ever restarting the child because of it
+    pub fn add_advisory_test<O: IntoTestResult + 'static>(
+        self,
+        name: &str,
+        test: impl FnMut(&mut ChildInfo) -> O + MaybeSend<S> + 'static,
+    ) -> Self {
+        self.push_check(test.boxed_test(name.to_string()), None, true)
     }
 
-    #[test]
-    fn event_on_test_run() {
-        let test_run_count: RefCell<i32> = RefCell::new(0);
-        let test_run_fn = || {
-            (*test_run_count.borrow_mut()) += 1;
-        };
+    // Runs the test on its own schedule instead of on every check, e.g. for
+    // expensive probes
+    pub fn add_test_every<O: IntoTestResult + 'static>(
+        self,
+        name: &str,
+        every: Duration,
+        test: impl FnMut(&mut ChildInfo) -> O + MaybeSend<S> + 'static,
+    ) -> Self {
+        self.push_check(test.boxed_test(name.to_string()), Some(every), false)
+    }
 
-        let mut process = SupervisedProcess::new("echo".to_string())
-            .with_args(vec!["-n"])
-            .add_test("always false", Box::from(|_: &mut Child| false))
-            .with_check_interval(Duration::from_millis(1))
-            .with_backoff_time(Duration::from_millis(1))
-            .with_restart_times(1)
-            .on_test_start(&test_run_fn);
+    pub fn add_check(self, check: impl HealthCheck + MaybeSend<S> + 'static) -> Self {
+        self.push_check(check.boxed_check(), None, false)
+    }
 
-        assert!(process.run().is_ok());
-        assert_eq!(*test_run_count.borrow(), 2);
+    fn push_check(
+        self,
+        check: Box<S::Check<'static>>,
+        every: Option<Duration>,
+        advisory: bool,
+    ) -> Self {
+        let mut tests = self.tests;
+        tests.push(ScheduledCheck {
+            check,
+            every,
+            due: None,
+            advisory,
+        });
+
+        Self { tests, ..self }
     }
 
-    #[test]
-    fn event_on_test_ok() {
-        let mut test_ok_count = 0;
-        let test_ok_ptr: *mut i32 = &mut test_ok_count;
+    pub fn add_concurrent_test<O: Into<TestOutcome>>(
+        self,
+        name: &str,
+        mut test: impl FnMut(u32) -> O + Send + 'static,
+    ) -> Self {
+        let mut concurrent_tests = self.concurrent_tests;
+        let test: ConcurrentTest = Box::new(move |pid| test(pid).into());
+        concurrent_tests.push(name.into(), test);
 
-        let test_ok_fn = || unsafe { *test_ok_ptr += 1 };
+        Self {
+            concurrent_tests,
+            ..self
+        }
+    }
 
-        let mut process = SupervisedProcess::new("sleep".to_string())
-            .with_args(vec!["0.1"])
-            .add_test(
-                "not running",
-                Box::from(|child: &mut Child| {
-                    if let Ok(None) = child.try_wait() {
-                        true
-                    } else {
-                        false
-                    }
-                }),
-            )
-            .with_check_interval(Duration::from_millis(80))
-            .with_backoff_time(Duration::from_millis(80))
-            .with_restart_times(0)
-            .on_tests_passing(&test_ok_fn);
-        assert!(process.run().is_ok());
-        drop(process);
+    // Async tests of a check run concurrently on a single thread, and are
+    // allowed until the same deadline as concurrent tests to complete
+    #[cfg(feature = "tokio")]
+    pub fn add_async_test<F, O>(
+        self,
+        name: &str,
+        test: impl FnMut(Probe) -> F + MaybeSend<S> + 'static,
+    ) -> Self
+    where
+        F: std::future::Future<Output = O> + 'static,
+        O: Into<TestOutcome>,
+    {
+        let mut async_tests = self.async_tests;
+        async_tests.push(name.into(), test.boxed_async_test());
 
-        assert_eq!(test_ok_count, 1);
+        Self {
+            async_tests,
+            ..self
+        }
     }
 
-    #[test]
-    fn use_child_in_test() {
-        let mut process = SupervisedProcess::new("sleep".to_string())
-            .with_args(vec!["0.2"])
-            .add_test(
-                "still running",
-                Box::from(|child: &mut Child| match child.try_wait() {
-                    Ok(None) => true,
-                    Ok(Some(exit_value)) => {
-                        println!("Got exit value {}", exit_value);
-                        false
-                    }
-                    _ => false,
-                }),
-            )
-            .with_check_interval(Duration::from_millis(5))
-            .with_restart_times(0);
-        assert!(process.run().is_ok());
+    pub fn with_test_deadline(self, test_deadline: Duration) -> Self {
+        Self {
+            test_deadline: Some(test_deadline),
+            ..self
+        }
     }
 
-    #[test]
-    fn it_runs_the_command() {
-        let mut process = SupervisedProcess::new("echo".to_string())
-            .add_test("always false", Box::from(|_child: &mut Child| false))
-            .with_check_interval(Duration::from_millis(10))
-            .with_backoff_time(Duration::from_millis(10))
-            .with_restart_times(1);
-        assert!(process.run().is_ok());
+    pub fn with_test_aggregation(self, test_aggregation: TestAggregation) -> Self {
+        Self {
+            test_aggregation,
+            ..self
+        }
     }
 
-    #[test]
-    fn it_runs_the_command_with_args() {
-        let mut process = SupervisedProcess::new("echo".to_string())
-            .with_args(vec!["-n"])
-            .add_test("always false", Box::from(|_child: &mut Child| false))
-            .with_check_interval(Duration::from_millis(10))
-            .with_backoff_time(Duration::from_millis(10))
-            .with_restart_times(1);
-        assert!(process.run().is_ok());
+    pub fn with_shutdown_signal(self, shutdown: Arc<AtomicBool>) -> Self {
+        Self {
+            shutdown: Some(shutdown),
+            ..self
+        }
+    }
+
+    // Like `with_shutdown_signal`, for applications shutting down through a
+    // token cancelled from async code
+    #[cfg(feature = "tokio")]
+    pub fn with_cancellation_token(
+        self,
+        cancellation_token: tokio_util::sync::CancellationToken,
+    ) -> Self {
+        Self {
+            cancellation_token: Some(cancellation_token),
+            ..self
+        }
+    }
+
+    // Relays these signals to the child when this process receives them, e.g.
+    // from systemd or a container runtime. After a SIGTERM, SIGINT or SIGQUIT
+    // the child is left to exit on its own and isn't restarted.
+    #[cfg(unix)]
+    pub fn with_signal_forwarding(self, signals: impl IntoIterator<Item = i32>) -> Self {
+        Self {
+            signal_forwarder: Some(SignalForwarder::new(signals.into_iter().collect())),
+            ..self
+        }
+    }
+
+    // Stops supervising on SIGINT or SIGTERM, even in the middle of a backoff.
+    // The child is sent a SIGTERM and given some time to exit before being
+    // killed.
+    #[cfg(unix)]
+    pub fn with_ctrlc_shutdown(self, ctrlc_shutdown: bool) -> Self {
+        let signals = vec![libc::SIGINT, libc::SIGTERM];
+        Self {
+            ctrlc_shutdown: ctrlc_shutdown.then(|| SignalForwarder::new(signals)),
+            ..self
+        }
+    }
+
+    #[cfg(feature = "watch")]
+    pub fn with_watch_paths(self, paths: impl IntoIterator<Item = impl Into<PathBuf>>) -> Self {
+        let watch_paths = paths.into_iter().map(Into::into).collect();
+        Self {
+            watch_paths,
+            ..self
+        }
+    }
+
+    // The stdio watchdog needs the child's stdin, so it can't be used along
+    // with this
+    pub fn with_stdin(self, stdin: StdinSource) -> Self {
+        Self {
+            stdin: Some(stdin),
+            ..self
+        }
+    }
+
+    // Starts and controls children through `backend` instead of
+    // `std::process`, e.g. a `MockBackend` in tests
+    pub fn with_backend(self, backend: impl ProcessBackend + 'static) -> Self {
+        Self {
+            backend: Arc::new(Mutex::new(backend)),
+            ..self
+        }
+    }
+
+    // Appends every event as a line of JSON to the file at `path`
+    pub fn with_json_log(self, path: impl AsRef<Path>) -> Self {
+        Self {
+            json_log: Some(JsonLog::file(path.as_ref().to_path_buf())),
+            ..self
+        }
+    }
+
+    pub fn with_json_log_writer(self, writer: impl Write + Send + 'static) -> Self {
+        Self {
+            json_log: Some(JsonLog::writer(writer)),
+            ..self
+        }
+    }
+
+    // Takes the time from `clock` and waits on it, e.g. a `MockClock` going
+    // through intervals and backoffs instantly in tests. Requests from a
+    // handle are then only noticed between waits.
+    pub fn with_clock(self, clock: impl Clock + 'static) -> Self {
+        let clock: Arc<dyn Clock> = Arc::new(clock);
+        self.control.set_clock(clock.clone());
+        Self {
+            clock: Some(clock),
+            ..self
+        }
+    }
+
+    pub fn with_stdio_watchdog(self, interval: Duration) -> Self {
+        Self {
+            stdio_watchdog: Some(interval),
+            ..self
+        }
+    }
+
+    // Keeps the last `lines` lines the child wrote to stdout and stderr, to
+    // report them on restarts. The output is still forwarded to ours.
+    pub fn with_output_tail(self, lines: usize) -> Self {
+        Self {
+            output_tail: Some(lines),
+            ..self
+        }
+    }
+
+    // Sends every line the child writes to stdout and stderr to the local
+    // syslog daemon instead of our output, tagged with `identifier` and the
+    // pid of the child
+    #[cfg(all(feature = "syslog", unix))]
+    pub fn with_syslog(self, identifier: &str, facility: SyslogFacility) -> Self {
+        Self {
+            syslog: Some(syslog::Syslog::new(identifier, facility)),
+            ..self
+        }
+    }
+
+    // Sends every line the child writes to stdout and stderr to the tracing
+    // subscriber instead of our output, with the supervisor name, the
+    // stream, the pid and the incarnation of the child as fields
+    #[cfg(feature = "tracing")]
+    pub fn with_output_tracing(self, output_tracing: bool) -> Self {
+        Self {
+            output_tracing,
+            ..self
+        }
+    }
+
+    // Runs `action` for every line of the child's stdout or stderr matching
+    // `pattern`. The output is still forwarded to ours.
+    #[cfg(feature = "output-match")]
+    pub fn on_output_match(self, pattern: Regex, action: OutputAction) -> Self {
+        self.output_matchers.push(pattern, action);
+        self
+    }
+
+    pub fn depends_on(self, name: &str) -> Self {
+        let mut dependencies = self.dependencies;
+        dependencies.push(name.into());
+
+        Self {
+            dependencies,
+            ..self
+        }
+    }
+
+    // Runs the child inside a job object, so that killing it also kills every
+    // process it spawned. Without it only the direct child is terminated on
+    // Windows, leaving the rest of the tree running. The job is terminated
+    // once the child had its chance to exit after a CTRL_BREAK_EVENT, see
+    // `with_kill_timeout`.
+    #[cfg(windows)]
+    pub fn with_job_object(self, job_object: bool) -> Self {
+        Self { job_object, ..self }
+    }
+
+    // Makes the supervisor adopt and reap every orphaned descendant, so it can
+    // run as PID 1 in a container without leaving zombies behind.
+    #[cfg(target_os = "linux")]
+    pub fn with_reap_orphans(self, reap_orphans: bool) -> Self {
+        Self {
+            reap_orphans,
+            ..self
+        }
+    }
+
+    // Runs the child with `root` as its root directory. The program, and the
+    // working directory when set, are then looked up inside of it.
+    #[cfg(target_os = "linux")]
+    pub fn with_chroot(self, root: impl AsRef<Path>) -> Self {
+        Self {
+            chroot: Some(root.as_ref().to_path_buf()),
+            ..self
+        }
+    }
+
+    // Gives the child namespaces of its own, for some isolation without a
+    // container runtime. Usually needs root.
+    #[cfg(target_os = "linux")]
+    pub fn with_namespaces(self, namespaces: NamespaceFlags) -> Self {
+        Self { namespaces, ..self }
+    }
+
+    // Runs each child in a cgroup of its own under `parent`, which has to be
+    // part of a writable cgroup v2 hierarchy with the needed controllers
+    // enabled. The cgroup is removed, along with anything left in it, before
+    // the next child starts.
+    #[cfg(all(feature = "cgroup", target_os = "linux"))]
+    pub fn with_cgroup(self, parent: impl AsRef<Path>, limits: CgroupLimits) -> Self {
+        Self {
+            cgroup: Some((parent.as_ref().to_path_buf(), limits)),
+            ..self
+        }
+    }
+
+    // Lets the child dump core, and moves the core dumps of crashed children
+    // into `dir` along with what is known about the crash. Restart events
+    // then tell where the core dump is. Cores handed to a program through
+    // core_pattern(5), like systemd-coredump, are left to it.
+    #[cfg(unix)]
+    pub fn with_core_dumps(self, dir: impl AsRef<Path>) -> Self {
+        Self {
+            core_dumps: Some(dir.as_ref().to_path_buf()),
+            ..self
+        }
+    }
+
+    // Runs the child at the given nice level, e.g. 10 for batch workers that
+    // shouldn't compete with the main service. Going below the nice level of
+    // the supervisor usually needs root.
+    #[cfg(unix)]
+    pub fn with_nice(self, nice: i8) -> Self {
+        Self {
+            nice: Some(nice),
+            ..self
+        }
+    }
+
+    // The umask of the child, e.g. 0o027, instead of the one of the
+    // supervisor
+    #[cfg(unix)]
+    pub fn with_umask(self, umask: u32) -> Self {
+        Self {
+            umask: Some(umask),
+            ..self
+        }
+    }
+
+    // Only lets the child run on the given CPUs, numbered from 0
+    #[cfg(target_os = "linux")]
+    pub fn with_cpu_affinity(self, cpus: &[usize]) -> Self {
+        Self {
+            cpu_affinity: cpus.to_vec(),
+            ..self
+        }
+    }
+
+    // Runs the child with the given IO priority, e.g. `Idle` for backups
+    // that shouldn't starve the main service of disk time. Levels go from 0,
+    // the highest, to 7.
+    #[cfg(target_os = "linux")]
+    pub fn with_io_priority(self, class: IoPriorityClass, level: u8) -> Self {
+        Self {
+            io_priority: Some((class, level)),
+            ..self
+        }
+    }
+
+    // Supervises an already running process instead of spawning a new one.
+    // Tests that need the `Child` are skipped until the process dies and gets
+    // restarted with the configured command.
+    #[cfg(unix)]
+    pub fn adopt(self, pid: u32) -> Self {
+        Self {
+            adopted: Some(pid),
+            ..self
+        }
+    }
+
+    // Records the child PID, restart count and last failure, so that a
+    // supervisor started again after a crash resumes where it left off
+    pub fn with_state_file(self, path: impl AsRef<Path>) -> Self {
+        Self {
+            state_file: Some(path.as_ref().to_path_buf()),
+            ..self
+        }
+    }
+
+    // When resuming from the state file, adopts the recorded child if it's
+    // still alive instead of spawning a new one. A process that has the
+    // recorded PID but started at another time is left alone.
+    #[cfg(unix)]
+    pub fn readopt_on_resume(self, readopt_on_resume: bool) -> Self {
+        Self {
+            readopt_on_resume,
+            ..self
+        }
+    }
+
+    // Serves status, stop, start, restart, shutdown and tail-logs commands as
+    // JSON lines on a Unix socket while running
+    #[cfg(all(feature = "control", unix))]
+    pub fn with_control_socket(self, path: impl Into<PathBuf>) -> Self {
+        Self {
+            control_socket: Some(path.into()),
+            ..self
+        }
+    }
+
+    // Serves `/status` and `/healthz` over HTTP while running, for probing the
+    // supervisor itself
+    #[cfg(feature = "status-server")]
+    pub fn with_status_server(self, addr: SocketAddr) -> Self {
+        Self {
+            status_server: Some(addr),
+            ..self
+        }
+    }
+
+    pub fn build(self) -> Result<Self, BuilderError> {
+        self.validate()?;
+        Ok(self)
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), BuilderError> {
+        if self.process.is_empty() {
+            return Err(BuilderError::EmptyProcess);
+        }
+        if self
+            .fallback_command
+            .as_ref()
+            .is_some_and(|(command, _)| command.first().is_none_or(|program| program.is_empty()))
+        {
+            return Err(BuilderError::EmptyFallbackCommand);
+        }
+        if self.check_interval.is_zero()
+            || self
+                .startup_checks
+                .is_some_and(|(interval, _)| interval.is_zero())
+            || self
+                .tests
+                .iter()
+                .any(|test| test.every.is_some_and(|every| every.is_zero()))
+        {
+            return Err(BuilderError::ZeroCheckInterval);
+        }
+        if self
+            .stdio_watchdog
+            .is_some_and(|interval| interval.is_zero())
+        {
+            return Err(BuilderError::ZeroWatchdogInterval);
+        }
+        if self.stdio_watchdog.is_some() && self.stdin.is_some() {
+            return Err(BuilderError::StdinWithWatchdog);
+        }
+        if self
+            .test_deadline
+            .is_some_and(|deadline| deadline.is_zero())
+        {
+            return Err(BuilderError::ZeroTestDeadline);
+        }
+        if matches!(
+            self.job_policy,
+            JobPolicy::RestartUntilSuccess { max_attempts: 0 }
+        ) {
+            return Err(BuilderError::ZeroMaxAttempts);
+        }
+        if self
+            .circuit_breaker
+            .as_ref()
+            .is_some_and(|breaker| breaker.max_failures() == 0)
+        {
+            return Err(BuilderError::ZeroCircuitBreakerFailures);
+        }
+
+        let mut names: Vec<&str> = self.tests.iter().map(|test| test.check.name()).collect();
+        names.extend(self.fixed_test_names());
+        names.sort_unstable();
+        if let Some(pair) = names.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(BuilderError::DuplicateTest(pair[0].to_string()));
+        }
+
+        Ok(())
+    }
+
+    // The tests other than those added with `add_test` and `add_check`, which
+    // a reload leaves in place
+    fn fixed_test_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.concurrent_tests.names().collect();
+        #[cfg(feature = "tokio")]
+        names.extend(self.async_tests.names());
+        if self.stdio_watchdog.is_some() {
+            names.push(STDIO_WATCHDOG_TEST);
+        }
+        #[cfg(unix)]
+        if self.adopted.is_some() {
+            names.push(unix::ADOPTED_ALIVE_TEST);
+        }
+        #[cfg(feature = "output-match")]
+        if self.output_matchers.reports_failures() {
+            names.push(matcher::OUTPUT_MATCH_TEST);
+        }
+        names
+    }
+
+    pub fn should_restart(&mut self) -> bool {
+        match self.restart_times {
+            None => true,
+            Some(0) => false,
+            Some(times) => {
+                self.restart_times = Some(times - 1);
+                self.publish_stats();
+                true
+            }
+        }
+    }
+
+    // None when the number of restarts is unlimited
+    pub fn restarts_remaining(&self) -> Option<u64> {
+        self.restart_times
+    }
+
+    pub fn total_restarts(&self) -> u64 {
+        self.restarts
+    }
+
+    // None when no child is running
+    pub fn uptime(&self) -> Option<Duration> {
+        self.control
+            .stats()
+            .started
+            .map(|started| self.now().saturating_duration_since(started))
+    }
+
+    pub fn last_failure(&self) -> Option<&str> {
+        self.last_failure.as_deref()
+    }
+
+    // The tests that ran so far, in the order they first ran
+    pub fn test_stats(&self) -> &[TestStats] {
+        &self.test_stats
+    }
+
+    pub(crate) fn publish_stats(&self) {
+        self.control.update_stats(|stats| {
+            stats.restarts = self.restarts;
+            stats.restarts_remaining = self.restart_times;
+            stats.last_failure = self.last_failure.clone();
+            stats.tests = self.test_stats.clone();
+        });
+    }
+
+    pub(crate) fn publish_started(&self, child: Option<&RunningChild>) {
+        self.control.update_stats(|stats| {
+            stats.started = child.map(|child| child.started);
+            stats.pid = child.map(RunningChild::id);
+            stats.ready = false;
+            stats.output = child.map(|child| child.output.clone()).unwrap_or_default();
+        });
+    }
+
+    pub fn on_restart(self, on_restart: impl FnMut(&RestartContext) + MaybeSend<S> + 'a) -> Self {
+        Self {
+            on_restart: Some(on_restart.boxed_restart_handler()),
+            ..self
+        }
+    }
+
+    pub fn on_no_restart(
+        self,
+        on_no_restart: impl FnMut(&RestartContext) + MaybeSend<S> + 'a,
+    ) -> Self {
+        Self {
+            on_no_restart: Some(on_no_restart.boxed_restart_handler()),
+            ..self
+        }
+    }
+
+    // Called when the flap detector sees the child switch between healthy and
+    // unhealthy too many times within its window. The configured `FlapAction`
+    // is taken by then: nothing more, a longer backoff before the next
+    // restart, or pausing at the next failure.
+    pub fn on_flapping(self, on_flapping: impl FnMut(&RestartContext) + MaybeSend<S> + 'a) -> Self {
+        Self {
+            on_flapping: Some(on_flapping.boxed_restart_handler()),
+            ..self
+        }
+    }
+
+    // Called when the child fails and there are no restarts left
+    pub fn on_restart_exhausted(
+        self,
+        on_restart_exhausted: impl FnMut(&RestartContext) + MaybeSend<S> + 'a,
+    ) -> Self {
+        Self {
+            on_restart_exhausted: Some(on_restart_exhausted.boxed_restart_handler()),
+            ..self
+        }
+    }
+
+    // Notifiers are told about every restart and about the supervisor giving up
+    pub fn with_notifier(self, notifier: impl Notifier + MaybeSend<S> + 'a) -> Self {
+        let mut notifiers = self.notifiers;
+        notifiers.push(notifier.boxed_notifier());
+        Self { notifiers, ..self }
+    }
+
+    // Runs a shell command whenever the child is restarted. {name}, {pid},
+    // {attempt}, {uptime}, {failed_test} and {core_dump} in the command are
+    // replaced by their values.
+    pub fn on_restart_run(mut self, command: &str) -> Self {
+        self.script_hooks.push(HookEvent::Restart, command);
+        self
+    }
+
+    pub fn on_no_restart_run(mut self, command: &str) -> Self {
+        self.script_hooks.push(HookEvent::NoRestart, command);
+        self
+    }
+
+    // Only {name}, {pid} and {attempt} are known when the child starts
+    pub fn on_spawn_run(mut self, command: &str) -> Self {
+        self.script_hooks.push(HookEvent::Spawn, command);
+        self
+    }
+
+    pub fn on_spawn(self, on_spawn: impl FnMut(u32) + MaybeSend<S> + 'a) -> Self {
+        Self {
+            on_spawn: Some(on_spawn.boxed_spawn_handler()),
+            ..self
+        }
+    }
+
+    // Called whenever the child terminates, whether it exited by itself or was
+    // killed by the supervisor
+    pub fn on_exit(self, on_exit: impl FnMut(ExitStatus) + MaybeSend<S> + 'a) -> Self {
+        Self {
+            on_exit: Some(on_exit.boxed_exit_handler()),
+            ..self
+        }
+    }
+
+    // Called with the backoff time and the attempt number of the upcoming
+    // restart when the supervisor starts waiting before restarting
+    pub fn on_backoff(self, on_backoff: impl FnMut(Duration, u64) + MaybeSend<S> + 'a) -> Self {
+        Self {
+            on_backoff: Some(on_backoff.boxed_backoff_handler()),
+            ..self
+        }
+    }
+
+    // Called with the attempt number once done waiting, even when stopped
+    // meanwhile
+    pub fn on_backoff_end(self, on_backoff_end: impl FnMut(u64) + MaybeSend<S> + 'a) -> Self {
+        Self {
+            on_backoff_end: Some(on_backoff_end.boxed_backoff_end_handler()),
+            ..self
+        }
+    }
+
+    pub fn on_test_start(self, on_test_start: impl FnMut() + MaybeSend<S> + 'a) -> Self {
+        Self {
+            on_test_start: Some(on_test_start.boxed_handler()),
+            ..self
+        }
+    }
+
+    pub fn on_tests_passing(self, on_tests_passing: impl FnMut() + MaybeSend<S> + 'a) -> Self {
+        Self {
+            on_tests_passing: Some(on_tests_passing.boxed_handler()),
+            ..self
+        }
+    }
+
+    pub fn on_test_ok(self, on_test_ok: impl FnMut(&str) + MaybeSend<S> + 'a) -> Self {
+        Self {
+            on_test_ok: Some(on_test_ok.boxed_test_handler()),
+            ..self
+        }
+    }
+
+    pub fn on_test_error(self, on_test_error: impl FnMut(&str) + MaybeSend<S> + 'a) -> Self {
+        Self {
+            on_test_error: Some(on_test_error.boxed_test_handler()),
+            ..self
+        }
+    }
+
+    // Called with the name of a test that panicked and the panic message. The
+    // panic counts as a failure of the test, and supervision goes on.
+    pub fn on_test_panic(self, on_test_panic: impl FnMut(&str, &str) + MaybeSend<S> + 'a) -> Self {
+        Self {
+            on_test_panic: Some(on_test_panic.boxed_panic_handler()),
+            ..self
+        }
+    }
+
+    // Called with the name of a test whose probe failed and the error, e.g.
+    // when a name doesn't resolve. The test then neither passes nor fails.
+    pub fn on_probe_error(
+        self,
+        on_probe_error: impl FnMut(&str, &str) + MaybeSend<S> + 'a,
+    ) -> Self {
+        Self {
+            on_probe_error: Some(on_probe_error.boxed_panic_handler()),
+            ..self
+        }
+    }
+
+    // Called after a composite check runs, with what each of the checks
+    // making it up found
+    pub fn on_sub_results(
+        self,
+        on_sub_results: impl FnMut(&str, &[SubResult]) + MaybeSend<S> + 'a,
+    ) -> Self {
+        Self {
+            on_sub_results: Some(on_sub_results.boxed_sub_results_handler()),
+            ..self
+        }
+    }
+
+    pub fn handle(&self) -> SupervisorHandle {
+        SupervisorHandle::new(self.control.clone())
+    }
+
+    fn thread_name(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => self.process.to_string_lossy().into_owned(),
+        }
+    }
+
+    fn now(&self) -> Instant {
+        match &self.clock {
+            Some(clock) => clock.now(),
+            None => Instant::now(),
+        }
+    }
+
+    fn system_time(&self) -> SystemTime {
+        match &self.clock {
+            Some(clock) => clock.system_time(),
+            None => SystemTime::now(),
+        }
+    }
+
+    fn uptime_of(&self, child: &RunningChild) -> Duration {
+        self.now().saturating_duration_since(child.started)
+    }
+
+    // Whether a failed check is let go because the child has just been
+    // restarted, or because it happened during a maintenance window
+    pub(crate) fn is_failure_tolerated(
+        &self,
+        child: &mut RunningChild,
+        outcome: TestOutcome,
+    ) -> bool {
+        let after_restart = self.restarts > 0
+            && self
+                .post_restart_grace
+                .is_some_and(|grace| self.uptime_of(child) < grace);
+        let now = self.system_time();
+        let in_maintenance = self
+            .maintenance_windows
+            .iter()
+            .any(|window| window.contains(now));
+        matches!(outcome, TestOutcome::Unhealthy | TestOutcome::RestartNow)
+            && (after_restart || in_maintenance)
+            && !child.has_exited()
+    }
+
+    fn is_shutting_down(&self) -> bool {
+        self.control.is_stopped()
+            || self
+                .run_deadline
+                .is_some_and(|deadline| self.now() >= deadline)
+            || self
+                .shutdown
+                .as_ref()
+                .is_some_and(|shutdown| shutdown.load(Ordering::SeqCst))
+            || self.is_cancelled()
+            || self.is_interrupted()
+    }
+
+    fn is_cancelled(&self) -> bool {
+        #[cfg(feature = "tokio")]
+        return self
+            .cancellation_token
+            .as_ref()
+            .is_some_and(|token| token.is_cancelled());
+        #[cfg(not(feature = "tokio"))]
+        false
+    }
+
+    // Why the supervisor is shutting down. Forwarded terminating signals
+    // leave no other trace.
+    fn stopped(&self) -> RunOutcome {
+        if self.control.is_stopped() {
+            RunOutcome::StoppedByHandle
+        } else if self
+            .run_deadline
+            .is_some_and(|deadline| self.now() >= deadline)
+        {
+            RunOutcome::TotalTimeout
+        } else {
+            RunOutcome::StoppedBySignal
+        }
+    }
+
+    #[cfg(unix)]
+    fn is_interrupted(&self) -> bool {
+        self.ctrlc_shutdown
+            .as_ref()
+            .is_some_and(SignalForwarder::any_received)
+    }
+
+    #[cfg(not(unix))]
+    fn is_interrupted(&self) -> bool {
+        false
+    }
+
+    // Sleeps for `duration`, waking up as soon as a request comes in through a
+    // handle. Reloads are applied here, and only show up as a restart request
+    // when the child has to be started differently.
+    fn wait(&mut self, duration: Duration) -> Option<Request> {
+        let deadline = self.now() + duration;
+        loop {
+            match self.wait_until(deadline) {
+                Some(Request::Reload) => {
+                    if self.apply_reload() {
+                        return Some(Request::Restart);
+                    }
+                }
+                request => return request,
+            }
+        }
+    }
+
+    // Reaching the total timeout counts as a stop request
+    fn wait_until(&self, deadline: Instant) -> Option<Request> {
+        let duration = deadline.saturating_duration_since(self.now());
+        if let Some(run_deadline) = self.run_deadline {
+            let remaining = run_deadline.saturating_duration_since(self.now());
+            if remaining <= duration {
+                return self.wait_for_request(remaining).or(Some(Request::Stop));
+            }
+        }
+        self.wait_for_request(duration)
+    }
+
+    // Like `wait`, relaying the signals received meanwhile to the child. Once
+    // it was sent a terminating signal, waits for it to exit then stops.
+    #[cfg(unix)]
+    fn wait_forwarding(&mut self, duration: Duration, child: &mut RunningChild) -> Option<Request> {
+        if self.signal_forwarder.is_none() {
+            return self.wait(duration);
+        }
+        let deadline = self.now() + duration;
+        let mut terminating = false;
+        loop {
+            let remaining = deadline.saturating_duration_since(self.now());
+            let request = match terminating {
+                true => self.wait(SHUTDOWN_POLL_INTERVAL),
+                false => self.wait(SHUTDOWN_POLL_INTERVAL.min(remaining)),
+            };
+            if let Some(forwarder) = &mut self.signal_forwarder {
+                terminating |= forwarder.forward(|signal| child.signal(signal));
+            }
+            if request == Some(Request::Stop) || terminating && child.has_exited() {
+                return Some(Request::Stop);
+            }
+            if !terminating && (request.is_some() || self.now() >= deadline) {
+                return request;
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn wait_forwarding(
+        &mut self,
+        duration: Duration,
+        _child: &mut RunningChild,
+    ) -> Option<Request> {
+        self.wait(duration)
+    }
+
+    // Returns whether the child has to be restarted for the changes to apply
+    fn apply_reload(&mut self) -> bool {
+        let Some(config) = self.control.take_reload() else {
+            return false;
+        };
+        if let Some(check_interval) = config.check_interval {
+            self.check_interval = check_interval;
+        }
+        if let Some(backoff_time) = config.backoff_time {
+            self.backoff_time = backoff_time;
+        }
+        if let Some(checks) = config.checks {
+            // The check of the dynamic port stays along with the fixed tests
+            let port_check = self
+                .port
+                .map(|port| TcpCheck::local_port(port).name().to_string());
+            let (kept, replaced) = std::mem::take(&mut self.tests)
+                .into_iter()
+                .partition(|test| port_check.as_deref() == Some(test.check.name()));
+            self.tests = kept;
+            self.tests
+                .extend(checks.into_iter().map(ReloadCheck::into_scheduled));
+            // Removed tests no longer show up in the stats
+            let removed: Vec<&ScheduledCheck<S>> = replaced
+                .iter()
+                .filter(|old| {
+                    !self
+                        .tests
+                        .iter()
+                        .any(|new| new.check.name() == old.check.name())
+                })
+                .collect();
+            self.test_stats
+                .retain(|stats| !removed.iter().any(|test| test.check.name() == stats.name));
+            self.publish_stats();
+        }
+
+        let mut respawn = false;
+        if let Some(args) = config.args.filter(|args| *args != self.args) {
+            self.args = args;
+            respawn = true;
+        }
+        if let Some(env) = config.env.filter(|env| *env != self.env) {
+            self.env = env;
+            respawn = true;
+        }
+        respawn
+    }
+
+    // Blocks while paused. Returns false once shutting down.
+    fn wait_resumed(&self) -> bool {
+        while self.control.is_paused() && !self.is_shutting_down() {
+            self.control.wait_paused(SHUTDOWN_POLL_INTERVAL);
+        }
+        !self.is_shutting_down()
+    }
+
+    // The shutdown flag and signals can't notify us, so they are polled
+    // instead
+    fn wait_for_request(&self, duration: Duration) -> Option<Request> {
+        if let Some(clock) = &self.clock {
+            if self.is_shutting_down() {
+                return Some(Request::Stop);
+            }
+            let request = self.control.wait_timeout(Duration::ZERO);
+            if request.is_none() {
+                clock.sleep(duration);
+            }
+            return request;
+        }
+        let polled = self.shutdown.is_some();
+        #[cfg(unix)]
+        let polled = polled || self.ctrlc_shutdown.is_some();
+        #[cfg(feature = "tokio")]
+        let polled = polled || self.cancellation_token.is_some();
+        if !polled {
+            return self.control.wait_timeout(duration);
+        }
+
+        let deadline = Instant::now() + duration;
+        loop {
+            if self.is_shutting_down() {
+                return Some(Request::Stop);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return None;
+            }
+            let request = self
+                .control
+                .wait_timeout(SHUTDOWN_POLL_INTERVAL.min(deadline - now));
+            if request.is_some() {
+                return request;
+            }
+        }
+    }
+
+    // The program and arguments the next child will be started with
+    fn command_line(&self) -> (&OsStr, &[OsString]) {
+        match &self.fallback_command {
+            Some((command, _)) if self.using_fallback => (&command[0], &command[1..]),
+            _ => (&self.process, &self.args),
+        }
+    }
+
+    pub(crate) fn allocate_port(&mut self) -> Result<(), SupervisorError> {
+        let Some(env_var) = self.dynamic_port.clone().filter(|_| self.port.is_none()) else {
+            return Ok(());
+        };
+        // The port is free again once the listener is dropped, for the child
+        // to bind it
+        let port = TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .map_err(SupervisorError::DynamicPort)?
+            .port();
+        self.port = Some(port);
+        self.env.push((env_var, port.to_string().into()));
+        self.template_vars.push(("port".into(), port.to_string()));
+        self.tests.push(ScheduledCheck {
+            check: S::boxed_send_check(Box::new(TcpCheck::local_port(port))),
+            every: None,
+            due: None,
+            advisory: false,
+        });
+        self.control.update_stats(|stats| stats.port = Some(port));
+        Ok(())
+    }
+
+    // Published for reloads to be checked against, since they can't replace
+    // these tests
+    fn publish_fixed_test_names(&self) {
+        let mut names: Vec<String> = self
+            .fixed_test_names()
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        if let Some(port) = self.port {
+            names.push(TcpCheck::local_port(port).name().to_string());
+        }
+        self.control.update_stats(|stats| stats.fixed_tests = names);
+    }
+
+    // Values for the placeholders in the arguments and environment, expanded
+    // again on every spawn
+    fn template_vars(&self) -> Vec<(&str, String)> {
+        let mut vars = vec![
+            ("name", self.name.clone().unwrap_or_default()),
+            ("restart_count", self.restarts.to_string()),
+        ];
+        if let Some(pid_file) = &self.pid_file {
+            vars.push(("pid_file", pid_file.display().to_string()));
+        }
+        vars.extend(
+            self.template_vars
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.clone())),
+        );
+        vars
+    }
+
+    fn command(&self) -> Result<Command, SupervisorError> {
+        let (program, args) = self.command_line();
+        let vars = self.template_vars();
+        let mut command = Command::new(program);
+        command.args(args.iter().map(|arg| template::expand_os(arg, &vars)));
+        for path in &self.env_files {
+            let env = envfile::read(path).map_err(|source| SupervisorError::EnvFile {
+                path: path.clone(),
+                source,
+            })?;
+            command.envs(env);
+        }
+        command.envs(
+            self.env
+                .iter()
+                .map(|(key, value)| (key, template::expand_os(value, &vars))),
+        );
+        // Inside a chroot, the child moves to its working directory itself
+        #[cfg(target_os = "linux")]
+        let working_dir = self.working_dir.as_ref().filter(|_| self.chroot.is_none());
+        #[cfg(not(target_os = "linux"))]
+        let working_dir = self.working_dir.as_ref();
+        if let Some(working_dir) = working_dir {
+            command.current_dir(working_dir);
+        }
+        Ok(command)
+    }
+
+    fn spawn_error(&self, command: &Command, source: io::Error) -> SupervisorError {
+        SupervisorError::Spawn {
+            name: self.name.clone(),
+            program: self.secrets.redact_os(command.get_program()),
+            args: command
+                .get_args()
+                .map(|arg| self.secrets.redact_os(arg))
+                .collect(),
+            source,
+        }
+    }
+
+    // How the next child will be started, after templating, dynamic ports
+    // and env files. Fails when an env file can't be read.
+    pub fn effective_command(&self) -> Result<EffectiveCommand, SupervisorError> {
+        Ok(EffectiveCommand::new(&self.command()?, &self.secrets))
+    }
+
+    // Fails early with a clear error when the program can't be found or
+    // run, instead of on every spawn
+    pub(crate) fn preflight(&self) -> Result<(), SupervisorError> {
+        let command = self.command()?;
+        // The program is only known from inside the chroot
+        #[cfg(target_os = "linux")]
+        if self.chroot.is_some() {
+            return Ok(());
+        }
+        let result = self.backend.lock().unwrap().preflight(&command);
+        result.map_err(|source| self.spawn_error(&command, source))
+    }
+
+    pub(crate) fn spawn(&self) -> Result<RunningChild, SupervisorError> {
+        for (src, dst) in &self.reset_dirs {
+            resetdir::reset(src, dst).map_err(SupervisorError::ResetDir)?;
+        }
+        let mut command = self.command()?;
+        let scratch_dir = match &self.scratch_dir {
+            Some(template) => {
+                let path =
+                    std::env::temp_dir().join(template::expand(template, &self.template_vars()));
+                let scratch_dir = ScratchDir::create(path).map_err(SupervisorError::ScratchDir)?;
+                command.env(SCRATCH_DIR_ENV, scratch_dir.path());
+                if !self.env.iter().any(|(key, _)| key == "TMPDIR") {
+                    command.env("TMPDIR", scratch_dir.path());
+                }
+                Some(scratch_dir)
+            }
+            None => None,
+        };
+        // Entered before any chroot hides the cgroup filesystem
+        #[cfg(all(feature = "cgroup", target_os = "linux"))]
+        let cgroup = match &self.cgroup {
+            Some((parent, limits)) => {
+                let name = self.name.as_deref().unwrap_or("child");
+                let name = format!("{}-{}-{}", name, std::process::id(), self.restarts + 1);
+                let cgroup = cgroup::ChildCgroup::create(parent, &name, limits)
+                    .and_then(|cgroup| cgroup.enter_on_spawn(&mut command).map(|_| cgroup))
+                    .map_err(SupervisorError::Cgroup)?;
+                Some(cgroup)
+            }
+            None => None,
+        };
+        #[cfg(target_os = "linux")]
+        if self.chroot.is_some() || !self.namespaces.is_empty() {
+            let root = self.chroot.as_deref();
+            let working_dir = self.working_dir.as_deref();
+            if let Err(e) = linux::isolate(&mut command, self.namespaces, root, working_dir) {
+                return Err(self.spawn_error(&command, e));
+            }
+        }
+        #[cfg(unix)]
+        if self.core_dumps.is_some() {
+            coredump::enable(&mut command);
+        }
+        #[cfg(windows)]
+        windows::new_process_group(&mut command);
+        #[cfg(unix)]
+        if let Some(nice) = self.nice {
+            unix::set_nice(&mut command, nice);
+        }
+        #[cfg(unix)]
+        if let Some(umask) = self.umask {
+            unix::set_umask(&mut command, umask);
+        }
+        #[cfg(target_os = "linux")]
+        if !self.cpu_affinity.is_empty() {
+            if let Err(e) = linux::set_cpu_affinity(&mut command, &self.cpu_affinity) {
+                return Err(self.spawn_error(&command, e));
+            }
+        }
+        #[cfg(target_os = "linux")]
+        if let Some((class, level)) = self.io_priority {
+            if let Err(e) = linux::set_io_priority(&mut command, class, level) {
+                return Err(self.spawn_error(&command, e));
+            }
+        }
+        if let Some(stdin) = &self.stdin {
+            match stdin.stdio() {
+                Ok(stdio) => command.stdin(stdio),
+                Err(e) => return Err(self.spawn_error(&command, e)),
+            };
+        }
+        if self.stdio_watchdog.is_some() {
+            command.stdin(Stdio::piped()).stdout(Stdio::piped());
+        }
+        if self.captures_output() {
+            command.stdout(Stdio::piped()).stderr(Stdio::piped());
+        }
+        let mut backend = self.backend.lock().unwrap();
+        let pid = match backend.spawn(&mut command) {
+            Ok(pid) => pid,
+            Err(e) => return Err(self.spawn_error(&command, e)),
+        };
+        let child = backend.take_child(pid);
+        drop(backend);
+        let mut running = match child {
+            Some(child) => self.prepare(child),
+            None => RunningChild::from_backend(pid, self.backend.clone(), self.now()),
+        };
+        if let Some(pid_file) = &self.pid_file {
+            if let Err(e) = std::fs::write(pid_file, pid.to_string()) {
+                let _ = running.kill(Duration::ZERO, self.kill_timeout);
+                return Err(SupervisorError::PidFile(e));
+            }
+        }
+        #[cfg(windows)]
+        if let Some(child) = running.child.as_ref().filter(|_| self.job_object) {
+            match windows::JobObject::assign(child) {
+                Ok(job) => running.job = Some(job),
+                Err(e) => {
+                    let _ = running.kill(Duration::ZERO, self.kill_timeout);
+                    return Err(SupervisorError::JobObject(e));
+                }
+            }
+        }
+        #[cfg(all(feature = "cgroup", target_os = "linux"))]
+        {
+            running.cgroup = cgroup;
+        }
+        running.scratch_dir = scratch_dir;
+        Ok(running)
+    }
+
+    // Hooks up the stdio of a freshly spawned child
+    fn prepare(&self, mut child: std::process::Child) -> RunningChild {
+        if let Some(stdin) = &self.stdin {
+            stdin.feed(child.stdin.take());
+        }
+
+        let output = self
+            .output_tail
+            .map(OutputTail::new)
+            .unwrap_or_default()
+            .with_streams(self.control.streams.clone(), self.restarts + 1);
+        #[cfg(all(feature = "syslog", unix))]
+        let output = output.with_syslog(
+            self.syslog
+                .as_ref()
+                .and_then(|syslog| syslog.sender(child.id())),
+        );
+        #[cfg(feature = "tracing")]
+        let output = match self.output_tracing {
+            true => output.with_tracing(output::TracedChild {
+                supervisor: self.name.clone(),
+                pid: child.id(),
+                incarnation: self.restarts + 1,
+            }),
+            false => output,
+        };
+        #[cfg(feature = "output-match")]
+        let output = if self.output_matchers.is_empty() {
+            output
+        } else {
+            output.with_matcher(matcher::LineMatcher::new(self.output_matchers.clone()))
+        };
+        let watchdog = self
+            .stdio_watchdog
+            .map(|interval| StdioWatchdog::start(&mut child, interval, output.clone()));
+        // With a watchdog, stdout is read by the watchdog
+        if let Some(stdout) = child.stdout.take() {
+            output::capture(stdout, false, output.clone());
+        }
+        if let Some(stderr) = child.stderr.take() {
+            output::capture(stderr, true, output.clone());
+        }
+        let mut running = RunningChild::new(child, watchdog, self.now());
+        running.output = output;
+        running
+    }
+
+    fn captures_output(&self) -> bool {
+        #[cfg(feature = "output-match")]
+        if !self.output_matchers.is_empty() {
+            return true;
+        }
+        #[cfg(feature = "tracing")]
+        if self.output_tracing {
+            return true;
+        }
+        #[cfg(all(feature = "syslog", unix))]
+        if self.syslog.is_some() {
+            return true;
+        }
+        self.output_tail.is_some()
+    }
+
+    // False until the child printed a line marking it ready, when waiting for
+    // one with `OutputAction::MarkReady`
+    pub(crate) fn is_ready(&self, running: &RunningChild) -> bool {
+        #[cfg(feature = "output-match")]
+        if self.output_matchers.waits_for_ready() {
+            return running
+                .output
+                .matcher()
+                .is_some_and(|matcher| matcher.is_ready());
+        }
+        let _ = running;
+        true
+    }
+
+    // Runs every due test against the child and returns the most severe
+    // outcome. Sequential tests stop at the first failure. Only tests with
+    // their own interval run outside of `regular` checks.
+    pub(crate) fn run_tests(&mut self, running: &mut RunningChild, regular: bool) -> TestOutcome {
+        macro_rules! report {
+            ($outcome:expr, $name:expr, $result:expr) => {
+                report!($outcome, $name, $result, None, None)
+            };
+            ($outcome:expr, $name:expr, $result:expr, $latency:expr, $panic:expr) => {{
+                let result: TestOutcome = $result;
+                let panic: Option<String> = $panic;
+                report!(@advisory $name, result, $latency, &panic);
+                if result.is_failure() && !$outcome.is_failure() {
+                    self.last_failure = Some($name.to_string());
+                    self.last_panic = panic;
+                }
+                $outcome = $outcome.worst(result);
+            }};
+            // Reports the outcome of a test without it counting for the check
+            (@advisory $name:expr, $result:expr, $latency:expr, $panic:expr) => {{
+                let result: TestOutcome = $result;
+                if let Some(message) = $panic {
+                    let message = &self.secrets.redact(message);
+                    event!(self.on_test_panic, $name, message);
+                    if let Some(json_log) = &mut self.json_log {
+                        json_log.write(self.name.as_deref(), LogEvent::TestPanic($name, message));
+                    }
+                }
+                if result == TestOutcome::Healthy {
+                    event!(self.on_test_ok, $name);
+                } else {
+                    event!(self.on_test_error, $name);
+                }
+                let stats = outcome::record(&mut self.test_stats, $name, result, $latency);
+                // Not `self.log`, which would borrow the tests being iterated
+                if let Some(json_log) = &mut self.json_log {
+                    json_log.write(self.name.as_deref(), LogEvent::Test(stats));
+                }
+            }};
+        }
+
+        event!(self.on_test_start);
+        let mut outcome = TestOutcome::Healthy;
+
+        #[cfg(feature = "output-match")]
+        if let Some(matcher) = running.output.matcher().filter(|_| regular) {
+            if self.output_matchers.reports_failures() {
+                report!(outcome, matcher::OUTPUT_MATCH_TEST, matcher.outcome());
+            }
+        }
+        // The other tests wait until the child says it is ready
+        if !outcome.is_failure() && !self.is_ready(running) {
+            return outcome;
+        }
+
+        if let Some(watchdog) = running.watchdog.as_ref().filter(|_| regular) {
+            report!(
+                outcome,
+                STDIO_WATCHDOG_TEST,
+                TestOutcome::from(!watchdog.is_failing())
+            );
+        }
+
+        #[cfg(unix)]
+        if regular && running.is_adopted() {
+            report!(
+                outcome,
+                unix::ADOPTED_ALIVE_TEST,
+                TestOutcome::from(unix::is_alive(running.id()))
+            );
+        }
+
+        // Concurrent tests run in the background while sequential tests run
+        // here, and are allowed until the deadline to complete
+        let concurrent = (regular && !self.concurrent_tests.is_empty()).then(|| {
+            let deadline = Instant::now() + self.test_deadline.unwrap_or(self.check_interval);
+            (self.concurrent_tests.start(running.id()), deadline)
+        });
+
+        // The tests added by the user are aggregated according to the policy,
+        // while a failure of the ones above always fails the check
+        let mut tests = outcome;
+        let mut passed = 0;
+
+        // Sequential tests aren't run against adopted processes
+        if !running.is_adopted() {
+            let now = self.now();
+            for test in self.tests.iter_mut() {
+                if outcome.is_failure() || self.test_aggregation.is_decided(tests) {
+                    break;
+                }
+                if !test.is_due(now, regular) {
+                    continue;
+                }
+                test.due = test.every.map(|every| now + every);
+                let mut info = running.info(now, self.clock.as_deref()).unwrap();
+                let started = Instant::now();
+                let result =
+                    panic::catch_unwind(AssertUnwindSafe(|| test.check.try_check(&mut info)));
+                let latency = Some(started.elapsed());
+                let mut sub_results = test.check.sub_results();
+                self.secrets.redact_sub_results(&mut sub_results);
+                if !sub_results.is_empty() {
+                    event!(self.on_sub_results, test.check.name(), &sub_results);
+                }
+                let (result, panic) = match result {
+                    Ok(Ok(result)) => (result, None),
+                    Ok(Err(error)) => {
+                        let message = &self.secrets.redact(&error.to_string());
+                        event!(self.on_probe_error, test.check.name(), message);
+                        if let Some(json_log) = &mut self.json_log {
+                            let event = LogEvent::ProbeError(test.check.name(), message);
+                            json_log.write(self.name.as_deref(), event);
+                        }
+                        (TestOutcome::Ignore, None)
+                    }
+                    Err(payload) => (
+                        TestOutcome::Unhealthy,
+                        Some(check::panic_message(&*payload)),
+                    ),
+                };
+                if test.advisory {
+                    report!(@advisory test.check.name(), result, latency, &panic);
+                    continue;
+                }
+                report!(tests, test.check.name(), result, latency, panic);
+                passed += usize::from(result == TestOutcome::Healthy);
+            }
+        }
+
+        #[cfg(feature = "tokio")]
+        if regular
+            && !self.async_tests.is_empty()
+            && !outcome.is_failure()
+            && !self.test_aggregation.is_decided(tests)
+        {
+            let deadline = Instant::now() + self.test_deadline.unwrap_or(self.check_interval);
+            let probe = Probe {
+                pid: running.id(),
+                port: self.port,
+                uptime: self.uptime_of(running),
+            };
+            let results = self.async_tests.run(&probe, deadline);
+            for (name, (result, latency, panic)) in self.async_tests.names().zip(results) {
+                report!(tests, name, result, latency, panic);
+                passed += usize::from(result == TestOutcome::Healthy);
+            }
+        }
+
+        if let Some((batch, deadline)) = concurrent {
+            let results = batch.collect(deadline);
+            for (name, (result, latency, panic)) in self.concurrent_tests.names().zip(results) {
+                report!(tests, name, result, latency, panic);
+                passed += usize::from(result == TestOutcome::Healthy);
+            }
+        }
+        outcome = outcome.worst(self.test_aggregation.aggregate(tests, passed));
+
+        if outcome.is_failure() {
+            self.consecutive_failures += 1;
+            if let Some((_, after_failures)) = self.fallback_command {
+                self.using_fallback |= self.consecutive_failures >= after_failures;
+            }
+        } else {
+            self.consecutive_failures = 0;
+        }
+        if !outcome.is_failure()
+            && self
+                .backoff_reset_after
+                .is_some_and(|reset_after| self.uptime_of(running) >= reset_after)
+        {
+            // Starts over as if the child had never failed
+            self.restart_times = self.restart_limit;
+            self.early_exits = 0;
+            if let Some(breaker) = &mut self.circuit_breaker {
+                breaker.reset();
+            }
+        }
+        self.publish_stats();
+        if !outcome.is_failure() {
+            self.control.update_stats(|stats| stats.ready = true);
+            event!(self.on_tests_passing);
+        }
+        outcome
+    }
+
+    pub(crate) fn restart_context(
+        &self,
+        child: &RunningChild,
+        failed_test: Option<String>,
+    ) -> RestartContext {
+        RestartContext {
+            reason: match failed_test {
+                Some(_) => RestartReason::FailedTest,
+                None => RestartReason::Requested,
+            },
+            test_panic: self
+                .last_panic
+                .as_ref()
+                .filter(|_| failed_test.is_some())
+                .map(|message| self.secrets.redact(message)),
+            failed_test,
+            output_tail: child
+                .output
+                .snapshot()
+                .iter()
+                .map(|line| self.secrets.redact(line))
+                .collect(),
+            ..RestartContext::new(
+                self.name.clone(),
+                self.restarts + 1,
+                self.uptime_of(child),
+                child.id(),
+            )
+        }
+    }
+
+    pub(crate) fn notify_restart(&mut self, context: &RestartContext) {
+        let context = &context.emitted();
+        self.restarts += 1;
+        let record = RestartRecord::from(context);
+        if let Some(path) = &self.history_file {
+            // Like the other logs, failing to write it doesn't stop the
+            // supervision
+            let _ = history::append(path, &record);
+        }
+        self.control.update_stats(|stats| {
+            history::push(&mut stats.history, record, self.history_capacity);
+        });
+        self.publish_stats();
+        self.log(LogEvent::Restart(context));
+        self.script_hooks.run_restart(HookEvent::Restart, context);
+        for notifier in &mut self.notifiers {
+            notifier.notify(NotifyEvent::Restart, context);
+        }
+        event!(self.on_restart, context);
+    }
+
+    pub(crate) fn notify_spawn(&mut self, child: &RunningChild) {
+        self.script_hooks
+            .run_spawn(self.name.as_deref(), child.id(), self.restarts + 1);
+        self.log(LogEvent::Spawn(child.id()));
+        event!(self.on_spawn, child.id());
+    }
+
+    // Kills and reaps the child, returning how it ended
+    pub(crate) fn terminate(
+        &mut self,
+        child: &mut RunningChild,
+    ) -> Result<Option<ExitStatus>, SupervisorError> {
+        self.terminate_within(child, self.kill_timeout)
+    }
+
+    // Like `terminate`, but leaves the child at most `grace` to exit after
+    // the SIGTERM. Without any left, it is killed right away.
+    pub(crate) fn terminate_within(
+        &mut self,
+        child: &mut RunningChild,
+        grace: Duration,
+    ) -> Result<Option<ExitStatus>, SupervisorError> {
+        match child.kill(grace.min(self.kill_timeout), self.kill_timeout) {
+            Ok(status) => {
+                if let Some(status) = status {
+                    self.exited(status);
+                }
+                Ok(status)
+            }
+            Err(_) => Err(SupervisorError::Unkillable {
+                name: self.name.clone(),
+                pid: child.id(),
+            }),
+        }
+    }
+
+    // Interrupted supervisors give the child a chance to exit cleanly
+    fn stop_child(
+        &mut self,
+        child: &mut RunningChild,
+    ) -> Result<Option<ExitStatus>, SupervisorError> {
+        #[cfg(unix)]
+        if self.is_interrupted() {
+            child.signal(libc::SIGTERM);
+            let deadline = self.now() + GRACEFUL_STOP_TIMEOUT;
+            while !child.has_exited() && self.now() < deadline {
+                match &self.clock {
+                    Some(clock) => clock.sleep(SHUTDOWN_POLL_INTERVAL),
+                    None => thread::sleep(SHUTDOWN_POLL_INTERVAL),
+                }
+            }
+        }
+        self.terminate(child)
+    }
+
+    pub(crate) fn notify_no_restart(&mut self, context: &RestartContext, exhausted: bool) {
+        let context = &context.emitted();
+        self.given_up = Some(context.clone());
+        if exhausted {
+            event!(self.on_restart_exhausted, context);
+        }
+        self.log(LogEvent::NoRestart(context));
+        self.script_hooks.run_restart(HookEvent::NoRestart, context);
+        for notifier in &mut self.notifiers {
+            notifier.notify(NotifyEvent::NoRestart, context);
+        }
+        event!(self.on_no_restart, context);
+    }
+
+    // Waits before the restart with the given attempt number, telling about it
+    fn back_off(&mut self, delay: Duration, attempt: u64) -> Option<Request> {
+        self.log(LogEvent::Backoff(delay, attempt));
+        event!(self.on_backoff, delay, attempt);
+        let request = self.wait(delay);
+        event!(self.on_backoff_end, attempt);
+        request
+    }
+
+    fn exited(&mut self, status: ExitStatus) {
+        self.log(LogEvent::Exit(status));
+        event!(self.on_exit, status);
+    }
+
+    fn log(&mut self, event: LogEvent) {
+        if let Some(json_log) = &mut self.json_log {
+            json_log.write(self.name.as_deref(), event);
+        }
+    }
+
+    fn record_health(&mut self, child: &RunningChild, healthy: bool) {
+        let now = self.now();
+        let Some(detector) = &mut self.flap_detector else {
+            return;
+        };
+        if !detector.record(healthy, now) {
+            return;
+        }
+        match detector.action() {
+            FlapAction::Notify => {}
+            FlapAction::ExtendBackoff(extra) => self.flap_backoff = extra,
+            FlapAction::PauseRestarts => self.pause_on_failure = true,
+        }
+        let context = self
+            .restart_context(child, self.last_failure.clone())
+            .emitted();
+        self.log(LogEvent::Flapping(&context));
+        for notifier in &mut self.notifiers {
+            notifier.notify(NotifyEvent::Flapping, &context);
+        }
+        event!(self.on_flapping, &context);
+    }
+
+    // None unless collecting core dumps and the child dumped one
+    fn collect_core_dump(
+        &self,
+        child: &RunningChild,
+        exit_status: Option<ExitStatus>,
+    ) -> Option<PathBuf> {
+        #[cfg(unix)]
+        {
+            let dir = self.core_dumps.as_ref()?;
+            let working_dir = match &self.working_dir {
+                Some(working_dir) => working_dir.clone(),
+                None => std::env::current_dir().ok()?,
+            };
+            let (program, _) = self.command_line();
+            let crash = coredump::Crash {
+                name: self.name.as_deref(),
+                program,
+                pid: child.id(),
+                status: exit_status?,
+                working_dir: &working_dir,
+            };
+            coredump::collect(dir, &crash)
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = (child, exit_status);
+            None
+        }
+    }
+
+    // Restarts the child right away, without using up the restart budget
+    fn restart_now(&mut self, child: &mut RunningChild) -> Result<Operation, SupervisorError> {
+        let mut context = self.restart_context(child, None);
+        context.exit_status = self.terminate(child)?;
+        self.notify_restart(&context);
+        Ok(Operation::Restart)
+    }
+
+    // Watches the child for the spawn grace period, failing if it exits.
+    // Returns the operation to perform if a request came in meanwhile.
+    fn watch_spawn(
+        &mut self,
+        child: &mut RunningChild,
+    ) -> Result<Option<Operation>, SupervisorError> {
+        let deadline = child.started + self.spawn_grace_period;
+        loop {
+            if let Some(status) = child.try_wait() {
+                self.exited(status);
+                return Err(SupervisorError::ExitedOnSpawn {
+                    name: self.name.clone(),
+                    status,
+                });
+            }
+            let now = self.now();
+            if now >= deadline {
+                return Ok(None);
+            }
+            match self.wait(SPAWN_POLL_INTERVAL.min(deadline - now)) {
+                Some(Request::Stop) => {
+                    self.stop_child(child)?;
+                    return Ok(Some(Operation::NoRestart(self.stopped())));
+                }
+                Some(Request::Restart) => return self.restart_now(child).map(Some),
+                Some(Request::Pause) => {
+                    self.terminate(child)?;
+                    return Ok(Some(Operation::Pause));
+                }
+                // Reloads are applied while waiting
+                None | Some(Request::Reload) => {}
+            }
+        }
+    }
+
+    fn next_check_interval(&self, child: &RunningChild) -> Duration {
+        match self.startup_checks {
+            Some((interval, period)) if self.uptime_of(child) < period => interval,
+            _ => self.check_interval,
+        }
+    }
+
+    // When the next regular check is due, an interval after `from` or on the
+    // next boundary of the wall clock
+    fn next_regular_check(&self, child: &RunningChild, from: Instant) -> Instant {
+        let interval = self.next_check_interval(child);
+        if !self.aligned_checks {
+            return from + interval;
+        }
+        let since_epoch = self
+            .system_time()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let past_boundary = since_epoch.as_nanos() % interval.as_nanos();
+        self.now() + interval - Duration::from_nanos(past_boundary as u64)
+    }
+
+    fn test_loop(&mut self, child: &mut RunningChild) -> Result<Operation, SupervisorError> {
+        if self.fail_fast_on_spawn {
+            if let Some(operation) = self.watch_spawn(child)? {
+                return Ok(operation);
+            }
+        }
+
+        let mut regular_due = match self.initial_delay {
+            Some(initial_delay) => child.started + initial_delay,
+            None => self.next_regular_check(child, child.started),
+        };
+        for test in self.tests.iter_mut() {
+            test.due = test
+                .every
+                .map(|every| child.started + self.initial_delay.unwrap_or(every));
+        }
+        loop {
+            let next_due = self
+                .tests
+                .iter()
+                .filter_map(|test| test.due)
+                .fold(regular_due, Instant::min);
+            let interval = next_due.saturating_duration_since(self.now());
+            let remaining_uptime = self
+                .max_uptime
+                .map(|max_uptime| max_uptime.saturating_sub(self.uptime_of(child)));
+            let timeout = match remaining_uptime {
+                Some(remaining) => remaining.min(interval),
+                None => interval,
+            };
+
+            match self.wait_forwarding(timeout, child) {
+                Some(Request::Stop) => {
+                    self.stop_child(child)?;
+                    return Ok(Operation::NoRestart(self.stopped()));
+                }
+                // Scheduled restarts are not failures, so they neither use up
+                // the restart budget nor wait for the backoff time
+                None if remaining_uptime.is_some_and(|remaining| remaining <= timeout) => {
+                    return self.restart_now(child);
+                }
+                Some(Request::Restart) => return self.restart_now(child),
+                Some(Request::Pause) => {
+                    self.terminate(child)?;
+                    return Ok(Operation::Pause);
+                }
+                None | Some(Request::Reload) => {}
+            }
+
+            #[cfg(target_os = "linux")]
+            if self.reap_orphans {
+                linux::reap_orphans(child.id());
+            }
+
+            let regular = self.now() >= regular_due;
+            let mut outcome = self.run_tests(child, regular);
+            // Exiting fails the check, even without a test to notice it
+            let exited = !outcome.is_failure() && child.has_exited();
+            if exited {
+                outcome = TestOutcome::Unhealthy;
+            }
+            self.record_health(child, !outcome.is_failure());
+            if regular {
+                regular_due = self.next_regular_check(child, self.now());
+            }
+            if outcome.is_failure() && !self.is_failure_tolerated(child, outcome) {
+                let mut context = match exited {
+                    true => RestartContext {
+                        reason: RestartReason::Exited,
+                        ..self.restart_context(child, None)
+                    },
+                    false => self.restart_context(child, self.last_failure.clone()),
+                };
+                let exit_status = child.try_wait();
+                if child.was_oom_killed(exit_status) {
+                    context.reason = RestartReason::OutOfMemory;
+                }
+                context.exit_status = self.terminate(child)?;
+                context.core_dump = self.collect_core_dump(child, context.exit_status);
+
+                if outcome == TestOutcome::Fatal {
+                    self.notify_no_restart(&context, false);
+                    return Ok(Operation::NoRestart(RunOutcome::FatalTest));
+                }
+                if std::mem::take(&mut self.pause_on_failure) {
+                    self.control.set_paused(true);
+                    return Ok(Operation::Pause);
+                }
+                if !self.should_restart() {
+                    self.notify_no_restart(&context, true);
+                    return Ok(Operation::NoRestart(RunOutcome::RestartsExhausted));
+                }
+
+                let early_exit = exit_status.is_some()
+                    && self
+                        .min_uptime
+                        .is_some_and(|min_uptime| context.uptime < min_uptime);
+                self.early_exits = if early_exit { self.early_exits + 1 } else { 0 };
+
+                let now = self.now();
+                let cool_down = self
+                    .circuit_breaker
+                    .as_mut()
+                    .and_then(|breaker| breaker.record_failure(now));
+                let oom_backoff = self
+                    .oom_backoff_time
+                    .filter(|_| context.reason == RestartReason::OutOfMemory);
+                let delay = match cool_down {
+                    Some(cool_down) => Some(cool_down),
+                    None if oom_backoff.is_some() => oom_backoff,
+                    None if early_exit => Some(
+                        self.backoff_time
+                            .saturating_mul(1 << (self.early_exits - 1).min(16)),
+                    ),
+                    None if outcome == TestOutcome::RestartNow => None,
+                    None if self.backoff_policy.skips(exit_status) => None,
+                    None => Some(self.backoff_time),
+                };
+                let delay = match std::mem::take(&mut self.flap_backoff) {
+                    extra if extra.is_zero() => delay,
+                    extra => Some(delay.unwrap_or_default() + extra),
+                };
+                if let Some(delay) = delay {
+                    if self.back_off(delay, context.attempt) == Some(Request::Stop) {
+                        return Ok(Operation::NoRestart(self.stopped()));
+                    }
+                }
+                context.backoff = delay.unwrap_or_default();
+                self.notify_restart(&context);
+                return Ok(Operation::Restart);
+            }
+        }
+    }
+
+    // Waits for a job to exit, restarting it until it succeeds. Tests don't
+    // run, the exit status is all that matters.
+    fn job_loop(
+        &mut self,
+        child: &mut RunningChild,
+        max_attempts: u64,
+    ) -> Result<Operation, SupervisorError> {
+        loop {
+            match self.wait(SPAWN_POLL_INTERVAL) {
+                Some(Request::Stop) => {
+                    self.stop_child(child)?;
+                    return Ok(Operation::NoRestart(self.stopped()));
+                }
+                Some(Request::Restart) => return self.restart_now(child),
+                Some(Request::Pause) => {
+                    self.terminate(child)?;
+                    return Ok(Operation::Pause);
+                }
+                None | Some(Request::Reload) => {}
+            }
+
+            let Some(status) = child.try_wait() else {
+                continue;
+            };
+            self.exited(status);
+            if status.success() {
+                return Ok(Operation::NoRestart(RunOutcome::JobSucceeded));
+            }
+
+            let (reason, backoff) = match child.was_oom_killed(Some(status)) {
+                true => (
+                    RestartReason::OutOfMemory,
+                    self.oom_backoff_time.unwrap_or(self.backoff_time),
+                ),
+                false => (RestartReason::Exited, self.backoff_time),
+            };
+            let context = RestartContext {
+                reason,
+                exit_status: Some(status),
+                core_dump: self.collect_core_dump(child, Some(status)),
+                backoff,
+                ..self.restart_context(child, None)
+            };
+            if context.attempt >= max_attempts || !self.should_restart() {
+                self.notify_no_restart(&context, true);
+                return Ok(Operation::NoRestart(RunOutcome::RestartsExhausted));
+            }
+            if self.back_off(backoff, context.attempt) == Some(Request::Stop) {
+                return Ok(Operation::NoRestart(self.stopped()));
+            }
+            self.notify_restart(&context);
+            return Ok(Operation::Restart);
+        }
+    }
+
+    fn resume(&mut self) -> Result<(), SupervisorError> {
+        let Some(path) = &self.state_file else {
+            return Ok(());
+        };
+        let Some(state) = State::load(path).map_err(SupervisorError::StateFile)? else {
+            return Ok(());
+        };
+        self.restarts = state.restarts;
+        // Restarts made before the crash count against the budget
+        if let Some(times) = &mut self.restart_times {
+            *times = times.saturating_sub(state.restarts);
+        }
+        self.last_failure = state.last_failure;
+        #[cfg(unix)]
+        if self.readopt_on_resume && self.adopted.is_none() {
+            // Only the very process recorded, not one that got its PID since
+            self.adopted = state.pid.filter(|pid| {
+                unix::is_alive(*pid)
+                    && state.start_time.is_some()
+                    && unix::start_time(*pid) == state.start_time
+            });
+        }
+        Ok(())
+    }
+
+    fn save_state(&self, child: &RunningChild) -> Result<(), SupervisorError> {
+        let Some(path) = &self.state_file else {
+            return Ok(());
+        };
+        #[cfg(unix)]
+        let start_time = unix::start_time(child.id());
+        #[cfg(not(unix))]
+        let start_time = None;
+        State {
+            pid: Some(child.id()),
+            start_time,
+            restarts: self.restarts,
+            last_failure: self.last_failure.clone(),
+        }
+        .save(path)
+        .map_err(SupervisorError::StateFile)
+    }
+
+    // Spawns the child, unless there's a running process to adopt
+    fn start_child(&mut self) -> Result<RunningChild, SupervisorError> {
+        #[cfg(unix)]
+        if let Some(pid) = self.adopted.take() {
+            return Ok(RunningChild::adopted(pid, self.now()));
+        }
+        let child = self.spawn()?;
+        self.notify_spawn(&child);
+        Ok(child)
+    }
+
+    // Runs the child a single time, never restarting it, whatever the restart
+    // budget. The child failing ends with `RunOutcome::ChildFailed`, and the
+    // context then tells how.
+    pub fn run_once(&mut self) -> Result<SingleRun, SupervisorError> {
+        let budget = (self.restart_times, self.restart_limit);
+        let never = RestartBudget::Never.remaining();
+        (self.restart_times, self.restart_limit) = (never, never);
+        self.given_up = None;
+        let outcome = self.run();
+        (self.restart_times, self.restart_limit) = budget;
+        self.publish_stats();
+
+        let outcome = match outcome? {
+            RunOutcome::RestartsExhausted => RunOutcome::ChildFailed,
+            outcome => outcome,
+        };
+        Ok(SingleRun {
+            outcome,
+            context: self.given_up.take(),
+        })
+    }
+
+    pub fn run(&mut self) -> Result<RunOutcome, SupervisorError> {
+        self.control.update_stats(|stats| stats.finished = false);
+        let outcome = self.supervise();
+        self.control.update_stats(|stats| stats.finished = true);
+        self.control.streams.close();
+        outcome
+    }
+
+    fn supervise(&mut self) -> Result<RunOutcome, SupervisorError> {
+        self.validate()?;
+        self.resume()?;
+        self.preflight()?;
+        self.allocate_port()?;
+        self.publish_fixed_test_names();
+        if let Some(json_log) = &mut self.json_log {
+            json_log.open().map_err(SupervisorError::JsonLog)?;
+        }
+        #[cfg(all(feature = "syslog", unix))]
+        if let Some(syslog) = &mut self.syslog {
+            syslog.connect().map_err(SupervisorError::Syslog)?;
+        }
+        // The signals get their previous handlers back once run() returns,
+        // so that Ctrl-C interrupts the host process again
+        #[cfg(unix)]
+        let _forwarded_handlers = match &mut self.signal_forwarder {
+            Some(forwarder) => Some(
+                forwarder
+                    .install()
+                    .map_err(SupervisorError::SignalForwarding)?,
+            ),
+            None => None,
+        };
+        #[cfg(unix)]
+        let _ctrlc_handlers = match &mut self.ctrlc_shutdown {
+            Some(forwarder) => Some(
+                forwarder
+                    .install()
+                    .map_err(SupervisorError::SignalForwarding)?,
+            ),
+            None => None,
+        };
+        self.run_deadline = self.total_timeout.map(|timeout| self.now() + timeout);
+        self.publish_stats();
+
+        #[cfg(target_os = "linux")]
+        if self.reap_orphans && std::process::id() != 1 {
+            linux::become_subreaper().map_err(SupervisorError::Subreaper)?;
+        }
+
+        #[cfg(feature = "watch")]
+        let _watcher = if self.watch_paths.is_empty() {
+            None
+        } else {
+            Some(watch::watch(&self.watch_paths, self.handle())?)
+        };
+        #[cfg(all(feature = "control", unix))]
+        let _control_socket = match &self.control_socket {
+            Some(path) => Some(control::ControlSocket::bind(path, self.handle())?),
+            None => None,
+        };
+        #[cfg(feature = "status-server")]
+        let _status_server = match self.status_server {
+            Some(addr) => Some(status::StatusServer::bind(addr, self.handle())?),
+            None => None,
+        };
+
+        loop {
+            if !self.wait_resumed() {
+                return Ok(self.stopped());
+            }
+            let mut child = self.start_child()?;
+            self.save_state(&child)?;
+            self.publish_started(Some(&child));
+            let operation = match self.job_policy {
+                JobPolicy::Daemon => self.test_loop(&mut child),
+                JobPolicy::RestartUntilSuccess { max_attempts } => {
+                    self.job_loop(&mut child, max_attempts)
+                }
+            };
+            self.publish_started(None);
+            self.save_state(&child)?;
+            match operation? {
+                Operation::Restart | Operation::Pause => continue,
+                Operation::NoRestart(outcome) => return Ok(outcome),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc, sync::Mutex, thread};
+
+    use super::*;
+
+    #[test]
+    fn it_builds_a_process_with_check_interval() {
+        let process = SupervisedProcess::new("test").with_check_interval(Duration::from_secs(15));
+        assert_eq!(process.check_interval, Duration::from_secs(15));
+    }
+
+    #[test]
+    fn it_builds_a_process_with_backoff_time() {
+        let process = SupervisedProcess::new("test").with_backoff_time(Duration::from_secs(15));
+        assert_eq!(process.backoff_time, Duration::from_secs(15));
+    }
+
+    // Fails to build as soon as a field isn't `Send` for `Threaded`
+    #[test]
+    fn threaded_supervisors_are_send() {
+        fn assert_send<T: Send>() {}
+        assert_send::<SupervisedProcess<'static, Threaded>>();
+        assert_send::<SupervisedProcess<'_, Threaded>>();
+    }
+
+    #[test]
+    fn it_builds_a_process_with_name() {
+        let process = SupervisedProcess::new("test").with_name("worker-a");
+        assert_eq!(process.name(), Some("worker-a"));
+    }
+
+    #[test]
+    fn it_waits_for_initial_delay_before_the_first_check() {
+        let mut check_times = vec![];
+        let started = Instant::now();
+
+        let mut process = SupervisedProcess::new("sleep")
+            .with_args(vec!["5"])
+            .add_test("always false", Box::from(|_: &mut ChildInfo| false))
+            .with_initial_delay(Duration::from_millis(100))
+            .with_check_interval(Duration::from_millis(10))
+            .with_restart_times(0)
+            .on_test_start(|| check_times.push(started.elapsed()));
+
+        assert!(process.run().is_ok());
+        drop(process);
+        assert_eq!(check_times.len(), 1);
+        assert!(check_times[0] >= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn it_checks_more_often_right_after_start() {
+        let mut check_times = vec![];
+        let started = Instant::now();
+
+        let mut process = SupervisedProcess::new("sleep")
+            .with_args(["5"])
+            .add_test("always true", |_: &mut ChildInfo| true)
+            .with_check_interval(Duration::from_millis(200))
+            .with_startup_check_interval(Duration::from_millis(20), Duration::from_millis(100))
+            .on_test_start(|| check_times.push(started.elapsed()));
+        let handle = process.handle();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(350));
+            handle.stop();
+        });
+
+        assert!(process.run().is_ok());
+        drop(process);
+        // Around 5 checks during the startup period, then one more at ~300ms
+        assert!(check_times.len() >= 5 && check_times.len() <= 7);
+        let last_gap = check_times[check_times.len() - 1] - check_times[check_times.len() - 2];
+        assert!(last_gap >= Duration::from_millis(200));
+    }
+
+    #[test]
+    fn tests_run_on_their_own_interval() {
+        let cheap = Arc::new(Mutex::new(0));
+        let expensive = Arc::new(Mutex::new(0));
+        let (cheap_count, expensive_count) = (cheap.clone(), expensive.clone());
+
+        let mut process = SupervisedProcess::new("sleep")
+            .with_args(["5"])
+            .add_test_every(
+                "cheap",
+                Duration::from_millis(20),
+                move |_: &mut ChildInfo| {
+                    *cheap_count.lock().unwrap() += 1;
+                    true
+                },
+            )
+            .add_test("expensive", move |_: &mut ChildInfo| {
+                *expensive_count.lock().unwrap() += 1;
+                true
+            })
+            .with_check_interval(Duration::from_millis(100));
+        let handle = process.handle();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(250));
+            handle.stop();
+        });
+
+        assert!(process.run().is_ok());
+        assert_eq!(*expensive.lock().unwrap(), 2);
+        let cheap = *cheap.lock().unwrap();
+        assert!((10..=13).contains(&cheap), "cheap test ran {} times", cheap);
+    }
+
+    #[test]
+    fn it_builds_a_process_adding_a_test() {
+        let process = SupervisedProcess::new("test")
+            .add_test("always false", Box::from(|_child: &mut ChildInfo| false));
+        assert_eq!(process.tests.len(), 1);
+    }
+
+    #[test]
+    fn it_validates_the_configuration_on_build() {
+        assert_eq!(
+            SupervisedProcess::new("").build().err(),
+            Some(BuilderError::EmptyProcess)
+        );
+        assert_eq!(
+            SupervisedProcess::new("test")
+                .with_check_interval(Duration::ZERO)
+                .build()
+                .err(),
+            Some(BuilderError::ZeroCheckInterval)
+        );
+        assert_eq!(
+            SupervisedProcess::new("test")
+                .with_stdio_watchdog(Duration::ZERO)
+                .build()
+                .err(),
+            Some(BuilderError::ZeroWatchdogInterval)
+        );
+        assert_eq!(
+            SupervisedProcess::new("test")
+                .with_stdio_watchdog(Duration::from_secs(1))
+                .with_stdin(StdinSource::Null)
+                .build()
+                .err(),
+            Some(BuilderError::StdinWithWatchdog)
+        );
+        assert_eq!(
+            SupervisedProcess::new("test")
+                .add_test("check", Box::from(|_: &mut ChildInfo| true))
+                .add_test("check", Box::from(|_: &mut ChildInfo| true))
+                .build()
+                .err(),
+            Some(BuilderError::DuplicateTest("check".to_string()))
+        );
+        assert_eq!(
+            SupervisedProcess::new("test")
+                .with_stdio_watchdog(Duration::from_secs(1))
+                .add_test("stdio watchdog", Box::from(|_: &mut ChildInfo| true))
+                .build()
+                .err(),
+            Some(BuilderError::DuplicateTest("stdio watchdog".to_string()))
+        );
+        assert_eq!(
+            SupervisedProcess::new("test")
+                .with_circuit_breaker(CircuitBreaker::new(0, Duration::ZERO, Duration::ZERO))
+                .build()
+                .err(),
+            Some(BuilderError::ZeroCircuitBreakerFailures)
+        );
+        assert_eq!(
+            SupervisedProcess::new("test")
+                .with_fallback_command([""], 1)
+                .build()
+                .err(),
+            Some(BuilderError::EmptyFallbackCommand)
+        );
+        assert_eq!(
+            SupervisedProcess::new("test")
+                .with_job_policy(JobPolicy::RestartUntilSuccess { max_attempts: 0 })
+                .build()
+                .err(),
+            Some(BuilderError::ZeroMaxAttempts)
+        );
+        assert!(SupervisedProcess::new("test")
+            .add_test("check", Box::from(|_: &mut ChildInfo| true))
+            .build()
+            .is_ok());
+    }
+
+    #[test]
+    fn it_validates_the_configuration_on_run() {
+        let mut process = SupervisedProcess::new("");
+        assert!(matches!(
+            process.run(),
+            Err(SupervisorError::Builder(BuilderError::EmptyProcess))
+        ));
+    }
+
+    #[test]
+    fn it_reports_spawn_errors() {
+        let mut process = SupervisedProcess::new("does-not-exist")
+            .with_args(vec!["--flag", "value"])
+            .with_name("worker-a");
+        let error = process.run().unwrap_err();
+
+        assert!(matches!(
+            &error,
+            SupervisorError::Spawn { source, .. } if source.kind() == std::io::ErrorKind::NotFound
+        ));
+        assert!(error
+            .to_string()
+            .starts_with("worker-a: failed to start `does-not-exist --flag value`: "));
+    }
+
+    #[test]
+    fn event_on_restart() {
+        let restart_count: RefCell<i32> = RefCell::new(0);
+        let restart_fn = |_: &RestartContext| {
+            (*restart_count.borrow_mut()) += 1;
+        };
+
+        let mut process = SupervisedProcess::new("echo")
+            .with_args(vec!["-n"])
+            .add_test("always false", Box::from(|_: &mut ChildInfo| false))
+            .with_check_interval(Duration::from_millis(1))
+            .with_backoff_time(Duration::from_millis(1))
+            .with_restart_times(1)
+            .on_restart(restart_fn);
+
+        assert!(process.run().is_ok());
+        let guard = restart_count.borrow();
+
+        assert_eq!(*guard, 1)
+    }
+
+    #[test]
+    fn event_on_no_restart() {
+        let no_restart_count: RefCell<i32> = RefCell::new(0);
+        let no_restart_fn = |_: &RestartContext| {
+            (*no_restart_count.borrow_mut()) += 1;
+        };
+
+        let mut process = SupervisedProcess::new("echo")
+            .with_args(vec!["-n"])
+            .add_test("always false", Box::from(|_: &mut ChildInfo| false))
+            .with_check_interval(Duration::from_millis(1))
+            .with_backoff_time(Duration::from_millis(1))
+            .with_restart_times(1)
+            .on_no_restart(no_restart_fn);
+
+        assert!(process.run().is_ok());
+
+        assert_eq!(*no_restart_count.borrow(), 1);
+    }
+
+    #[test]
+    fn event_on_restart_exhausted() {
+        let mut exhausted = vec![];
+
+        let mut process = SupervisedProcess::new("sleep")
+            .with_args(["5"])
+            .add_test("always false", |_: &mut ChildInfo| false)
+            .with_check_interval(Duration::from_millis(10))
+            .with_backoff_time(Duration::from_millis(1))
+            .with_restart_times(1)
+            .on_restart_exhausted(|context: &RestartContext| exhausted.push(context.attempt));
+        assert!(process.run().is_ok());
+        drop(process);
+        assert_eq!(exhausted, vec![2]);
+
+        let mut exhausted_count = 0;
+        let mut process = SupervisedProcess::new("sleep")
+            .with_args(["5"])
+            .add_test("fatal", |_: &mut ChildInfo| TestOutcome::Fatal)
+            .with_check_interval(Duration::from_millis(10))
+            .on_restart_exhausted(|_: &RestartContext| exhausted_count += 1);
+        assert!(process.run().is_ok());
+        drop(process);
+        assert_eq!(exhausted_count, 0);
+    }
+
+    #[test]
+    fn circuit_breaker_cools_down_after_repeated_failures() {
+        let started = Instant::now();
+        let mut restart_times = vec![];
+
+        let mut process = SupervisedProcess::new("sleep")
+            .with_args(["5"])
+            .add_test("always false", |_: &mut ChildInfo| false)
+            .with_check_interval(Duration::from_millis(10))
+            .with_backoff_time(Duration::from_millis(1))
+            .with_restart_times(3)
+            .with_circuit_breaker(CircuitBreaker::new(
+                2,
+                Duration::from_secs(10),
+                Duration::from_millis(200),
+            ))
+            .on_restart(|_: &RestartContext| restart_times.push(started.elapsed()));
+        assert!(process.run().is_ok());
+        drop(process);
+
+        assert_eq!(restart_times.len(), 3);
+        assert!(restart_times[0] < Duration::from_millis(200));
+        assert!(restart_times[1] - restart_times[0] >= Duration::from_millis(200));
+        assert!(restart_times[2] - restart_times[1] < Duration::from_millis(200));
+    }
+
+    #[test]
+    fn it_switches_to_the_fallback_command_after_repeated_failures() {
+        let mut passing_count = 0;
+
+        let mut process = SupervisedProcess::new("false")
+            .add_test("still running", |child: &mut ChildInfo| {
+                matches!(child.try_wait(), Ok(None))
+            })
+            .with_fallback_command(["sleep", "5"], 2)
+            .with_check_interval(Duration::from_millis(20))
+            .with_backoff_time(Duration::from_millis(1))
+            .with_restart_times(2)
+            .on_tests_passing(|| passing_count += 1);
+        let handle = process.handle();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(150));
+            handle.stop();
+        });
+
+        assert!(process.run().is_ok());
+        assert!(process.using_fallback);
+        drop(process);
+        assert!(passing_count > 0);
+    }
+
+    #[test]
+    fn event_on_spawn() {
+        let mut pids = vec![];
+        let mut contexts = vec![];
+
+        let mut process = SupervisedProcess::new("sleep")
+            .with_args(["5"])
+            .add_test("always false", |_: &mut ChildInfo| false)
+            .with_check_interval(Duration::from_millis(10))
+            .with_backoff_time(Duration::from_millis(1))
+            .with_restart_times(1)
+            .on_spawn(|pid| pids.push(pid))
+            .on_restart(|context: &RestartContext| contexts.push(context.pid))
+            .on_no_restart(|_: &RestartContext| {});
+        assert!(process.run().is_ok());
+        drop(process);
+
+        assert_eq!(pids.len(), 2);
+        assert_eq!(contexts, vec![pids[0]]);
+        assert_ne!(pids[0], pids[1]);
+    }
+
+    #[test]
+    fn event_on_exit() {
+        let mut statuses = vec![];
+
+        let mut process = SupervisedProcess::new("sh")
+            .with_args(["-c", "exit 3"])
+            .add_test("still running", |child: &mut ChildInfo| {
+                matches!(child.try_wait(), Ok(None))
+            })
+            .with_check_interval(Duration::from_millis(50))
+            .with_restart_times(0)
+            .on_exit(|status| statuses.push(status.code()));
+        assert!(process.run().is_ok());
+        drop(process);
+
+        assert_eq!(statuses, vec![Some(3)]);
+    }
+
+    #[test]
+    fn event_on_test_error() {
+        let error_fn = |name: &str| assert_eq!("always false", name);
+
+        let mut process = SupervisedProcess::new("echo")
+            .with_args(vec!["-n"])
+            .add_test("always false", Box::from(|_: &mut ChildInfo| false))
+            .with_check_interval(Duration::from_millis(1))
+            .with_backoff_time(Duration::from_millis(1))
+            .with_restart_times(0)
+            .on_test_error(error_fn);
+
+        assert!(process.run().is_ok());
+    }
+
+    #[test]
+    fn event_on_test_run() {
+        let test_run_count: RefCell<i32> = RefCell::new(0);
+        let test_run_fn = || {
+            (*test_run_count.borrow_mut()) += 1;
+        };
+
+        let mut process = SupervisedProcess::new("echo")
+            .with_args(vec!["-n"])
+            .add_test("always false", Box::from(|_: &mut ChildInfo| false))
+            .with_check_interval(Duration::from_millis(1))
+            .with_backoff_time(Duration::from_millis(1))
+            .with_restart_times(1)
+            .on_test_start(test_run_fn);
+
+        assert!(process.run().is_ok());
+        assert_eq!(*test_run_count.borrow(), 2);
+    }
+
+    #[test]
+    fn event_on_test_ok() {
+        let mut test_ok_count = 0;
+
+        let mut process = SupervisedProcess::new("sleep")
+            .with_args(vec!["0.1"])
+            .add_test(
+                "not running",
+                Box::from(|child: &mut ChildInfo| matches!(child.try_wait(), Ok(None))),
+            )
+            .with_check_interval(Duration::from_millis(80))
+            .with_backoff_time(Duration::from_millis(80))
+            .with_restart_times(0)
+            .on_tests_passing(|| test_ok_count += 1);
+        assert!(process.run().is_ok());
+        drop(process);
+
+        assert_eq!(test_ok_count, 1);
+    }
+
+    #[test]
+    fn restart_handlers_receive_context() {
+        let mut contexts = vec![];
+        let mut no_restart_contexts = vec![];
+
+        let mut process = SupervisedProcess::new("sleep")
+            .with_args(vec!["5"])
+            .add_test("always false", Box::from(|_: &mut ChildInfo| false))
+            .with_check_interval(Duration::from_millis(20))
+            .with_backoff_time(Duration::from_millis(1))
+            .with_restart_times(1)
+            .with_name("worker-a")
+            .on_restart(|context: &RestartContext| contexts.push(context.clone()))
+            .on_no_restart(|context: &RestartContext| no_restart_contexts.push(context.clone()));
+        assert!(process.run().is_ok());
+        drop(process);
+        contexts.append(&mut no_restart_contexts);
+
+        assert_eq!(contexts.len(), 2);
+        assert_eq!(contexts[0].attempt, 1);
+        assert_eq!(contexts[1].attempt, 2);
+        assert_ne!(contexts[0].pid, contexts[1].pid);
+        for context in contexts {
+            assert_eq!(context.name.as_deref(), Some("worker-a"));
+            assert_eq!(context.failed_test.as_deref(), Some("always false"));
+            assert!(context.uptime >= Duration::from_millis(20));
+        }
+    }
+
+    #[test]
+    fn it_restarts_after_max_uptime() {
+        let mut contexts = vec![];
+
+        let mut process = SupervisedProcess::new("sleep")
+            .with_args(vec!["5"])
+            .add_test("always true", Box::from(|_: &mut ChildInfo| true))
+            .with_check_interval(Duration::from_secs(10))
+            .with_max_uptime(Duration::from_millis(50))
+            .with_restart_times(0)
+            .on_restart(|context: &RestartContext| contexts.push(context.clone()));
+        let handle = process.handle();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(180));
+            handle.stop();
+        });
+
+        assert!(process.run().is_ok());
+        drop(process);
+
+        assert_eq!(contexts.len(), 3);
+        for context in contexts {
+            assert_eq!(context.failed_test, None);
+            assert!(context.uptime >= Duration::from_millis(50));
+        }
+    }
+
+    #[test]
+    fn it_fails_fast_when_the_child_exits_on_spawn() {
+        let mut process = SupervisedProcess::new("sh")
+            .with_args(vec!["-c", "exit 3"])
+            .add_test("always true", Box::from(|_: &mut ChildInfo| true))
+            .fail_fast_on_spawn(true)
+            .with_spawn_grace_period(Duration::from_millis(500));
+
+        let started = Instant::now();
+        let error = process.run().unwrap_err();
+        assert!(started.elapsed() < Duration::from_millis(500));
+        assert!(matches!(
+            error,
+            SupervisorError::ExitedOnSpawn { status, .. } if status.code() == Some(3)
+        ));
+    }
+
+    #[test]
+    fn it_does_not_fail_fast_after_the_grace_period() {
+        let mut process = SupervisedProcess::new("sleep")
+            .with_args(vec!["0.05"])
+            .add_test("always false", Box::from(|_: &mut ChildInfo| false))
+            .with_check_interval(Duration::from_millis(1))
+            .with_restart_times(0)
+            .fail_fast_on_spawn(true)
+            .with_spawn_grace_period(Duration::from_millis(20));
+        assert!(process.run().is_ok());
+    }
+
+    #[test]
+    fn it_runs_concurrent_tests_with_a_deadline() {
+        let mut errors = vec![];
+
+        let mut process = SupervisedProcess::new("sleep")
+            .with_args(vec!["5"])
+            .add_test("always true", Box::from(|_: &mut ChildInfo| true))
+            .add_concurrent_test("alive", Box::new(|pid| pid > 0))
+            .add_concurrent_test(
+                "hanging",
+                Box::new(|_| {
+                    thread::sleep(Duration::from_secs(5));
+                    true
+                }),
+            )
+            .with_check_interval(Duration::from_millis(10))
+            .with_test_deadline(Duration::from_millis(50))
+            .with_restart_times(0)
+            .on_test_error(|name: &str| errors.push(name.to_string()));
+
+        let started = Instant::now();
+        assert!(process.run().is_ok());
+        assert!(started.elapsed() < Duration::from_secs(1));
+        drop(process);
+        assert_eq!(errors, vec!["hanging".to_string()]);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn it_runs_async_tests_with_a_deadline() {
+        let mut errors = vec![];
+
+        let mut process = SupervisedProcess::new("sleep")
+            .with_args(vec!["5"])
+            .add_async_test("alive", |probe: Probe| async move { probe.pid() > 0 })
+            .add_async_test("hanging", |_| async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                true
+            })
+            .with_check_interval(Duration::from_millis(10))
+            .with_test_deadline(Duration::from_millis(50))
+            .with_restart_times(0)
+            .on_test_error(|name: &str| errors.push(name.to_string()));
+
+        let started = Instant::now();
+        assert!(process.run().is_ok());
+        assert!(started.elapsed() < Duration::from_secs(1));
+        drop(process);
+        assert_eq!(errors, vec!["hanging".to_string()]);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn handles_resolve_futures_on_readiness_and_stop() {
+        let process = SupervisedProcess::new_send("sleep")
+            .with_args(vec!["5"])
+            .add_test("always true", |_: &mut ChildInfo| true)
+            .with_check_interval(Duration::from_millis(10));
+        let handle = process.handle();
+        let supervisor = process.start().unwrap();
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            assert!(handle.wait_ready().await);
+            assert!(handle.pid().is_some());
+            handle.stop_async().await;
+        });
+        assert!(handle.is_finished());
+        assert!(!handle.is_ready());
+        assert_eq!(
+            supervisor.join().unwrap().unwrap(),
+            RunOutcome::StoppedByHandle
+        );
+    }
+
+    #[test]
+    fn handles_stream_stdout_across_restarts() {
+        let mut process = SupervisedProcess::new("sh")
+            .with_args(vec!["-c", "echo out; echo err >&2; sleep 0.1"])
+            .add_test("still running", |child: &mut ChildInfo| {
+                matches!(child.try_wait(), Ok(None))
+            })
+            .with_output_tail(0)
+            .with_check_interval(Duration::from_millis(10))
+            .with_backoff_time(Duration::from_millis(1))
+            .with_restart_times(1);
+        let lines = process.handle().stdout_lines();
+
+        assert_eq!(process.run().unwrap(), RunOutcome::RestartsExhausted);
+        let lines: Vec<(u64, String)> = lines
+            .map(|output| (output.incarnation, output.line))
+            .collect();
+        assert_eq!(lines, vec![(1, "out".to_string()), (2, "out".to_string())]);
+    }
+
+    #[test]
+    fn env_files_are_read_again_on_every_restart() {
+        let path = std::env::temp_dir().join(format!("env-file-{}", std::process::id()));
+        std::fs::write(&path, "GREETING=hello\nNAME=env file\n").unwrap();
+        let rewritten = path.clone();
+        let mut process = SupervisedProcess::new("sh")
+            .with_args(vec!["-c", "echo \"$GREETING $NAME\"; sleep 0.1"])
+            .with_env_file(&path)
+            .with_env("NAME", "override")
+            .add_test("still running", move |child: &mut ChildInfo| {
+                let running = matches!(child.try_wait(), Ok(None));
+                if !running {
+                    std::fs::write(&rewritten, "GREETING='hi there'").unwrap();
+                }
+                running
+            })
+            .with_output_tail(0)
+            .with_check_interval(Duration::from_millis(10))
+            .with_backoff_time(Duration::from_millis(1))
+            .with_restart_times(1);
+        let lines = process.handle().stdout_lines();
+
+        assert_eq!(process.run().unwrap(), RunOutcome::RestartsExhausted);
+        let lines: Vec<String> = lines.map(|output| output.line).collect();
+        assert_eq!(lines, vec!["hello override", "hi there override"]);
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            process.run(),
+            Err(SupervisorError::EnvFile { path: missing, .. }) if missing == path
+        ));
+    }
+
+    #[test]
+    fn secrets_are_masked_in_what_is_reported() {
+        let mut contexts = vec![];
+        let mut process = SupervisedProcess::new("sh")
+            .with_args(vec!["-c", "echo $TOKEN $0; sleep 5"])
+            .with_secret_arg("s3cr3t-arg")
+            .with_secret_env("TOKEN", "s3cr3t-token")
+            .with_output_tail(1)
+            .add_test("no output", |child: &mut ChildInfo| {
+                child.output_tail().is_empty()
+            })
+            .with_check_interval(Duration::from_millis(10))
+            .with_restart_times(0)
+            .on_no_restart(|context: &RestartContext| contexts.push(context.clone()));
+        let spec = format!("{:?}", process.spec());
+        assert!(!spec.contains("s3cr3t") && spec.contains("***"));
+
+        assert_eq!(process.run().unwrap(), RunOutcome::RestartsExhausted);
+        drop(process);
+        assert_eq!(contexts[0].output_tail, vec!["*** ***"]);
+
+        let error = SupervisedProcess::new("does-not-exist")
+            .with_secret_arg("s3cr3t")
+            .run()
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "failed to start `does-not-exist ***`: does-not-exist not found in PATH"
+        );
+    }
+
+    #[test]
+    fn it_tells_the_effective_command() {
+        let process = SupervisedProcess::new("sh")
+            .with_name("web")
+            .with_args(vec!["-c", "exec server --name {name}"])
+            .with_secret_arg("s3cr3t")
+            .with_env("MODE", "{name}-prod")
+            .with_env("PATH", std::env::var_os("PATH").unwrap())
+            .with_working_dir("/tmp");
+
+        let command = process.effective_command().unwrap();
+        assert!(command.program.is_absolute() && command.program.ends_with("sh"));
+        assert_eq!(command.args, ["-c", "exec server --name web", "***"]);
+        assert_eq!(command.env, [("MODE".into(), "web-prod".into())]);
+        assert_eq!(command.working_dir, Some(PathBuf::from("/tmp")));
+        assert_eq!(
+            command.to_string(),
+            format!(
+                "cd /tmp && MODE=web-prod {} -c exec server --name web ***",
+                command.program.display()
+            )
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn children_can_get_namespaces_of_their_own() {
+        // Unsharing namespaces needs root
+        if unsafe { libc::geteuid() } != 0 {
+            return;
+        }
+        let mut process = SupervisedProcess::new("sh")
+            .with_args(vec!["-c", "wc -l < /proc/self/net/dev; sleep 0.1"])
+            .with_namespaces(NamespaceFlags::NETWORK | NamespaceFlags::UTS)
+            .add_test("still running", |child: &mut ChildInfo| {
+                matches!(child.try_wait(), Ok(None))
+            })
+            .with_output_tail(0)
+            .with_check_interval(Duration::from_millis(10))
+            .with_restart_times(0);
+        let lines = process.handle().stdout_lines();
+
+        assert_eq!(process.run().unwrap(), RunOutcome::RestartsExhausted);
+        // Only the loopback interface, after the two header lines
+        let lines: Vec<String> = lines.map(|output| output.line).collect();
+        assert_eq!(lines, vec!["3"]);
+    }
+
+    #[cfg(all(feature = "cgroup", target_os = "linux"))]
+    #[test]
+    fn each_child_gets_a_cgroup_removed_after_it() {
+        // Needs a writable cgroup v2 hierarchy
+        let Some(root) = ["/sys/fs/cgroup", "/sys/fs/cgroup/unified"]
+            .iter()
+            .map(Path::new)
+            .find(|root| root.join("cgroup.procs").exists())
+        else {
+            return;
+        };
+        if unsafe { libc::geteuid() } != 0 {
+            return;
+        }
+        let parent = root.join(format!("supervised-{}", std::process::id()));
+        let mut process = SupervisedProcess::new("sh")
+            .with_name("web")
+            .with_args(vec!["-c", "grep ^0:: /proc/self/cgroup; sleep 0.1"])
+            .with_cgroup(&parent, CgroupLimits::new())
+            .add_test("still running", |child: &mut ChildInfo| {
+                matches!(child.try_wait(), Ok(None))
+            })
+            .with_output_tail(0)
+            .with_check_interval(Duration::from_millis(10))
+            .with_restart_times(1);
+        let lines = process.handle().stdout_lines();
+
+        assert_eq!(process.run().unwrap(), RunOutcome::RestartsExhausted);
+        let lines: Vec<String> = lines.map(|output| output.line).collect();
+        let cgroups: Vec<PathBuf> = (1..=2)
+            .map(|incarnation| parent.join(format!("web-{}-{}", std::process::id(), incarnation)))
+            .collect();
+        let expected: Vec<String> = cgroups
+            .iter()
+            .map(|path| format!("0::/{}", path.strip_prefix(root).unwrap().display()))
+            .collect();
+        assert_eq!(lines, expected);
+        assert!(cgroups.iter().all(|path| !path.exists()));
+        std::fs::remove_dir(&parent).unwrap();
+    }
+
+    #[test]
+    fn fatal_outcome_stops_supervision() {
+        let mut restart_count = 0;
+        let mut no_restart_count = 0;
+
+        let mut process = SupervisedProcess::new("sleep")
+            .with_args(vec!["5"])
+            .add_test("fatal", |_: &mut ChildInfo| TestOutcome::Fatal)
+            .with_check_interval(Duration::from_millis(1))
+            .on_restart(|_: &RestartContext| restart_count += 1)
+            .on_no_restart(|_: &RestartContext| no_restart_count += 1);
+
+        assert_eq!(process.run().unwrap(), RunOutcome::FatalTest);
+        drop(process);
+        assert_eq!(restart_count, 0);
+        assert_eq!(no_restart_count, 1);
+    }
+
+    #[test]
+    fn restart_now_outcome_skips_backoff() {
+        let mut process = SupervisedProcess::new("sleep")
+            .with_args(vec!["5"])
+            .add_test("restart now", |_: &mut ChildInfo| TestOutcome::RestartNow)
+            .with_check_interval(Duration::from_millis(1))
+            .with_backoff_time(Duration::from_secs(10))
+            .with_restart_times(2);
+
+        let started = Instant::now();
+        assert!(process.run().is_ok());
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn backoff_policy_skips_backoff_on_clean_exit() {
+        let mut process = SupervisedProcess::new("true")
+            .add_test("still running", |child: &mut ChildInfo| {
+                matches!(child.try_wait(), Ok(None))
+            })
+            .with_check_interval(Duration::from_millis(10))
+            .with_backoff_time(Duration::from_secs(10))
+            .with_backoff_policy(BackoffPolicy::SkipOnCleanExit)
+            .with_restart_times(2);
+
+        let started = Instant::now();
+        assert!(process.run().is_ok());
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn children_run_with_the_nice_level_and_cpus_given() {
+        let mut process = SupervisedProcess::new("sh")
+            .with_args(vec![
+                "-c",
+                "cut -d' ' -f19 /proc/self/stat; grep Cpus_allowed_list /proc/self/status; \
+                 sleep 0.1",
+            ])
+            .with_nice(5)
+            .with_cpu_affinity(&[0])
+            .add_test("still running", |child: &mut ChildInfo| {
+                matches!(child.try_wait(), Ok(None))
+            })
+            .with_output_tail(0)
+            .with_check_interval(Duration::from_millis(10))
+            .with_restart_times(0);
+        let lines = process.handle().stdout_lines();
+
+        assert_eq!(process.run().unwrap(), RunOutcome::RestartsExhausted);
+        let lines: Vec<String> = lines.map(|output| output.line).collect();
+        assert_eq!(lines, vec!["5", "Cpus_allowed_list:\t0"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn data_dirs_are_reset_before_every_child() {
+        let dir = std::env::temp_dir().join(format!("fixture-{}", std::process::id()));
+        let (seed, data) = (dir.join("seed"), dir.join("data"));
+        std::fs::create_dir_all(&seed).unwrap();
+        std::fs::write(seed.join("rows"), "1\n").unwrap();
+        let mut process = SupervisedProcess::new("sh")
+            .with_args(vec!["-c", "cat rows; echo 2 >> rows; sleep 0.1"])
+            .with_working_dir(&data)
+            .with_reset_dir(&seed, &data)
+            .add_test("still running", |child: &mut ChildInfo| {
+                matches!(child.try_wait(), Ok(None))
+            })
+            .with_output_tail(0)
+            .with_check_interval(Duration::from_millis(10))
+            .with_backoff_time(Duration::from_millis(1))
+            .with_restart_times(1);
+        let lines = process.handle().stdout_lines();
+
+        assert_eq!(process.run().unwrap(), RunOutcome::RestartsExhausted);
+        let lines: Vec<String> = lines.map(|output| output.line).collect();
+        assert_eq!(lines, vec!["1", "1"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn children_get_a_fresh_scratch_dir_each() {
+        let template = format!("scratch-{}-{{restart_count}}", std::process::id());
+        let mut process = SupervisedProcess::new("sh")
+            .with_args(vec![
+                "-c",
+                "ls $SCRATCH_DIR; echo $SCRATCH_DIR; touch $TMPDIR/file; sleep 0.1",
+            ])
+            .with_scratch_dir(&template)
+            .add_test("still running", |child: &mut ChildInfo| {
+                matches!(child.try_wait(), Ok(None))
+            })
+            .with_output_tail(0)
+            .with_check_interval(Duration::from_millis(10))
+            .with_backoff_time(Duration::from_millis(1))
+            .with_restart_times(1);
+        let lines = process.handle().stdout_lines();
+
+        assert_eq!(process.run().unwrap(), RunOutcome::RestartsExhausted);
+        let lines: Vec<String> = lines.map(|output| output.line).collect();
+        let dirs: Vec<PathBuf> = (0..2)
+            .map(|restarts| {
+                let name = format!("scratch-{}-{}", std::process::id(), restarts);
+                std::env::temp_dir().join(name)
+            })
+            .collect();
+        let expected: Vec<String> = dirs.iter().map(|dir| dir.display().to_string()).collect();
+        assert_eq!(lines, expected);
+        assert!(dirs.iter().all(|dir| !dir.exists()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn children_run_with_the_umask_given() {
+        let mut process = SupervisedProcess::new("sh")
+            .with_args(vec!["-c", "umask; sleep 0.1"])
+            .with_umask(0o027)
+            .add_test("still running", |child: &mut ChildInfo| {
+                matches!(child.try_wait(), Ok(None))
+            })
+            .with_output_tail(0)
+            .with_check_interval(Duration::from_millis(10))
+            .with_restart_times(0);
+        let lines = process.handle().stdout_lines();
+
+        assert_eq!(process.run().unwrap(), RunOutcome::RestartsExhausted);
+        let lines: Vec<String> = lines.map(|output| output.line).collect();
+        assert_eq!(lines, vec!["0027"]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn children_run_with_the_io_priority_given() {
+        // Told by ionice(1), from util-linux
+        if Command::new("ionice").output().is_err() {
+            return;
+        }
+        let mut process = SupervisedProcess::new("sh")
+            .with_args(vec!["-c", "ionice -p $$; sleep 0.1"])
+            .with_io_priority(IoPriorityClass::BestEffort, 6)
+            .add_test("still running", |child: &mut ChildInfo| {
+                matches!(child.try_wait(), Ok(None))
+            })
+            .with_output_tail(0)
+            .with_check_interval(Duration::from_millis(10))
+            .with_restart_times(0);
+        let lines = process.handle().stdout_lines();
+
+        assert_eq!(process.run().unwrap(), RunOutcome::RestartsExhausted);
+        let lines: Vec<String> = lines.map(|output| output.line).collect();
+        assert_eq!(lines, vec!["best-effort: prio 6"]);
+
+        let process = SupervisedProcess::new("true").with_io_priority(IoPriorityClass::Idle, 8);
+        assert!(matches!(
+            process.spawn(),
+            Err(SupervisorError::Spawn { source, .. }) if source.kind() == io::ErrorKind::InvalidInput
+        ));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn core_dumps_of_crashed_children_are_collected() {
+        // Only cores written to files can be collected
+        let pattern = std::fs::read_to_string("/proc/sys/kernel/core_pattern").unwrap();
+        if pattern.starts_with('|') {
+            return;
+        }
+        let dir = std::env::temp_dir().join(format!("crash-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut core_dumps = vec![];
+        let mut process = SupervisedProcess::new("sh")
+            .with_name("web")
+            .with_args(vec!["-c", "kill -SEGV $$"])
+            .with_working_dir(&dir)
+            .with_core_dumps(dir.join("cores"))
+            .add_test("still running", |child: &mut ChildInfo| {
+                matches!(child.try_wait(), Ok(None))
+            })
+            .with_check_interval(Duration::from_millis(10))
+            .with_backoff_time(Duration::from_millis(1))
+            .with_restart_times(1)
+            .on_restart(|context: &RestartContext| {
+                core_dumps.push((context.pid, context.core_dump.clone()))
+            });
+
+        assert_eq!(process.run().unwrap(), RunOutcome::RestartsExhausted);
+        drop(process);
+        let (pid, core_dump) = core_dumps.pop().unwrap();
+        let core_dump = core_dump.unwrap();
+        assert_eq!(core_dump, dir.join(format!("cores/core.web.{}", pid)));
+        assert!(core_dump.is_file());
+        let metadata = std::fs::read_to_string(core_dump.with_extension(format!("{}.txt", pid)));
+        assert!(metadata.unwrap().contains("signal=11\n"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sigkill_alone_is_not_taken_for_out_of_memory() {
+        let mut restarts = vec![];
+        let mut process = SupervisedProcess::new("sh")
+            .with_args(vec!["-c", "kill -9 $$"])
+            .add_test("still running", |child: &mut ChildInfo| {
+                matches!(child.try_wait(), Ok(None))
+            })
+            .with_check_interval(Duration::from_millis(10))
+            .with_backoff_time(Duration::from_millis(20))
+            .with_oom_backoff_time(Duration::from_secs(10))
+            .with_restart_times(1)
+            .on_restart(|context: &RestartContext| {
+                restarts.push((context.reason, context.backoff))
+            });
+
+        assert_eq!(process.run().unwrap(), RunOutcome::RestartsExhausted);
+        drop(process);
+        assert_eq!(
+            restarts,
+            vec![(RestartReason::FailedTest, Duration::from_millis(20))]
+        );
+    }
+
+    #[test]
+    fn ignored_outcome_reports_error_but_keeps_running() {
+        let mut errors = vec![];
+        let mut passing_count = 0;
+
+        let mut process = SupervisedProcess::new("sleep")
+            .with_args(vec!["5"])
+            .add_test("ignored", |_: &mut ChildInfo| TestOutcome::Ignore)
+            .with_check_interval(Duration::from_millis(10))
+            .on_test_error(|name: &str| errors.push(name.to_string()))
+            .on_tests_passing(|| passing_count += 1);
+        let handle = process.handle();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            handle.stop();
+        });
+
+        assert!(process.run().is_ok());
+        drop(process);
+        assert!(!errors.is_empty());
+        assert_eq!(errors.len(), passing_count);
+    }
+
+    #[test]
+    fn stateful_event_handlers() {
+        let mut restarts = vec![];
+        let mut errors = vec![];
+
+        let mut process = SupervisedProcess::new("echo")
+            .add_test("always false", Box::from(|_: &mut ChildInfo| false))
+            .with_check_interval(Duration::from_millis(1))
+            .with_backoff_time(Duration::from_millis(1))
+            .with_restart_times(2)
+            .on_restart(|_: &RestartContext| restarts.push(restarts.len()))
+            .on_test_error(|name: &str| errors.push(name.to_string()));
+        assert!(process.run().is_ok());
+        drop(process);
+
+        assert_eq!(restarts, vec![0, 1]);
+        assert_eq!(errors.len(), 3);
+    }
+
+    #[test]
+    fn use_child_in_test() {
+        let mut process = SupervisedProcess::new("sleep")
+            .with_args(vec!["0.2"])
+            .add_test(
+                "still running",
+                Box::from(|child: &mut ChildInfo| match child.try_wait() {
+                    Ok(None) => true,
+                    Ok(Some(exit_value)) => {
+                        println!("Got exit value {}", exit_value);
+                        false
+                    }
+                    _ => false,
+                }),
+            )
+            .with_check_interval(Duration::from_millis(5))
+            .with_restart_times(0);
+        assert!(process.run().is_ok());
+    }
+
+    #[test]
+    fn it_stops_on_shutdown_signal() {
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let trigger = shutdown.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            trigger.store(true, Ordering::SeqCst);
+        });
+
+        let mut process = SupervisedProcess::new("sleep")
+            .with_args(vec!["5"])
+            .add_test("always true", Box::from(|_: &mut ChildInfo| true))
+            .with_check_interval(Duration::from_secs(10))
+            .with_shutdown_signal(shutdown);
+
+        let started = Instant::now();
+        assert_eq!(process.run().unwrap(), RunOutcome::StoppedBySignal);
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn it_stops_on_cancellation_token() {
+        let token = tokio_util::sync::CancellationToken::new();
+        let trigger = token.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            trigger.cancel();
+        });
+
+        let mut process = SupervisedProcess::new("sleep")
+            .with_args(vec!["5"])
+            .add_test("always true", Box::from(|_: &mut ChildInfo| true))
+            .with_check_interval(Duration::from_secs(10))
+            .with_cancellation_token(token);
+
+        let started = Instant::now();
+        assert_eq!(process.run().unwrap(), RunOutcome::StoppedBySignal);
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn it_skips_backoff_on_shutdown_signal() {
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let trigger = shutdown.clone();
+        let error_fn = move |_: &str| trigger.store(true, Ordering::SeqCst);
+
+        let mut process = SupervisedProcess::new("echo")
+            .add_test("always false", Box::from(|_: &mut ChildInfo| false))
+            .with_check_interval(Duration::from_millis(1))
+            .with_backoff_time(Duration::from_secs(10))
+            .with_shutdown_signal(shutdown)
+            .on_test_error(&error_fn);
+
+        let started = Instant::now();
+        assert!(process.run().is_ok());
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn it_restarts_jobs_until_they_succeed() {
+        let dir = std::env::temp_dir().join(format!("job-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut contexts = vec![];
+        let mut statuses = vec![];
+
+        // Fails on the first two attempts, then succeeds
+        let mut process = SupervisedProcess::new("sh")
+            .with_args(["-c", "echo >> attempts; test $(wc -l < attempts) -ge 3"])
+            .with_working_dir(&dir)
+            .with_job_policy(JobPolicy::RestartUntilSuccess { max_attempts: 5 })
+            .with_backoff_time(Duration::from_millis(1))
+            .on_restart(|context: &RestartContext| contexts.push(context.attempt))
+            .on_exit(|status| statuses.push(status.success()));
+        assert_eq!(process.run().unwrap(), RunOutcome::JobSucceeded);
+        drop(process);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(contexts, vec![1, 2]);
+        assert_eq!(statuses, vec![false, false, true]);
+    }
+
+    #[test]
+    fn it_gives_up_on_jobs_after_max_attempts() {
+        let mut restart_count = 0;
+        let mut exhausted = vec![];
+
+        let mut process = SupervisedProcess::new("false")
+            .with_job_policy(JobPolicy::RestartUntilSuccess { max_attempts: 3 })
+            .with_backoff_time(Duration::from_millis(1))
+            .on_restart(|_: &RestartContext| restart_count += 1)
+            .on_restart_exhausted(|context: &RestartContext| exhausted.push(context.attempt));
+        assert_eq!(process.run().unwrap(), RunOutcome::RestartsExhausted);
+        drop(process);
+
+        assert_eq!(restart_count, 2);
+        assert_eq!(exhausted, vec![3]);
+    }
+
+    #[test]
+    fn it_stops_after_total_timeout() {
+        let mut exit_count = 0;
+
+        let mut process = SupervisedProcess::new("sleep")
+            .with_args(["5"])
+            .add_test("always true", |_: &mut ChildInfo| true)
+            .with_check_interval(Duration::from_millis(20))
+            .with_total_timeout(Duration::from_millis(100))
+            .on_exit(|_| exit_count += 1);
+
+        let started = Instant::now();
+        assert_eq!(process.run().unwrap(), RunOutcome::TotalTimeout);
+        let elapsed = started.elapsed();
+        drop(process);
+        assert!(elapsed >= Duration::from_millis(100));
+        assert!(elapsed < Duration::from_secs(1));
+        assert_eq!(exit_count, 1);
+    }
+
+    #[test]
+    fn threaded_supervisor_runs_on_another_thread() {
+        let restarted = Arc::new(AtomicBool::new(false));
+        let flag = restarted.clone();
+
+        let mut process = SupervisedProcess::new_send("sleep")
+            .with_args(["5"])
+            .add_test("always false", |_: &mut ChildInfo| false)
+            .with_check_interval(Duration::from_millis(10))
+            .with_backoff_time(Duration::from_millis(1))
+            .with_restart_times(1)
+            .on_restart(move |_: &RestartContext| flag.store(true, Ordering::SeqCst));
+        let supervisor = thread::spawn(move || process.run().is_ok());
+
+        assert!(supervisor.join().unwrap());
+        assert!(restarted.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn it_stops_through_handle() {
+        let mut process = SupervisedProcess::new("sleep")
+            .with_args(vec!["5"])
+            .add_test("always true", Box::from(|_: &mut ChildInfo| true))
+            .with_check_interval(Duration::from_secs(10));
+        let handle = process.handle();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            handle.stop();
+        });
+
+        let started = Instant::now();
+        assert_eq!(process.run().unwrap(), RunOutcome::StoppedByHandle);
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn it_restarts_through_handle() {
+        let restart_count: RefCell<i32> = RefCell::new(0);
+        let restart_fn = |_: &RestartContext| {
+            (*restart_count.borrow_mut()) += 1;
+        };
+
+        let mut process = SupervisedProcess::new("sleep")
+            .with_args(vec!["5"])
+            .add_test("always true", Box::from(|_: &mut ChildInfo| true))
+            .with_check_interval(Duration::from_secs(10))
+            .on_restart(restart_fn);
+        let handle = process.handle();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            handle.restart();
+            thread::sleep(Duration::from_millis(50));
+            handle.stop();
+        });
+
+        let started = Instant::now();
+        assert!(process.run().is_ok());
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert_eq!(*restart_count.borrow(), 1);
+    }
+
+    #[cfg(feature = "watch")]
+    #[test]
+    fn it_restarts_when_watched_files_change() {
+        let dir = std::env::temp_dir().join(format!("supervised-process-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let restart_count: RefCell<i32> = RefCell::new(0);
+        let restart_fn = |_: &RestartContext| {
+            (*restart_count.borrow_mut()) += 1;
+        };
+
+        let mut process = SupervisedProcess::new("sleep")
+            .with_args(vec!["5"])
+            .add_test("always true", Box::from(|_: &mut ChildInfo| true))
+            .with_check_interval(Duration::from_secs(10))
+            .with_watch_paths([&dir])
+            .on_restart(restart_fn);
+        let handle = process.handle();
+        let file = dir.join("config");
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            std::fs::write(file, "changed").unwrap();
+            thread::sleep(Duration::from_millis(200));
+            handle.stop();
+        });
+
+        assert!(process.run().is_ok());
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(*restart_count.borrow() >= 1);
+    }
+
+    #[test]
+    fn stdio_watchdog_passes_when_child_answers() {
+        let error_fn = |name: &str| panic!("{} failed", name);
+
+        let mut process = SupervisedProcess::new("sh")
+            .with_args(vec!["-c", "while read line; do echo pong; done"])
+            .with_stdio_watchdog(Duration::from_millis(20))
+            .with_check_interval(Duration::from_millis(100))
+            .on_test_error(error_fn);
+        let handle = process.handle();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(350));
+            handle.stop();
+        });
+
+        assert!(process.run().is_ok());
+    }
+
+    #[test]
+    fn stdio_watchdog_fails_when_child_does_not_answer() {
+        let error_count: RefCell<i32> = RefCell::new(0);
+        let error_fn = |name: &str| {
+            assert_eq!(name, "stdio watchdog");
+            (*error_count.borrow_mut()) += 1;
+        };
+
+        let mut process = SupervisedProcess::new("sleep")
+            .with_args(vec!["5"])
+            .with_stdio_watchdog(Duration::from_millis(20))
+            .with_check_interval(Duration::from_millis(100))
+            .with_restart_times(0)
+            .on_test_error(error_fn);
+
+        assert!(process.run().is_ok());
+        assert_eq!(*error_count.borrow(), 1);
+    }
+
+    #[test]
+    fn it_runs_the_command_in_working_dir() {
+        let dir = std::env::temp_dir().join(format!("working-dir-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("marker"), "").unwrap();
+        let mut passing_count = 0;
+
+        let mut process = SupervisedProcess::new("sh")
+            .with_args(["-c", "test -f marker && sleep 5"])
+            .add_test("still running", |child: &mut ChildInfo| {
+                matches!(child.try_wait(), Ok(None))
+            })
+            .with_working_dir(&dir)
+            .with_check_interval(Duration::from_millis(50))
+            .with_restart_times(0)
+            .on_tests_passing(|| passing_count += 1);
+        let handle = process.handle();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(120));
+            handle.stop();
+        });
+
+        assert!(process.run().is_ok());
+        drop(process);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(passing_count, 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn it_runs_non_utf8_programs_and_args() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let arg = OsStr::from_bytes(b"caf\xe9");
+        let mut process = SupervisedProcess::new(OsStr::new("echo"))
+            .with_args([arg])
+            .add_test("always false", |_: &mut ChildInfo| false)
+            .with_check_interval(Duration::from_millis(10))
+            .with_restart_times(0);
+        assert!(process.run().is_ok());
+        assert_eq!(process.args, vec![arg.to_os_string()]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn it_adopts_a_running_process_and_restarts_it_when_it_dies() {
+        let mut adopted = std::process::Command::new("sleep")
+            .arg("5")
+            .spawn()
+            .unwrap();
+        let pid = adopted.id();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            adopted.kill().unwrap();
+            adopted.wait().unwrap();
+        });
+        let mut contexts = vec![];
+        let mut no_restart_contexts = vec![];
+
+        let mut process = SupervisedProcess::new("false")
+            .adopt(pid)
+            .add_test("still running", |child: &mut ChildInfo| {
+                matches!(child.try_wait(), Ok(None))
+            })
+            .with_check_interval(Duration::from_millis(20))
+            .with_backoff_time(Duration::from_millis(1))
+            .with_restart_times(1)
+            .on_restart(|context: &RestartContext| contexts.push(context.clone()))
+            .on_no_restart(|context: &RestartContext| no_restart_contexts.push(context.clone()));
+        assert!(process.run().is_ok());
+        drop(process);
+
+        assert_eq!(contexts.len(), 1);
+        assert_eq!(contexts[0].pid, pid);
+        assert_eq!(
+            contexts[0].failed_test.as_deref(),
+            Some("adopted process alive")
+        );
+        assert_eq!(no_restart_contexts.len(), 1);
+        assert_ne!(no_restart_contexts[0].pid, pid);
+        assert_eq!(
+            no_restart_contexts[0].failed_test.as_deref(),
+            Some("still running")
+        );
+    }
+
+    #[test]
+    fn it_resumes_restart_count_from_state_file() {
+        let path = std::env::temp_dir().join(format!("state-resume-{}", std::process::id()));
+        std::fs::write(&path, "restarts=1\nlast_failure=earlier\n").unwrap();
+        let mut contexts = vec![];
+
+        let mut process = SupervisedProcess::new("sleep")
+            .with_args(["5"])
+            .add_test("always false", |_: &mut ChildInfo| false)
+            .with_check_interval(Duration::from_millis(10))
+            .with_restart_times(1)
+            .with_state_file(&path)
+            .on_no_restart(|context: &RestartContext| contexts.push(context.clone()));
+        assert!(process.run().is_ok());
+        drop(process);
+
+        let state = State::load(&path).unwrap().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(contexts.len(), 1);
+        assert_eq!(contexts[0].attempt, 2);
+        assert_eq!(state.restarts, 1);
+        assert_eq!(state.pid, Some(contexts[0].pid));
+        assert_eq!(state.last_failure.as_deref(), Some("always false"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn it_readopts_the_child_recorded_in_state_file() {
+        let path = std::env::temp_dir().join(format!("state-readopt-{}", std::process::id()));
+        let mut previous = std::process::Command::new("sleep")
+            .arg("5")
+            .spawn()
+            .unwrap();
+        let state = format!(
+            "restarts=0\npid={}\nstart_time={}\n",
+            previous.id(),
+            unix::start_time(previous.id()).unwrap()
+        );
+        std::fs::write(&path, state).unwrap();
+        let mut contexts = vec![];
+
+        let mut process = SupervisedProcess::new("sleep")
+            .with_args(["5"])
+            .add_concurrent_test("always false", |_| false)
+            .with_check_interval(Duration::from_millis(10))
+            .with_restart_times(0)
+            .with_state_file(&path)
+            .readopt_on_resume(true)
+            .on_no_restart(|context: &RestartContext| contexts.push(context.clone()));
+        assert!(process.run().is_ok());
+        drop(process);
+
+        std::fs::remove_file(&path).unwrap();
+        previous.wait().unwrap();
+        assert_eq!(contexts.len(), 1);
+        assert_eq!(contexts[0].pid, previous.id());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn it_does_not_readopt_another_process_with_the_recorded_pid() {
+        let path = std::env::temp_dir().join(format!("state-reused-{}", std::process::id()));
+        let mut unrelated = std::process::Command::new("sleep")
+            .arg("5")
+            .spawn()
+            .unwrap();
+        // As after a reboot, when the PID went to another process
+        let state = format!("restarts=0\npid={}\nstart_time=1\n", unrelated.id());
+        std::fs::write(&path, state).unwrap();
+        let mut contexts = vec![];
+
+        let mut process = SupervisedProcess::new("sleep")
+            .with_args(["5"])
+            .add_test("always false", |_: &mut ChildInfo| false)
+            .with_check_interval(Duration::from_millis(10))
+            .with_restart_times(0)
+            .with_state_file(&path)
+            .readopt_on_resume(true)
+            .on_no_restart(|context: &RestartContext| contexts.push(context.clone()));
+        assert!(process.run().is_ok());
+        drop(process);
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(unix::is_alive(unrelated.id()));
+        unrelated.kill().unwrap();
+        unrelated.wait().unwrap();
+        assert_eq!(contexts.len(), 1);
+        assert_ne!(contexts[0].pid, unrelated.id());
+    }
+
+    #[test]
+    fn it_exposes_counters_through_handle() {
+        let mut seen = vec![];
+
+        let process = SupervisedProcess::new("sleep")
+            .with_args(["5"])
+            .add_test("always false", |_: &mut ChildInfo| false)
+            .with_check_interval(Duration::from_millis(10))
+            .with_backoff_time(Duration::from_millis(1))
+            .with_restart_times(2);
+        let handle = process.handle();
+        let mut process = process.on_test_error(|_: &str| {
+            seen.push((
+                handle.total_restarts(),
+                handle.restarts_remaining(),
+                handle.uptime().is_some(),
+            ))
+        });
+        assert!(process.run().is_ok());
+        assert_eq!(process.total_restarts(), 2);
+        assert_eq!(process.restarts_remaining(), Some(0));
+        assert_eq!(process.last_failure(), Some("always false"));
+        assert_eq!(process.uptime(), None);
+        drop(process);
+
+        assert_eq!(
+            seen,
+            vec![(0, Some(2), true), (1, Some(1), true), (2, Some(0), true)]
+        );
+    }
+
+    #[test]
+    fn it_resets_restart_budget_after_healthy_uptime() {
+        let mut checks = 0;
+        let mut restart_count = 0;
+
+        // Every child passes its first two checks and fails the third one,
+        // which would exhaust a budget of one restart without the reset
+        let mut process = SupervisedProcess::new("sleep")
+            .with_args(["5"])
+            .add_test("third check fails", move |_: &mut ChildInfo| {
+                checks += 1;
+                checks % 3 != 0
+            })
+            .with_check_interval(Duration::from_millis(20))
+            .with_backoff_time(Duration::from_millis(1))
+            .with_restart_times(1)
+            .with_backoff_reset_after(Duration::from_millis(30))
+            .on_restart(|_: &RestartContext| restart_count += 1);
+        let handle = process.handle();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(400));
+            handle.stop();
+        });
+
+        assert!(process.run().is_ok());
+        drop(process);
+        assert!(restart_count > 1);
+    }
+
+    #[test]
+    fn restart_context_includes_output_tail() {
+        let mut contexts = vec![];
+
+        let mut process = SupervisedProcess::new("sh")
+            .with_args([
+                "-c",
+                "echo zero; echo one; sleep 0.05; echo two >&2; sleep 5",
+            ])
+            .add_test("always false", |_: &mut ChildInfo| false)
+            .with_check_interval(Duration::from_millis(100))
+            .with_restart_times(0)
+            .with_output_tail(2)
+            .on_no_restart(|context: &RestartContext| contexts.push(context.clone()));
+        assert!(process.run().is_ok());
+        drop(process);
+
+        assert_eq!(contexts[0].output_tail, vec!["one", "two"]);
+    }
+
+    #[cfg(feature = "output-match")]
+    #[test]
+    fn output_matches_drive_readiness_and_restarts() {
+        let mut checked = 0;
+        let mut failures = vec![];
+
+        let mut process = SupervisedProcess::new("sh")
+            .with_args([
+                "-c",
+                "sleep 0.15; echo listening on 8080; sleep 0.15; echo FATAL: oops; sleep 5",
+            ])
+            .add_test("check", |_: &mut ChildInfo| true)
+            .on_test_ok(|name: &str| checked += usize::from(name == "check"))
+            .on_output_match(
+                Regex::new("^listening on").unwrap(),
+                OutputAction::MarkReady,
+            )
+            .on_output_match(Regex::new("^FATAL:").unwrap(), OutputAction::Restart)
+            .with_check_interval(Duration::from_millis(50))
+            .with_restart_times(0)
+            .on_no_restart(|context: &RestartContext| failures.push(context.failed_test.clone()));
+        assert!(process.run().is_ok());
+        drop(process);
+
+        // Checks only started once the child said it was listening
+        assert!((1..=4).contains(&checked), "{checked}");
+        assert_eq!(failures, vec![Some("output match".to_string())]);
+    }
+
+    #[test]
+    fn it_feeds_stdin_on_every_spawn() {
+        let tails = RefCell::new(vec![]);
+
+        let mut process = SupervisedProcess::new("sh")
+            .with_args(["-c", "read line; echo got $line; sleep 5"])
+            .with_stdin(StdinSource::Bytes(b"config\n".to_vec()))
+            .add_test("always false", |_: &mut ChildInfo| false)
+            .with_check_interval(Duration::from_millis(100))
+            .with_backoff_time(Duration::from_millis(10))
+            .with_restart_times(1)
+            .with_output_tail(1)
+            .on_restart(|context: &RestartContext| {
+                tails.borrow_mut().push(context.output_tail.clone())
+            })
+            .on_no_restart(|context: &RestartContext| {
+                tails.borrow_mut().push(context.output_tail.clone())
+            });
+        assert!(process.run().is_ok());
+        drop(process);
+
+        assert_eq!(
+            tails.into_inner(),
+            vec![vec!["got config"], vec!["got config"]]
+        );
+    }
+
+    #[test]
+    fn it_runs_script_hooks_on_restarts() {
+        let log = std::env::temp_dir().join(format!("script-hooks-{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&log);
+
+        let mut process = SupervisedProcess::new("sleep")
+            .with_args(["5"])
+            .with_name("web")
+            .add_test("always false", |_: &mut ChildInfo| false)
+            .with_check_interval(Duration::from_millis(10))
+            .with_backoff_time(Duration::from_millis(10))
+            .with_restart_times(1)
+            .on_restart_run(&format!("echo {{name}} {{attempt}} >> {}", log.display()))
+            .on_no_restart_run(&format!("echo gave up >> {}", log.display()));
+        assert!(process.run().is_ok());
+
+        // Hooks run in the background
+        let deadline = Instant::now() + Duration::from_secs(2);
+        let mut contents = String::new();
+        while Instant::now() < deadline && contents.lines().count() < 2 {
+            thread::sleep(Duration::from_millis(10));
+            contents = std::fs::read_to_string(&log).unwrap_or_default();
+        }
+        std::fs::remove_file(&log).unwrap();
+        let mut lines: Vec<&str> = contents.lines().collect();
+        lines.sort_unstable();
+        assert_eq!(lines, vec!["gave up", "web 1"]);
+    }
+
+    #[test]
+    fn notifiers_are_told_about_restarts() {
+        struct Recorder<'r>(&'r RefCell<Vec<(NotifyEvent, u64)>>);
+
+        impl Notifier for Recorder<'_> {
+            fn notify(&mut self, event: NotifyEvent, context: &RestartContext) {
+                self.0.borrow_mut().push((event, context.attempt));
+            }
+        }
+
+        let events = RefCell::new(vec![]);
+        let mut process = SupervisedProcess::new("sleep")
+            .with_args(["5"])
+            .add_test("always false", |_: &mut ChildInfo| false)
+            .with_check_interval(Duration::from_millis(10))
+            .with_backoff_time(Duration::from_millis(10))
+            .with_restart_times(1)
+            .with_notifier(Recorder(&events));
+        assert!(process.run().is_ok());
+        drop(process);
+
+        assert_eq!(
+            events.into_inner(),
+            vec![(NotifyEvent::Restart, 1), (NotifyEvent::NoRestart, 2)]
+        );
+    }
+
+    #[test]
+    fn pausing_stops_the_child_until_resumed() {
+        let mut pids = vec![];
+        let mut process = SupervisedProcess::new("sleep")
+            .with_args(["5"])
+            .add_test("always true", |_: &mut ChildInfo| true)
+            .with_check_interval(Duration::from_millis(10))
+            .on_spawn(|pid| pids.push(pid));
+        let handle = process.handle();
+        let controller = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            handle.pause();
+            thread::sleep(Duration::from_millis(100));
+            let paused_pid = handle.pid();
+            handle.resume();
+            thread::sleep(Duration::from_millis(100));
+            let resumed_pid = handle.pid();
+            handle.stop();
+            (paused_pid, resumed_pid)
+        });
+        assert!(process.run().is_ok());
+        drop(process);
+
+        let (paused_pid, resumed_pid) = controller.join().unwrap();
+        assert_eq!(paused_pid, None);
+        assert_eq!(pids.len(), 2);
+        assert_eq!(resumed_pid, Some(pids[1]));
+    }
+
+    #[test]
+    fn reloading_restarts_only_for_spawn_settings() {
+        let mut process = SupervisedProcess::new("sh")
+            .with_args(["-c", "echo $GREETING; sleep 5"])
+            .with_env("GREETING", "hello")
+            .with_output_tail(1)
+            .add_test("always true", |_: &mut ChildInfo| true)
+            .with_check_interval(Duration::from_millis(10));
+        let handle = process.handle();
+        let controller = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            let first_pid = handle.pid();
+            handle
+                .reload(ReloadConfig::new().with_check_interval(Duration::from_millis(20)))
+                .unwrap();
+            thread::sleep(Duration::from_millis(100));
+            let interval_pid = handle.pid();
+            handle
+                .reload(ReloadConfig::new().with_env([("GREETING", "bye")]))
+                .unwrap();
+            thread::sleep(Duration::from_millis(200));
+            let env_pid = handle.pid();
+            let output = handle.output_tail();
+            handle.stop();
+            (first_pid, interval_pid, env_pid, output)
+        });
+        assert!(process.run().is_ok());
+
+        let (first_pid, interval_pid, env_pid, output) = controller.join().unwrap();
+        assert_eq!(process.check_interval, Duration::from_millis(20));
+        assert_eq!(first_pid, interval_pid);
+        assert_ne!(interval_pid, env_pid);
+        assert_eq!(output, vec!["bye"]);
+    }
+
+    #[test]
+    fn reloaded_checks_keep_their_options() {
+        let mut process = SupervisedProcess::new("my-daemon")
+            .with_dynamic_port("PORT")
+            .add_test("old", |_: &mut ChildInfo| true)
+            .add_test("kept", |_: &mut ChildInfo| true);
+        process.allocate_port().unwrap();
+        process.publish_fixed_test_names();
+        for name in ["old", "kept"] {
+            outcome::record(&mut process.test_stats, name, TestOutcome::Healthy, None);
+        }
+        let port_check = TcpCheck::local_port(process.port.unwrap());
+
+        let handle = process.handle();
+        let every_zero =
+            ReloadConfig::new().add_check_every(Duration::ZERO, TcpCheck::local_port(1));
+        assert_eq!(
+            handle.reload(every_zero),
+            Err(BuilderError::ZeroCheckInterval)
+        );
+        let taken = port_check.name().to_string();
+        assert_eq!(
+            handle.reload(ReloadConfig::new().add_check((taken.clone(), |_: &mut ChildInfo| true))),
+            Err(BuilderError::DuplicateTest(taken))
+        );
+        let config = ReloadConfig::new()
+            .add_check(("kept".to_string(), |_: &mut ChildInfo| true))
+            .add_check_every(
+                Duration::from_secs(60),
+                ("hourly".to_string(), |_: &mut ChildInfo| true),
+            )
+            .add_advisory_check(("advisory".to_string(), |_: &mut ChildInfo| false));
+        handle.reload(config).unwrap();
+        assert!(!process.apply_reload());
+
+        let tests: Vec<_> = process
+            .tests
+            .iter()
+            .map(|test| (test.check.name(), test.every, test.advisory))
+            .collect();
+        assert_eq!(
+            tests,
+            [
+                (port_check.name(), None, false),
+                ("kept", None, false),
+                ("hourly", Some(Duration::from_secs(60)), false),
+                ("advisory", None, true),
+            ]
+        );
+        let stats: Vec<_> = handle
+            .test_stats()
+            .into_iter()
+            .map(|test| test.name)
+            .collect();
+        assert_eq!(stats, ["kept"]);
+    }
+
+    #[test]
+    fn it_expands_templates_on_every_spawn() {
+        let pid_file = std::env::temp_dir().join(format!("templates-{}.pid", std::process::id()));
+        let contexts = RefCell::new(vec![]);
+
+        let mut process = SupervisedProcess::new("sh")
+            .with_args([
+                "-c",
+                "echo {name}-{restart_count}-{color} $PID_FILE; sleep 5",
+            ])
+            .with_env("PID_FILE", "{pid_file}")
+            .with_name("web")
+            .with_template_var("color", "blue")
+            .with_pid_file(&pid_file)
+            .add_test("always false", |_: &mut ChildInfo| false)
+            .with_check_interval(Duration::from_millis(100))
+            .with_backoff_time(Duration::from_millis(10))
+            .with_restart_times(1)
+            .with_output_tail(1)
+            .on_restart(|context: &RestartContext| contexts.borrow_mut().push(context.clone()))
+            .on_no_restart(|context: &RestartContext| contexts.borrow_mut().push(context.clone()));
+        assert!(process.run().is_ok());
+        drop(process);
+
+        let contexts = contexts.into_inner();
+        let tails: Vec<String> = contexts
+            .iter()
+            .map(|context| context.output_tail.join(""))
+            .collect();
+        assert_eq!(
+            tails,
+            vec![
+                format!("web-0-blue {}", pid_file.display()),
+                format!("web-1-blue {}", pid_file.display()),
+            ]
+        );
+        assert_eq!(
+            std::fs::read_to_string(&pid_file).unwrap(),
+            contexts[1].pid.to_string()
+        );
+        std::fs::remove_file(pid_file).unwrap();
+    }
+
+    #[test]
+    fn it_gives_the_child_a_dynamic_port() {
+        let mut contexts = vec![];
+
+        let mut process = SupervisedProcess::new("sh")
+            .with_args(["-c", "echo $PORT {port}; sleep 5"])
+            .with_dynamic_port("PORT")
+            .with_check_interval(Duration::from_millis(100))
+            .with_restart_times(0)
+            .with_output_tail(1)
+            .on_no_restart(|context: &RestartContext| contexts.push(context.clone()));
+        let handle = process.handle();
+        assert!(process.run().is_ok());
+        drop(process);
+
+        // Nothing listens on the port, so the child fails its test
+        let port = handle.port().unwrap();
+        assert_eq!(
+            contexts[0].failed_test,
+            Some(format!("tcp 127.0.0.1:{}", port))
+        );
+        assert_eq!(contexts[0].output_tail, vec![format!("{} {}", port, port)]);
+    }
+
+    #[test]
+    fn it_runs_the_command() {
+        let mut process = SupervisedProcess::new("echo")
+            .add_test("always false", Box::from(|_child: &mut ChildInfo| false))
+            .with_check_interval(Duration::from_millis(10))
+            .with_backoff_time(Duration::from_millis(10))
+            .with_restart_times(1);
+        assert!(process.run().is_ok());
+    }
+
+    #[test]
+    fn it_runs_the_command_with_args() {
+        let mut process = SupervisedProcess::new("echo")
+            .with_args(vec!["-n"])
+            .add_test("always false", Box::from(|_child: &mut ChildInfo| false))
+            .with_check_interval(Duration::from_millis(10))
+            .with_backoff_time(Duration::from_millis(10))
+            .with_restart_times(1);
+        assert!(process.run().is_ok());
+    }
+
+    #[test]
+    fn it_supervises_through_the_backend() {
+        let backend = MockBackend::new();
+        let mut exit_codes = vec![];
+
+        let mut process = SupervisedProcess::new("my-daemon")
+            .with_args(["--port", "80"])
+            .with_backend(backend.clone())
+            .add_test("still running", |child: &mut ChildInfo| {
+                matches!(child.try_wait(), Ok(None))
+            })
+            .with_check_interval(Duration::from_millis(10))
+            .with_backoff_time(Duration::from_millis(10))
+            .with_restart_times(1)
+            .on_spawn(|pid| backend.exit(pid, 1))
+            .on_exit(|status| exit_codes.push(status.code()));
+        assert!(process.run().is_ok());
+        drop(process);
+
+        assert_eq!(backend.pids(), vec![1, 2]);
+        assert_eq!(
+            backend.command(2),
+            Some(vec!["my-daemon".into(), "--port".into(), "80".into()])
+        );
+        assert_eq!(exit_codes, vec![Some(1), Some(1)]);
+    }
+
+    #[test]
+    fn failures_during_maintenance_only_get_reported() {
+        // 02:00 UTC
+        let clock = MockClock::starting_at(UNIX_EPOCH + Duration::from_secs(1_700_006_400 + 7200));
+        let mut errors = 0;
+
+        let mut process = SupervisedProcess::new("my-daemon")
+            .with_backend(MockBackend::new())
+            .with_clock(clock.clone())
+            .with_maintenance_window_str("02:00-03:00")
+            .unwrap()
+            .add_test("database", |_: &mut ChildInfo| false)
+            .with_check_interval(Duration::from_secs(600))
+            .with_restart_times(0)
+            .on_test_error(|_: &str| errors += 1);
+        assert_eq!(process.run().unwrap(), RunOutcome::RestartsExhausted);
+        drop(process);
+
+        assert_eq!(clock.elapsed(), Duration::from_secs(3600));
+        assert_eq!(errors, 6);
+    }
+
+    #[test]
+    fn it_waits_on_the_clock() {
+        let clock = MockClock::new();
+        let mut uptimes = vec![];
+
+        let mut process = SupervisedProcess::new("my-daemon")
+            .with_backend(MockBackend::new())
+            .with_clock(clock.clone())
+            .add_test("always false", |_: &mut ChildInfo| false)
+            .with_check_interval(Duration::from_secs(600))
+            .with_backoff_time(Duration::from_secs(3600))
+            .with_restart_times(2)
+            .on_restart(|context: &RestartContext| uptimes.push(context.uptime));
+        let started = Instant::now();
+        assert!(process.run().is_ok());
+        drop(process);
+
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert_eq!(clock.elapsed(), Duration::from_secs(2 * 3600 + 3 * 600));
+        assert_eq!(uptimes, vec![Duration::from_secs(600); 2]);
+    }
+
+    #[test]
+    fn it_logs_events_as_json_lines() {
+        let path = std::env::temp_dir().join(format!("events-{}.jsonl", std::process::id()));
+        let mut process = SupervisedProcess::new("my-daemon")
+            .with_name("web")
+            .with_backend(MockBackend::new())
+            .with_clock(MockClock::new())
+            .with_json_log(&path)
+            .add_test("always false", |_: &mut ChildInfo| false)
+            .with_restart_times(0);
+        assert!(process.run().is_ok());
+        drop(process);
+
+        let log = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let events: Vec<&str> = log
+            .lines()
+            .map(|line| line.split("\"event\":\"").nth(1).unwrap())
+            .map(|rest| &rest[..rest.find('"').unwrap()])
+            .collect();
+        assert_eq!(events, vec!["spawn", "test_error", "exit", "no_restart"]);
+        assert!(log.lines().all(|line| line.contains("\"service\":\"web\"")));
+    }
+
+    #[test]
+    fn it_pauses_a_flapping_child() {
+        let backend = MockBackend::new();
+        let checks = Arc::new(std::sync::atomic::AtomicU64::new(0));
+        let counter = checks.clone();
+        let mut flapping = vec![];
+
+        let process = SupervisedProcess::new("my-daemon")
+            .with_backend(backend.clone())
+            .with_clock(MockClock::new())
+            .add_test("every other time", move |_: &mut ChildInfo| {
+                counter.fetch_add(1, Ordering::SeqCst).is_multiple_of(2)
+            })
+            .with_flap_detection(
+                FlapDetector::new(2, Duration::from_secs(3600))
+                    .with_action(FlapAction::PauseRestarts),
+            );
+        let handle = process.handle();
+        let mut process = process.on_flapping(|context: &RestartContext| {
+            flapping.push(context.attempt);
+            handle.stop();
+        });
+        assert!(process.run().is_ok());
+        drop(process);
+
+        assert_eq!(flapping, vec![2]);
+        assert_eq!(backend.pids().len(), 2);
+        assert!(!backend.is_running(2));
+        assert_eq!(checks.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn it_keeps_stats_of_each_test() {
+        let mut process = SupervisedProcess::new("my-daemon")
+            .with_backend(MockBackend::new())
+            .with_clock(MockClock::new())
+            .add_test("always true", |_: &mut ChildInfo| true)
+            .add_test("slow", |_: &mut ChildInfo| {
+                thread::sleep(Duration::from_millis(5));
+                false
+            })
+            .with_restart_times(1);
+        let handle = process.handle();
+        assert!(process.run().is_ok());
+
+        let stats = process.test_stats();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].consecutive_failures, 0);
+        assert_eq!(stats[1].name, "slow");
+        assert_eq!(stats[1].last_outcome, TestOutcome::Unhealthy);
+        assert_eq!(stats[1].consecutive_failures, 2);
+        assert!(stats[1].latency.unwrap() >= Duration::from_millis(5));
+        assert_eq!(handle.test_stats(), stats);
+    }
+
+    #[test]
+    fn early_exits_double_the_backoff() {
+        let backend = MockBackend::new();
+        backend.exit_on_spawn(Some(1));
+        let clock = MockClock::new();
+
+        let mut process = SupervisedProcess::new("my-daemon")
+            .with_backend(backend)
+            .with_clock(clock.clone())
+            .add_test("still running", |child: &mut ChildInfo| {
+                matches!(child.try_wait(), Ok(None))
+            })
+            .with_check_interval(Duration::from_secs(1))
+            .with_backoff_time(Duration::from_secs(10))
+            .with_backoff_policy(BackoffPolicy::SkipOnExit)
+            .with_min_uptime(Duration::from_secs(60))
+            .with_restart_times(3);
+        assert!(process.run().is_ok());
+
+        // Four checks, and backoffs of 10, 20 and 40 seconds
+        assert_eq!(clock.elapsed(), Duration::from_secs(74));
+    }
+
+    #[test]
+    fn backoff_starts_over_after_healthy_uptime() {
+        let backend = MockBackend::new();
+        backend.exit_on_spawn(Some(1));
+        let mut checks = 0;
+        let mut backoffs = vec![];
+
+        // The first two children exit right away, the third one passes two
+        // checks before exiting
+        let mock = backend.clone();
+        let mut process = SupervisedProcess::new("my-daemon")
+            .with_backend(backend.clone())
+            .with_clock(MockClock::new())
+            .add_test("still running", move |child: &mut ChildInfo| {
+                checks += 1;
+                if checks == 5 {
+                    mock.exit(child.pid(), 1);
+                }
+                matches!(child.try_wait(), Ok(None))
+            })
+            .with_check_interval(Duration::from_secs(1))
+            .with_backoff_time(Duration::from_secs(10))
+            .with_min_uptime(Duration::from_secs(60))
+            .with_backoff_reset_after(Duration::from_secs(2))
+            .with_restart_times(3)
+            .on_restart(|context: &RestartContext| {
+                backoffs.push(context.backoff);
+                backend.exit_on_spawn((backoffs.len() != 2).then_some(1));
+            });
+        assert_eq!(process.run().unwrap(), RunOutcome::RestartsExhausted);
+        drop(process);
+
+        let secs = Duration::from_secs;
+        assert_eq!(backoffs, [secs(10), secs(20), secs(10), secs(20), secs(40)]);
+    }
+
+    #[test]
+    fn uptime_goes_by_the_clock() {
+        let process = SupervisedProcess::new("my-daemon")
+            .with_backend(MockBackend::new())
+            .with_clock(MockClock::new());
+        let handle = process.handle();
+        let uptimes = Rc::new(RefCell::new(vec![]));
+        let seen = uptimes.clone();
+        let mut process = process
+            .add_test("three checks", move |child: &mut ChildInfo| {
+                let mut seen = seen.borrow_mut();
+                seen.push((child.uptime(), handle.uptime()));
+                seen.len() < 3
+            })
+            .with_check_interval(Duration::from_secs(60))
+            .with_restart_times(0);
+        assert_eq!(process.run().unwrap(), RunOutcome::RestartsExhausted);
+
+        let minutes = |minutes: u64| Duration::from_secs(60 * minutes);
+        assert_eq!(
+            *uptimes.borrow(),
+            [1, 2, 3].map(|n| (minutes(n), Some(minutes(n))))
+        );
+    }
+
+    #[test]
+    fn supervisors_tell_the_uptime_by_the_clock() {
+        let clock = MockClock::new();
+        let process = SupervisedProcess::new("my-daemon").with_clock(clock.clone());
+        process
+            .control
+            .update_stats(|stats| stats.started = Some(clock.now()));
+        clock.advance(Duration::from_secs(90));
+        assert_eq!(process.uptime(), Some(Duration::from_secs(90)));
+        assert_eq!(process.handle().uptime(), Some(Duration::from_secs(90)));
+    }
+
+    #[test]
+    fn restart_contexts_carry_the_exit_status() {
+        let backend = MockBackend::new();
+        backend.exit_on_spawn(Some(3));
+        let mut statuses = vec![];
+
+        let mut process = SupervisedProcess::new("my-daemon")
+            .with_backend(backend.clone())
+            .with_clock(MockClock::new())
+            .add_test("still running", |child: &mut ChildInfo| {
+                matches!(child.try_wait(), Ok(None))
+            })
+            .with_check_interval(Duration::from_secs(1))
+            .with_restart_times(1)
+            .on_restart(|context: &RestartContext| statuses.push(context.exit_status));
+        assert!(process.run().is_ok());
+        drop(process);
+
+        assert_eq!(statuses.len(), 1);
+        assert_eq!(statuses[0].and_then(|status| status.code()), Some(3));
+    }
+
+    #[test]
+    fn threaded_supervisors_start_on_a_named_thread() {
+        let thread_name = Arc::new(Mutex::new(None));
+        let seen = thread_name.clone();
+        let process = SupervisedProcess::new_send("my-daemon")
+            .with_name("worker-7")
+            .with_backend(MockBackend::new())
+            .with_clock(MockClock::new())
+            .add_test("record thread", move |_: &mut ChildInfo| {
+                *seen.lock().unwrap() = thread::current().name().map(String::from);
+                false
+            })
+            .with_restart_times(0);
+
+        let outcome = process.start().unwrap().join().unwrap();
+        assert_eq!(outcome.unwrap(), RunOutcome::RestartsExhausted);
+        assert_eq!(thread_name.lock().unwrap().as_deref(), Some("worker-7"));
+    }
+
+    #[test]
+    fn panicking_tests_fail_without_ending_supervision() {
+        let mut panics = vec![];
+        let mut checks = 0;
+        let mut process = SupervisedProcess::new("my-daemon")
+            .with_backend(MockBackend::new())
+            .with_clock(MockClock::new())
+            .add_test("flaky", move |_: &mut ChildInfo| {
+                checks += 1;
+                if checks == 1 {
+                    panic!("lost connection");
+                }
+                checks < 3
+            })
+            .with_restart_times(1)
+            .on_test_panic(|name, message| panics.push(format!("{}: {}", name, message)));
+        assert_eq!(process.run().unwrap(), RunOutcome::RestartsExhausted);
+        assert_eq!(process.total_restarts(), 1);
+        drop(process);
+        assert_eq!(panics, vec!["flaky: lost connection"]);
+    }
+
+    #[test]
+    fn probe_errors_are_reported_apart_from_failures() {
+        let mut probe_errors = vec![];
+        let mut restarts = 0;
+        let mut checks = 0;
+
+        let mut process = SupervisedProcess::new("sleep")
+            .with_args(vec!["5"])
+            .add_test("config", |_: &mut ChildInfo| -> Result<bool, TestError> {
+                std::fs::read_to_string("/does/not/exist")?;
+                Ok(true)
+            })
+            .add_test("flaky probe", move |_: &mut ChildInfo| {
+                checks += 1;
+                match checks {
+                    1 => Err(TestError::new("connection refused")),
+                    _ => Ok(TestOutcome::Healthy),
+                }
+            })
+            .with_check_interval(Duration::from_millis(10))
+            .on_probe_error(|name, message| probe_errors.push(format!("{}: {}", name, message)))
+            .on_restart(|_: &RestartContext| restarts += 1);
+        let handle = process.handle();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            handle.stop();
+        });
+
+        assert!(process.run().is_ok());
+        drop(process);
+        assert_eq!(restarts, 0);
+        assert_eq!(
+            probe_errors[..2],
+            [
+                "config: No such file or directory (os error 2)",
+                "flaky probe: connection refused"
+            ]
+        );
+        assert!(probe_errors[2..]
+            .iter()
+            .all(|error| error.starts_with("config: ")));
+    }
+
+    #[test]
+    fn composite_checks_report_their_sub_results() {
+        let mut reported = vec![];
+        let port = |_: &mut ChildInfo| true;
+        let http = |_: &mut ChildInfo| Err::<bool, _>(TestError::new("token hunter2 rejected"));
+        let mut process = SupervisedProcess::new("sleep")
+            .with_args(vec!["5"])
+            .with_secret_env("TOKEN", "hunter2")
+            .add_check(CompositeCheck::all_of(
+                "serving",
+                vec![
+                    Box::new(("port".to_string(), port)),
+                    Box::new(("http".to_string(), http)),
+                ],
+            ))
+            .with_check_interval(Duration::from_millis(10))
+            .with_restart_times(0)
+            .on_sub_results(|name: &str, results: &[SubResult]| {
+                reported.push((name.to_string(), results.to_vec()))
+            });
+        let handle = process.handle();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            handle.stop();
+        });
+
+        assert_eq!(process.run().unwrap(), RunOutcome::StoppedByHandle);
+        drop(process);
+        let (name, results) = &reported[0];
+        assert_eq!(name, "serving");
+        let results: Vec<_> = results
+            .iter()
+            .map(|r| (r.name.as_str(), r.result.clone()))
+            .collect();
+        assert_eq!(
+            results,
+            vec![
+                ("port", Ok(TestOutcome::Healthy)),
+                ("http", Err("token *** rejected".to_string()))
+            ]
+        );
+    }
+
+    #[test]
+    fn restart_contexts_carry_what_the_test_panicked_with() {
+        let mut contexts = vec![];
+        let mut last = None;
+        let mut process = SupervisedProcess::new("my-daemon")
+            .with_backend(MockBackend::new())
+            .with_clock(MockClock::new())
+            .add_concurrent_test("panicking", |pid: u32| -> bool {
+                if pid == 1 {
+                    panic!("bad response from {}", pid);
+                }
+                false
+            })
+            .with_restart_times(1)
+            .on_restart(|context: &RestartContext| contexts.push(context.clone()))
+            .on_no_restart(|context: &RestartContext| last = Some(context.clone()));
+        assert_eq!(process.run().unwrap(), RunOutcome::RestartsExhausted);
+        drop(process);
+
+        assert_eq!(contexts.len(), 1);
+        assert_eq!(contexts[0].failed_test.as_deref(), Some("panicking"));
+        assert_eq!(
+            contexts[0].test_panic.as_deref(),
+            Some("bad response from 1")
+        );
+        // The test kept running, and failing, after panicking
+        let last = last.unwrap();
+        assert_eq!(last.failed_test.as_deref(), Some("panicking"));
+        assert_eq!(last.test_panic, None);
+    }
+
+    #[test]
+    fn a_quorum_of_tests_keeps_the_child_running() {
+        let checks = Rc::new(RefCell::new(0));
+        let counter = checks.clone();
+        let mut failed_test = None;
+        let mut process = SupervisedProcess::new("my-daemon")
+            .with_backend(MockBackend::new())
+            .with_clock(MockClock::new())
+            .add_test("primary", |_: &mut ChildInfo| true)
+            .add_test("replica", |_: &mut ChildInfo| false)
+            .add_test("cache", move |_: &mut ChildInfo| {
+                *counter.borrow_mut() += 1;
+                *counter.borrow() < 3
+            })
+            .with_test_aggregation(TestAggregation::Quorum(2))
+            .with_restart_times(0)
+            .on_no_restart(|context: &RestartContext| failed_test = context.failed_test.clone());
+        assert_eq!(process.run().unwrap(), RunOutcome::RestartsExhausted);
+        drop(process);
+
+        // Every test ran although "replica" failed from the start
+        assert_eq!(*checks.borrow(), 3);
+        assert_eq!(failed_test.as_deref(), Some("replica"));
+    }
+
+    #[test]
+    fn advisory_tests_never_restart_the_child() {
+        let checks = Rc::new(RefCell::new(0));
+        let counter = checks.clone();
+        let mut errors = vec![];
+        let mut failed_test = None;
+        let mut process = SupervisedProcess::new("my-daemon")
+            .with_backend(MockBackend::new())
+            .with_clock(MockClock::new())
+            .add_advisory_test("slow queries", |_: &mut ChildInfo| false)
+            .add_test("alive", move |_: &mut ChildInfo| {
+                *counter.borrow_mut() += 1;
+                *counter.borrow() < 3
+            })
+            .with_restart_times(0)
+            .on_test_error(|name: &str| errors.push(name.to_string()))
+            .on_no_restart(|context: &RestartContext| failed_test = context.failed_test.clone());
+        assert_eq!(process.run().unwrap(), RunOutcome::RestartsExhausted);
+        let stats = process.test_stats().to_vec();
+        drop(process);
+
+        assert_eq!(*checks.borrow(), 3);
+        assert_eq!(failed_test.as_deref(), Some("alive"));
+        assert_eq!(
+            errors,
+            ["slow queries", "slow queries", "slow queries", "alive"]
+        );
+        assert_eq!(stats[0].consecutive_failures, 3);
+    }
+
+    #[test]
+    fn failures_right_after_a_restart_are_let_go() {
+        let checks = Rc::new(RefCell::new(0));
+        let counter = checks.clone();
+        let mut process = SupervisedProcess::new("my-daemon")
+            .with_backend(MockBackend::new())
+            .with_clock(MockClock::new())
+            .add_test("warmed up", move |_: &mut ChildInfo| {
+                *counter.borrow_mut() += 1;
+                false
+            })
+            .with_check_interval(Duration::from_secs(30))
+            .with_post_restart_grace(Duration::from_secs(90))
+            .with_restart_times(1);
+        assert_eq!(process.run().unwrap(), RunOutcome::RestartsExhausted);
+        assert_eq!(process.total_restarts(), 1);
+
+        // The first child gets no grace, the second one fails twice during it
+        assert_eq!(*checks.borrow(), 4);
+    }
+
+    #[test]
+    fn restarts_are_kept_in_the_history() {
+        let path = std::env::temp_dir().join(format!("history-{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut process = SupervisedProcess::new("my-daemon")
+            .with_backend(MockBackend::new())
+            .with_clock(MockClock::new())
+            .add_test("always false", |_: &mut ChildInfo| false)
+            .with_backoff_time(Duration::from_secs(10))
+            .with_restart_times(3)
+            .with_restart_history(2)
+            .with_restart_history_file(&path);
+        let handle = process.handle();
+        assert_eq!(process.run().unwrap(), RunOutcome::RestartsExhausted);
+
+        let history = handle.history();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].attempt, 2);
+        assert_eq!(history[1].attempt, 3);
+        assert_eq!(history[1].reason, RestartReason::FailedTest);
+        assert_eq!(history[1].failed_test.as_deref(), Some("always false"));
+        assert_eq!(history[1].backoff, Duration::from_secs(10));
+
+        let lines = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(lines.lines().count(), 3);
+        assert!(lines
+            .lines()
+            .all(|line| line.contains(" reason=failed_test failed_test=\"always false\" ")));
+    }
+
+    #[test]
+    fn durations_can_be_given_as_strings() {
+        let process = SupervisedProcess::new("my-daemon")
+            .with_check_interval_str("1m 30s")
+            .and_then(|process| process.with_backoff_time_str("250ms"))
+            .unwrap();
+        assert_eq!(process.check_interval, Duration::from_secs(90));
+        assert_eq!(process.backoff_time, Duration::from_millis(250));
+
+        assert_eq!(
+            SupervisedProcess::new("my-daemon")
+                .with_initial_delay_str("soon")
+                .err(),
+            Some(BuilderError::InvalidDuration("soon".into()))
+        );
+    }
+
+    #[test]
+    fn running_once_tells_how_the_child_failed() {
+        let backend = MockBackend::new();
+        let mut restarts = 0;
+        let mut process = SupervisedProcess::new("my-daemon")
+            .with_backend(backend.clone())
+            .with_clock(MockClock::new())
+            .add_test("always false", |_: &mut ChildInfo| false)
+            .with_restart_times(5)
+            .on_restart(|_: &RestartContext| restarts += 1);
+
+        let run = process.run_once().unwrap();
+        assert_eq!(run.outcome, RunOutcome::ChildFailed);
+        let context = run.context.unwrap();
+        assert_eq!(context.failed_test.as_deref(), Some("always false"));
+        assert_eq!(context.exit_status.unwrap().code(), Some(137));
+        // The restart budget is left alone
+        assert_eq!(process.restarts_remaining(), Some(5));
+        drop(process);
+
+        assert_eq!(restarts, 0);
+        assert_eq!(backend.pids().len(), 1);
+    }
+
+    #[test]
+    fn running_once_notices_the_child_exiting_without_tests() {
+        let backend = MockBackend::new();
+        backend.exit_on_spawn(Some(3));
+        let mut process = SupervisedProcess::new("my-daemon")
+            .with_backend(backend.clone())
+            .with_clock(MockClock::new())
+            .with_restart_budget(RestartBudget::Unlimited);
+
+        let run = process.run_once().unwrap();
+        assert_eq!(run.outcome, RunOutcome::ChildFailed);
+        let context = run.context.unwrap();
+        assert_eq!(context.reason, RestartReason::Exited);
+        assert_eq!(context.failed_test, None);
+        assert_eq!(context.exit_status.unwrap().code(), Some(3));
+        assert_eq!(process.restarts_remaining(), None);
+        assert_eq!(backend.pids().len(), 1);
+    }
+
+    #[test]
+    fn it_tells_about_backoffs() {
+        let events = RefCell::new(vec![]);
+        let mut process = SupervisedProcess::new("my-daemon")
+            .with_backend(MockBackend::new())
+            .with_clock(MockClock::new())
+            .add_test("always false", |_: &mut ChildInfo| false)
+            .with_backoff_time(Duration::from_secs(10))
+            .with_restart_times(2)
+            .on_backoff(|delay, attempt| {
+                events
+                    .borrow_mut()
+                    .push(format!("backoff {:?} {}", delay, attempt))
+            })
+            .on_backoff_end(|attempt| events.borrow_mut().push(format!("end {}", attempt)));
+        assert_eq!(process.run().unwrap(), RunOutcome::RestartsExhausted);
+        drop(process);
+
+        assert_eq!(
+            events.into_inner(),
+            ["backoff 10s 1", "end 1", "backoff 10s 2", "end 2"]
+        );
+    }
+
+    #[test]
+    fn checks_can_be_aligned_to_the_wall_clock() {
+        let uptimes = RefCell::new(vec![]);
+        let started = UNIX_EPOCH + Duration::from_secs(1025);
+        let mut process = SupervisedProcess::new("my-daemon")
+            .with_backend(MockBackend::new())
+            .with_clock(MockClock::starting_at(started))
+            .add_test("always false", |_: &mut ChildInfo| false)
+            .with_check_interval(Duration::from_secs(60))
+            .with_backoff_time(Duration::from_secs(10))
+            .with_aligned_checks(true)
+            .with_restart_times(1)
+            .on_restart(|context: &RestartContext| uptimes.borrow_mut().push(context.uptime))
+            .on_no_restart(|context: &RestartContext| uptimes.borrow_mut().push(context.uptime));
+        assert_eq!(process.run().unwrap(), RunOutcome::RestartsExhausted);
+        drop(process);
+
+        // Started at 1025s then 1090s, checked at 1080s and 1140s
+        assert_eq!(
+            uptimes.into_inner(),
+            [Duration::from_secs(55), Duration::from_secs(50)]
+        );
+    }
+
+    #[test]
+    fn it_checks_the_program_before_running() {
+        let mut spawns = 0;
+        let mut process = SupervisedProcess::new("sh")
+            .with_program(PathBuf::from("/nonexistent/my-daemon"))
+            .on_spawn(|_| spawns += 1);
+        match process.run() {
+            Err(SupervisorError::Spawn { source, .. }) => {
+                assert_eq!(source.kind(), std::io::ErrorKind::NotFound)
+            }
+            result => panic!("unexpected {:?}", result),
+        }
+        drop(process);
+        assert_eq!(spawns, 0);
     }
 }
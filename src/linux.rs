@@ -0,0 +1,190 @@
+use std::{
+    ffi::CString,
+    fs, io, mem,
+    ops::BitOr,
+    os::unix::{ffi::OsStrExt, process::CommandExt},
+    path::Path,
+    process::Command,
+    ptr,
+};
+
+// Namespaces the child gets of its own, combined with `|`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NamespaceFlags(libc::c_int);
+
+impl NamespaceFlags {
+    pub const MOUNT: Self = Self(libc::CLONE_NEWNS);
+    pub const NETWORK: Self = Self(libc::CLONE_NEWNET);
+    // Only the children of the child end up in the new PID namespace, the
+    // child itself keeps its PID
+    pub const PID: Self = Self(libc::CLONE_NEWPID);
+    pub const IPC: Self = Self(libc::CLONE_NEWIPC);
+    pub const UTS: Self = Self(libc::CLONE_NEWUTS);
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl BitOr for NamespaceFlags {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+// How the kernel schedules the disk accesses of the child, see ionice(1)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoPriorityClass {
+    // Needs root, and can starve everything else
+    RealTime,
+    BestEffort,
+    // Only gets disk time when nobody else wants it. The level is ignored.
+    Idle,
+}
+
+impl IoPriorityClass {
+    fn code(self) -> libc::c_int {
+        match self {
+            Self::RealTime => 1,
+            Self::BestEffort => 2,
+            Self::Idle => 3,
+        }
+    }
+}
+
+// Sets the IO priority of the child before it runs the program. Levels go
+// from 0, the highest, to 7.
+pub(crate) fn set_io_priority(
+    command: &mut Command,
+    class: IoPriorityClass,
+    level: u8,
+) -> io::Result<()> {
+    const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+    const IOPRIO_CLASS_SHIFT: libc::c_int = 13;
+
+    if level > 7 {
+        let message = format!("IO priority level {} is out of range", level);
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+    }
+    let priority = class.code() << IOPRIO_CLASS_SHIFT | libc::c_int::from(level);
+    let set_io_priority = move || {
+        let result =
+            unsafe { libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, priority) };
+        if result != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    };
+    unsafe { command.pre_exec(set_io_priority) };
+    Ok(())
+}
+
+// Unshares the namespaces then changes the root of the child before it runs
+// the program, moving to `working_dir` inside the new root
+pub(crate) fn isolate(
+    command: &mut Command,
+    namespaces: NamespaceFlags,
+    root: Option<&Path>,
+    working_dir: Option<&Path>,
+) -> io::Result<()> {
+    // Nothing gets allocated between fork and exec
+    let path = |path: &Path| {
+        CString::new(path.as_os_str().as_bytes())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+    };
+    let root = root.map(path).transpose()?;
+    let working_dir = path(working_dir.unwrap_or(Path::new("/")))?;
+    let isolate = move || {
+        if !namespaces.is_empty() && unsafe { libc::unshare(namespaces.0) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if let Some(root) = &root {
+            if unsafe { libc::chroot(root.as_ptr()) } != 0
+                || unsafe { libc::chdir(working_dir.as_ptr()) } != 0
+            {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    };
+    unsafe { command.pre_exec(isolate) };
+    Ok(())
+}
+
+// Pins the child to the given CPUs before it runs the program
+pub(crate) fn set_cpu_affinity(command: &mut Command, cpus: &[usize]) -> io::Result<()> {
+    let mut set: libc::cpu_set_t = unsafe { mem::zeroed() };
+    for &cpu in cpus {
+        if cpu >= libc::CPU_SETSIZE as usize {
+            let message = format!("CPU {} is out of range", cpu);
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+        }
+        unsafe { libc::CPU_SET(cpu, &mut set) };
+    }
+    let set_cpu_affinity = move || {
+        if unsafe { libc::sched_setaffinity(0, mem::size_of_val(&set), &set) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    };
+    unsafe { command.pre_exec(set_cpu_affinity) };
+    Ok(())
+}
+
+pub(crate) fn become_subreaper() -> io::Result<()> {
+    if unsafe { libc::prctl(libc::PR_SET_CHILD_SUBREAPER, 1, 0, 0, 0) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+// Reaps every exited child except the supervised one, whose status belongs to
+// its `Child`. Children are peeked with WNOWAIT first so that the supervised
+// process is never waited on by accident.
+pub(crate) fn reap_orphans(supervised: u32) {
+    loop {
+        let mut info: libc::siginfo_t = unsafe { mem::zeroed() };
+        let flags = libc::WEXITED | libc::WNOHANG | libc::WNOWAIT;
+        if unsafe { libc::waitid(libc::P_ALL, 0, &mut info, flags) } != 0 {
+            return;
+        }
+
+        let pid = unsafe { info.si_pid() };
+        if pid == 0 || pid as u32 == supervised {
+            return;
+        }
+        unsafe {
+            libc::waitpid(pid, ptr::null_mut(), libc::WNOHANG);
+        }
+    }
+}
+
+// How many processes the OOM killer killed since boot, across the system
+pub(crate) fn oom_kills() -> Option<u64> {
+    let vmstat = fs::read_to_string("/proc/vmstat").ok()?;
+    vmstat
+        .lines()
+        .find_map(|line| line.strip_prefix("oom_kill "))
+        .and_then(|count| count.trim().parse().ok())
+}
+
+// When the process started, in clock ticks since boot. A process later given
+// the same PID has another one.
+pub(crate) fn start_time(pid: u32) -> Option<String> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // Field 22, counting from the state right after the command name
+    let (_, rest) = stat.rsplit_once(')')?;
+    rest.split_whitespace().nth(19).map(str::to_string)
+}
+
+// Zombies are dead, but still show up until their parent waits on them
+pub(crate) fn is_zombie(pid: u32) -> bool {
+    fs::read_to_string(format!("/proc/{}/stat", pid)).is_ok_and(|stat| {
+        // The command name in parentheses may contain anything, the state
+        // comes right after it
+        stat.rsplit_once(')')
+            .is_some_and(|(_, rest)| rest.trim_start().starts_with('Z'))
+    })
+}
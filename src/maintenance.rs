@@ -0,0 +1,110 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::BuilderError;
+
+const DAY: u64 = 24 * 3600;
+
+// A time of day when the systems the child relies on are known to be down,
+// e.g. for nightly maintenance. Repeats every day, in UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaintenanceWindow {
+    // Since midnight
+    start: Duration,
+    length: Duration,
+}
+
+impl MaintenanceWindow {
+    // Windows may run past midnight
+    pub fn daily(hour: u8, minute: u8, length: Duration) -> Result<Self, BuilderError> {
+        if hour >= 24 || minute >= 60 {
+            let start = format!("{:02}:{:02}", hour, minute);
+            return Err(BuilderError::InvalidMaintenanceWindow(start));
+        }
+        Ok(Self {
+            start: Duration::from_secs(since_midnight(hour, minute)),
+            length,
+        })
+    }
+
+    // Parses windows like "02:00-03:30" or "23:30-00:15"
+    pub fn parse(value: &str) -> Result<Self, BuilderError> {
+        let invalid = || BuilderError::InvalidMaintenanceWindow(value.to_string());
+        let time = |time: &str| {
+            let (hour, minute) = time.trim().split_once(':')?;
+            let (hour, minute): (u8, u8) = (hour.parse().ok()?, minute.parse().ok()?);
+            (hour < 24 && minute < 60).then_some((hour, minute))
+        };
+        let (start, end) = value.split_once('-').ok_or_else(invalid)?;
+        let ((start_hour, start_minute), (end_hour, end_minute)) = (
+            time(start).ok_or_else(invalid)?,
+            time(end).ok_or_else(invalid)?,
+        );
+        let (start, end) = (
+            since_midnight(start_hour, start_minute),
+            since_midnight(end_hour, end_minute),
+        );
+        let length = (end + DAY - start) % DAY;
+        if length == 0 {
+            return Err(invalid());
+        }
+        Self::daily(start_hour, start_minute, Duration::from_secs(length))
+    }
+
+    pub(crate) fn contains(&self, time: SystemTime) -> bool {
+        let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let day = Duration::from_secs(DAY);
+        let time_of_day = Duration::from_nanos((since_epoch.as_nanos() % day.as_nanos()) as u64);
+        let since_start = match time_of_day.checked_sub(self.start) {
+            Some(since_start) => since_start,
+            None => time_of_day + day - self.start,
+        };
+        since_start < self.length
+    }
+}
+
+fn since_midnight(hour: u8, minute: u8) -> u64 {
+    u64::from(hour) * 3600 + u64::from(minute) * 60
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(hour: u64, minute: u64) -> SystemTime {
+        // Some day at midnight, UTC
+        UNIX_EPOCH + Duration::from_secs(1_700_006_400 + hour * 3600 + minute * 60)
+    }
+
+    #[test]
+    fn windows_repeat_every_day() {
+        let window = MaintenanceWindow::parse("02:00-03:30").unwrap();
+        assert_eq!(
+            window,
+            MaintenanceWindow::daily(2, 0, Duration::from_secs(90 * 60)).unwrap()
+        );
+        assert!(!window.contains(at(1, 59)));
+        assert!(window.contains(at(2, 0)));
+        assert!(window.contains(at(27, 29)));
+        assert!(!window.contains(at(3, 30)));
+
+        let overnight = MaintenanceWindow::parse("23:30-00:15").unwrap();
+        assert!(overnight.contains(at(23, 45)));
+        assert!(overnight.contains(at(24, 10)));
+        assert!(!overnight.contains(at(24, 15)));
+
+        for invalid in ["02:00", "02:00-02:00", "25:00-01:00", "2h-3h"] {
+            assert_eq!(
+                MaintenanceWindow::parse(invalid),
+                Err(BuilderError::InvalidMaintenanceWindow(invalid.into()))
+            );
+        }
+        assert_eq!(
+            MaintenanceWindow::daily(24, 0, Duration::from_secs(60)),
+            Err(BuilderError::InvalidMaintenanceWindow("24:00".into()))
+        );
+        assert_eq!(
+            MaintenanceWindow::daily(2, 60, Duration::from_secs(60)),
+            Err(BuilderError::InvalidMaintenanceWindow("02:60".into()))
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+
+use regex::Regex;
+
+use crate::TestOutcome;
+
+pub(crate) const OUTPUT_MATCH_TEST: &str = "output match";
+
+// What to do when a line of the child's stdout or stderr matches a pattern
+pub enum OutputAction {
+    // Holds off the tests until a matching line shows up
+    MarkReady,
+    // Fails the next check
+    MarkUnhealthy,
+    // Restarts the child on the next check, without waiting for the backoff
+    Restart,
+    Custom(Box<dyn FnMut(&str) + Send>),
+}
+
+// Shared by every child of the supervisor
+#[derive(Clone, Default)]
+pub(crate) struct OutputMatchers {
+    matchers: Arc<Mutex<Vec<(Regex, OutputAction)>>>,
+}
+
+impl OutputMatchers {
+    pub(crate) fn push(&self, pattern: Regex, action: OutputAction) {
+        self.matchers.lock().unwrap().push((pattern, action));
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.matchers.lock().unwrap().is_empty()
+    }
+
+    pub(crate) fn waits_for_ready(&self) -> bool {
+        self.any(|action| matches!(action, OutputAction::MarkReady))
+    }
+
+    pub(crate) fn reports_failures(&self) -> bool {
+        self.any(|action| matches!(action, OutputAction::MarkUnhealthy | OutputAction::Restart))
+    }
+
+    fn any(&self, predicate: impl Fn(&OutputAction) -> bool) -> bool {
+        self.matchers
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|(_, action)| predicate(action))
+    }
+}
+
+// Matches the output of a single child, remembering what it triggered
+#[derive(Clone)]
+pub(crate) struct LineMatcher {
+    matchers: OutputMatchers,
+    flags: Arc<[AtomicBool; 3]>,
+}
+
+const READY: usize = 0;
+const UNHEALTHY: usize = 1;
+const RESTART: usize = 2;
+
+impl LineMatcher {
+    pub(crate) fn new(matchers: OutputMatchers) -> Self {
+        Self {
+            matchers,
+            flags: Arc::default(),
+        }
+    }
+
+    pub(crate) fn check(&self, line: &str) {
+        let mut matchers = self.matchers.matchers.lock().unwrap();
+        for (pattern, action) in matchers.iter_mut() {
+            if !pattern.is_match(line) {
+                continue;
+            }
+            match action {
+                OutputAction::MarkReady => self.flags[READY].store(true, Ordering::SeqCst),
+                OutputAction::MarkUnhealthy => self.flags[UNHEALTHY].store(true, Ordering::SeqCst),
+                OutputAction::Restart => self.flags[RESTART].store(true, Ordering::SeqCst),
+                OutputAction::Custom(callback) => callback(line),
+            }
+        }
+    }
+
+    pub(crate) fn is_ready(&self) -> bool {
+        self.flags[READY].load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn outcome(&self) -> TestOutcome {
+        if self.flags[RESTART].load(Ordering::SeqCst) {
+            TestOutcome::RestartNow
+        } else if self.flags[UNHEALTHY].load(Ordering::SeqCst) {
+            TestOutcome::Unhealthy
+        } else {
+            TestOutcome::Healthy
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_remembers_what_the_output_triggered() {
+        let matchers = OutputMatchers::default();
+        let seen = Arc::new(Mutex::new(vec![]));
+        let seen_by_callback = seen.clone();
+        matchers.push(
+            Regex::new("^listening on").unwrap(),
+            OutputAction::MarkReady,
+        );
+        matchers.push(Regex::new("^FATAL:").unwrap(), OutputAction::MarkUnhealthy);
+        matchers.push(
+            Regex::new("port (\\d+)").unwrap(),
+            OutputAction::Custom(Box::new(move |line| {
+                seen_by_callback.lock().unwrap().push(line.to_string())
+            })),
+        );
+
+        let matcher = LineMatcher::new(matchers);
+        matcher.check("starting");
+        assert!(!matcher.is_ready());
+        matcher.check("listening on port 8080");
+        assert!(matcher.is_ready());
+        assert_eq!(matcher.outcome(), TestOutcome::Healthy);
+        matcher.check("FATAL: out of memory");
+        assert_eq!(matcher.outcome(), TestOutcome::Unhealthy);
+        assert_eq!(*seen.lock().unwrap(), vec!["listening on port 8080"]);
+    }
+}
@@ -0,0 +1,24 @@
+use crate::RestartContext;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotifyEvent {
+    Restart,
+    NoRestart,
+    Flapping,
+}
+
+impl NotifyEvent {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Restart => "restart",
+            Self::NoRestart => "no_restart",
+            Self::Flapping => "flapping",
+        }
+    }
+}
+
+// Sends restart and no-restart events somewhere outside the process, like a
+// webhook or a mailbox
+pub trait Notifier {
+    fn notify(&mut self, event: NotifyEvent, context: &RestartContext);
+}
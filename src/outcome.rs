@@ -0,0 +1,218 @@
+use std::time::Duration;
+
+use crate::RestartContext;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestOutcome {
+    Healthy,
+    Unhealthy,
+    Fatal,
+    RestartNow,
+    Ignore,
+}
+
+impl TestOutcome {
+    pub fn is_failure(self) -> bool {
+        matches!(self, Self::Unhealthy | Self::Fatal | Self::RestartNow)
+    }
+
+    fn severity(self) -> u8 {
+        match self {
+            Self::Healthy | Self::Ignore => 0,
+            Self::Unhealthy => 1,
+            Self::RestartNow => 2,
+            Self::Fatal => 3,
+        }
+    }
+
+    // Combines the outcomes of two tests, keeping the most severe one
+    pub(crate) fn worst(self, other: Self) -> Self {
+        if other.severity() > self.severity() {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+// How the outcomes of the tests of a check make up its own. Tests that are
+// fatal or ask for a restart always count, the policy only decides how many
+// unhealthy tests a check can put up with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TestAggregation {
+    #[default]
+    AllMustPass,
+    AnyPasses,
+    // At least this many tests must pass
+    Quorum(usize),
+}
+
+impl TestAggregation {
+    // Whether the check can stop running tests after one with `outcome`
+    pub(crate) fn is_decided(self, outcome: TestOutcome) -> bool {
+        match self {
+            Self::AllMustPass => outcome.is_failure(),
+            _ => outcome.worst(TestOutcome::Unhealthy) != TestOutcome::Unhealthy,
+        }
+    }
+
+    // The outcome of a check from the worst outcome of its tests and how many
+    // of them passed
+    pub(crate) fn aggregate(self, worst: TestOutcome, passed: usize) -> TestOutcome {
+        let enough = match self {
+            Self::AllMustPass => false,
+            Self::AnyPasses => passed >= 1,
+            Self::Quorum(quorum) => passed >= quorum,
+        };
+        match worst {
+            TestOutcome::Unhealthy if enough => TestOutcome::Healthy,
+            worst => worst,
+        }
+    }
+}
+
+// Why a supervisor stopped running
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunOutcome {
+    // The child kept failing until it ran out of restarts or attempts
+    RestartsExhausted,
+    StoppedByHandle,
+    // Stopped by ctrl-c, a forwarded terminating signal or the shutdown flag
+    StoppedBySignal,
+    TotalTimeout,
+    FatalTest,
+    JobSucceeded,
+    // The child run by `run_once` failed
+    ChildFailed,
+}
+
+// How the child run by `run_once` ended. The context is there when the child
+// failed or a test was fatal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SingleRun {
+    pub outcome: RunOutcome,
+    pub context: Option<RestartContext>,
+}
+
+// How a test has been doing lately
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestStats {
+    pub name: String,
+    pub last_outcome: TestOutcome,
+    pub consecutive_failures: u32,
+    // How long its last run took. Unknown for tests that missed their
+    // deadline, or that the supervisor runs itself like the stdio watchdog.
+    pub latency: Option<Duration>,
+}
+
+// Updates the stats of the test called `name`, adding them the first time
+pub(crate) fn record<'s>(
+    stats: &'s mut Vec<TestStats>,
+    name: &str,
+    outcome: TestOutcome,
+    latency: Option<Duration>,
+) -> &'s TestStats {
+    let index = match stats.iter().position(|test| test.name == name) {
+        Some(index) => index,
+        None => {
+            stats.push(TestStats {
+                name: name.into(),
+                last_outcome: outcome,
+                consecutive_failures: 0,
+                latency,
+            });
+            stats.len() - 1
+        }
+    };
+    let test = &mut stats[index];
+    test.last_outcome = outcome;
+    test.latency = latency;
+    if outcome.is_failure() {
+        test.consecutive_failures += 1;
+    } else if outcome == TestOutcome::Healthy {
+        test.consecutive_failures = 0;
+    }
+    test
+}
+
+impl From<bool> for TestOutcome {
+    fn from(healthy: bool) -> Self {
+        if healthy {
+            Self::Healthy
+        } else {
+            Self::Unhealthy
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_keeps_the_worst_outcome() {
+        use TestOutcome::*;
+
+        assert_eq!(Healthy.worst(Ignore), Healthy);
+        assert_eq!(Ignore.worst(Unhealthy), Unhealthy);
+        assert_eq!(Unhealthy.worst(RestartNow), RestartNow);
+        assert_eq!(Fatal.worst(RestartNow), Fatal);
+    }
+
+    #[test]
+    fn aggregation_policies_forgive_some_unhealthy_tests() {
+        use TestOutcome::*;
+
+        assert_eq!(
+            TestAggregation::AllMustPass.aggregate(Unhealthy, 3),
+            Unhealthy
+        );
+        assert_eq!(TestAggregation::AnyPasses.aggregate(Unhealthy, 1), Healthy);
+        assert_eq!(
+            TestAggregation::AnyPasses.aggregate(Unhealthy, 0),
+            Unhealthy
+        );
+        assert_eq!(
+            TestAggregation::Quorum(2).aggregate(Unhealthy, 1),
+            Unhealthy
+        );
+        assert_eq!(TestAggregation::Quorum(2).aggregate(Unhealthy, 2), Healthy);
+        assert_eq!(TestAggregation::AnyPasses.aggregate(Fatal, 5), Fatal);
+
+        assert!(TestAggregation::AllMustPass.is_decided(Unhealthy));
+        assert!(!TestAggregation::AnyPasses.is_decided(Unhealthy));
+        assert!(TestAggregation::Quorum(2).is_decided(RestartNow));
+    }
+
+    #[test]
+    fn it_records_consecutive_failures() {
+        let mut stats = vec![];
+        record(&mut stats, "http", TestOutcome::Unhealthy, None);
+        record(&mut stats, "tcp", TestOutcome::Healthy, None);
+        record(&mut stats, "http", TestOutcome::Ignore, None);
+        let http = record(
+            &mut stats,
+            "http",
+            TestOutcome::Unhealthy,
+            Some(Duration::from_millis(5)),
+        );
+        assert_eq!(
+            *http,
+            TestStats {
+                name: "http".into(),
+                last_outcome: TestOutcome::Unhealthy,
+                consecutive_failures: 2,
+                latency: Some(Duration::from_millis(5)),
+            }
+        );
+        record(&mut stats, "http", TestOutcome::Healthy, None);
+        assert_eq!(stats[0].consecutive_failures, 0);
+        assert_eq!(stats.len(), 2);
+    }
+
+    #[test]
+    fn it_converts_from_bool() {
+        assert_eq!(TestOutcome::from(true), TestOutcome::Healthy);
+        assert_eq!(TestOutcome::from(false), TestOutcome::Unhealthy);
+    }
+}
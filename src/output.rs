@@ -0,0 +1,343 @@
+use std::{
+    collections::VecDeque,
+    io::{BufRead, BufReader, Read},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, Receiver, SyncSender, TrySendError},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+#[cfg(feature = "output-match")]
+use crate::matcher::LineMatcher;
+#[cfg(all(feature = "syslog", unix))]
+use crate::syslog::SyslogSender;
+
+// How many lines a receiver can fall behind before new ones are dropped
+const STREAM_CAPACITY: usize = 1024;
+
+// A line the child wrote to its stdout, along with which of the successive
+// children wrote it, starting from 1. Lines streamed by a group also tell
+// which of its members wrote them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputLine {
+    pub incarnation: u64,
+    pub line: String,
+    pub member: Option<String>,
+}
+
+// The receivers of the stdout of every child of a supervisor, and the
+// streams of the groups it is a member of
+#[derive(Clone, Default)]
+pub(crate) struct LineStreams {
+    senders: Arc<Mutex<Vec<SyncSender<OutputLine>>>>,
+    forwards: Arc<Mutex<Vec<(LineStreams, String)>>>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl LineStreams {
+    pub(crate) fn subscribe(&self) -> Receiver<OutputLine> {
+        let (sender, receiver) = mpsc::sync_channel(STREAM_CAPACITY);
+        self.senders.lock().unwrap().push(sender);
+        receiver
+    }
+
+    // Also sends the lines to `streams`, as written by `member`
+    pub(crate) fn forward_to(&self, streams: LineStreams, member: &str) {
+        self.forwards
+            .lock()
+            .unwrap()
+            .push((streams, member.to_string()));
+    }
+
+    // Lines that receivers too far behind missed
+    pub(crate) fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    // Receivers that were dropped are forgotten
+    fn send(&self, line: OutputLine) {
+        self.senders
+            .lock()
+            .unwrap()
+            .retain(|sender| match sender.try_send(line.clone()) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) => {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    true
+                }
+                Err(TrySendError::Disconnected(_)) => false,
+            });
+        for (streams, member) in self.forwards.lock().unwrap().iter() {
+            streams.send(OutputLine {
+                member: Some(member.clone()),
+                ..line.clone()
+            });
+        }
+    }
+
+    // Ends the streams, once no more children will be started
+    pub(crate) fn close(&self) {
+        self.senders.lock().unwrap().clear();
+    }
+}
+
+// The last lines the child wrote to its stdout and stderr
+#[derive(Clone, Default)]
+pub(crate) struct OutputTail {
+    lines: Arc<Mutex<VecDeque<String>>>,
+    capacity: usize,
+    streams: Option<(LineStreams, u64)>,
+    #[cfg(feature = "tracing")]
+    tracing: Option<Arc<TracedChild>>,
+    #[cfg(all(feature = "syslog", unix))]
+    syslog: Option<SyslogSender>,
+    #[cfg(feature = "output-match")]
+    matcher: Option<LineMatcher>,
+}
+
+impl OutputTail {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            lines: Arc::default(),
+            capacity,
+            streams: None,
+            #[cfg(feature = "tracing")]
+            tracing: None,
+            #[cfg(all(feature = "syslog", unix))]
+            syslog: None,
+            #[cfg(feature = "output-match")]
+            matcher: None,
+        }
+    }
+
+    pub(crate) fn with_streams(self, streams: LineStreams, incarnation: u64) -> Self {
+        Self {
+            streams: Some((streams, incarnation)),
+            ..self
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    pub(crate) fn with_tracing(self, tracing: TracedChild) -> Self {
+        Self {
+            tracing: Some(Arc::new(tracing)),
+            ..self
+        }
+    }
+
+    #[cfg(all(feature = "syslog", unix))]
+    pub(crate) fn with_syslog(self, syslog: Option<SyslogSender>) -> Self {
+        Self { syslog, ..self }
+    }
+
+    #[cfg(feature = "output-match")]
+    pub(crate) fn with_matcher(self, matcher: LineMatcher) -> Self {
+        Self {
+            matcher: Some(matcher),
+            ..self
+        }
+    }
+
+    #[cfg(feature = "output-match")]
+    pub(crate) fn matcher(&self) -> Option<&LineMatcher> {
+        self.matcher.as_ref()
+    }
+
+    pub(crate) fn push(&self, line: String) {
+        #[cfg(feature = "output-match")]
+        if let Some(matcher) = &self.matcher {
+            matcher.check(&line);
+        }
+        if self.capacity == 0 {
+            return;
+        }
+        let mut lines = self.lines.lock().unwrap();
+        if lines.len() == self.capacity {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    // Forwards a line of the child's output to ours, or to syslog or the
+    // tracing subscriber, before keeping it
+    pub(crate) fn forward(&self, line: String, stderr: bool) {
+        #[cfg(all(feature = "syslog", unix))]
+        if let Some(syslog) = &self.syslog {
+            syslog.send(&line, stderr);
+            return self.keep(line, stderr);
+        }
+        #[cfg(feature = "tracing")]
+        if let Some(tracing) = &self.tracing {
+            tracing.emit(&line, stderr);
+            return self.keep(line, stderr);
+        }
+        if stderr {
+            eprintln!("{}", line);
+        } else {
+            println!("{}", line);
+        }
+        self.keep(line, stderr);
+    }
+
+    fn keep(&self, line: String, stderr: bool) {
+        match stderr {
+            true => self.push(line),
+            false => self.push_stdout(line),
+        }
+    }
+
+    // Also hands the line to the streams
+    pub(crate) fn push_stdout(&self, line: String) {
+        if let Some((streams, incarnation)) = &self.streams {
+            streams.send(OutputLine {
+                incarnation: *incarnation,
+                line: line.clone(),
+                member: None,
+            });
+        }
+        self.push(line);
+    }
+
+    pub(crate) fn snapshot(&self) -> Vec<String> {
+        self.lines.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+// Forwards the child's output to ours line by line, keeping the tail
+pub(crate) fn capture(output: impl Read + Send + 'static, to_stderr: bool, tail: OutputTail) {
+    thread::spawn(move || {
+        for line in BufReader::new(output).lines().map_while(Result::ok) {
+            tail.forward(line, to_stderr);
+        }
+    });
+}
+
+// Who wrote the lines sent to the tracing subscriber
+#[cfg(feature = "tracing")]
+pub(crate) struct TracedChild {
+    pub(crate) supervisor: Option<String>,
+    pub(crate) pid: u32,
+    pub(crate) incarnation: u64,
+}
+
+#[cfg(feature = "tracing")]
+impl TracedChild {
+    fn emit(&self, line: &str, stderr: bool) {
+        tracing::info!(
+            supervisor = self.supervisor.as_deref(),
+            stream = if stderr { "stderr" } else { "stdout" },
+            pid = self.pid,
+            incarnation = self.incarnation,
+            "{}",
+            line
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_keeps_the_last_lines() {
+        let tail = OutputTail::new(2);
+        for line in ["one", "two", "three"] {
+            tail.push(line.to_string());
+        }
+        assert_eq!(tail.snapshot(), vec!["two", "three"]);
+    }
+
+    #[test]
+    fn it_keeps_nothing_without_capacity() {
+        let tail = OutputTail::new(0);
+        tail.push("one".to_string());
+        assert!(tail.snapshot().is_empty());
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn it_sends_output_to_the_tracing_subscriber() {
+        use std::fmt::{Debug, Write};
+        use tracing::{field::Field, span, Event, Metadata, Subscriber};
+
+        struct Recorder(Arc<Mutex<Vec<String>>>);
+
+        impl Subscriber for Recorder {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+            fn new_span(&self, _: &span::Attributes<'_>) -> span::Id {
+                span::Id::from_u64(1)
+            }
+            fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+            fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+            fn event(&self, event: &Event<'_>) {
+                let mut fields = String::new();
+                event.record(&mut |field: &Field, value: &dyn Debug| {
+                    let _ = write!(fields, "{}={:?} ", field, value);
+                });
+                self.0.lock().unwrap().push(fields.trim_end().to_string());
+            }
+            fn enter(&self, _: &span::Id) {}
+            fn exit(&self, _: &span::Id) {}
+        }
+
+        let events = Arc::new(Mutex::new(vec![]));
+        let tail = OutputTail::new(1).with_tracing(TracedChild {
+            supervisor: Some("web".into()),
+            pid: 42,
+            incarnation: 3,
+        });
+        tracing::subscriber::with_default(Recorder(events.clone()), || {
+            tail.forward("listening".to_string(), false);
+            tail.forward("oops".to_string(), true);
+        });
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                "message=listening supervisor=\"web\" stream=\"stdout\" pid=42 incarnation=3",
+                "message=oops supervisor=\"web\" stream=\"stderr\" pid=42 incarnation=3",
+            ]
+        );
+        assert_eq!(tail.snapshot(), vec!["oops"]);
+    }
+
+    #[test]
+    fn it_streams_stdout_lines_until_closed() {
+        let streams = LineStreams::default();
+        let lines = streams.subscribe();
+        drop(streams.subscribe());
+        let tail = OutputTail::new(0).with_streams(streams.clone(), 2);
+        tail.push_stdout("out".to_string());
+        tail.push("err".to_string());
+        streams.close();
+
+        let line = OutputLine {
+            incarnation: 2,
+            line: "out".to_string(),
+            member: None,
+        };
+        assert_eq!(lines.into_iter().collect::<Vec<_>>(), vec![line]);
+        assert_eq!(streams.senders.lock().unwrap().len(), 0);
+    }
+
+    #[test]
+    fn it_forwards_lines_to_groups_and_drops_what_overflows() {
+        let (member, group) = (LineStreams::default(), LineStreams::default());
+        member.forward_to(group.clone(), "web");
+        let lines = group.subscribe();
+        let tail = OutputTail::new(0).with_streams(member.clone(), 1);
+        for n in 0..STREAM_CAPACITY + 2 {
+            tail.push_stdout(n.to_string());
+        }
+        group.close();
+
+        let lines: Vec<_> = lines.into_iter().collect();
+        assert_eq!(lines.len(), STREAM_CAPACITY);
+        assert_eq!(lines[0].member.as_deref(), Some("web"));
+        assert_eq!(group.dropped(), 2);
+    }
+}
@@ -0,0 +1,150 @@
+use std::{ffi::OsString, time::Duration};
+
+use crate::{check::ScheduledCheck, BuilderError, HealthCheck, Threading};
+
+// A check replacing the current ones, with the same options as when added to
+// the builder
+pub(crate) struct ReloadCheck {
+    check: Box<dyn HealthCheck + Send>,
+    every: Option<Duration>,
+    advisory: bool,
+}
+
+impl ReloadCheck {
+    pub(crate) fn into_scheduled<S: Threading>(self) -> ScheduledCheck<S> {
+        ScheduledCheck {
+            check: S::boxed_send_check(self.check),
+            every: self.every,
+            due: None,
+            advisory: self.advisory,
+        }
+    }
+}
+
+// Settings to change on a running supervisor through its handle. Unset
+// settings are left as they are. Only changing the arguments or environment
+// restarts the child.
+#[derive(Default)]
+pub struct ReloadConfig {
+    pub(crate) args: Option<Vec<OsString>>,
+    pub(crate) env: Option<Vec<(OsString, OsString)>>,
+    pub(crate) check_interval: Option<Duration>,
+    pub(crate) backoff_time: Option<Duration>,
+    pub(crate) checks: Option<Vec<ReloadCheck>>,
+}
+
+impl ReloadConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_args(self, args: impl IntoIterator<Item = impl Into<OsString>>) -> Self {
+        Self {
+            args: Some(args.into_iter().map(Into::into).collect()),
+            ..self
+        }
+    }
+
+    // Replaces every variable set with `with_env`
+    pub fn with_env(
+        self,
+        env: impl IntoIterator<Item = (impl Into<OsString>, impl Into<OsString>)>,
+    ) -> Self {
+        Self {
+            env: Some(
+                env.into_iter()
+                    .map(|(key, value)| (key.into(), value.into()))
+                    .collect(),
+            ),
+            ..self
+        }
+    }
+
+    pub fn with_check_interval(self, check_interval: Duration) -> Self {
+        Self {
+            check_interval: Some(check_interval),
+            ..self
+        }
+    }
+
+    pub fn with_backoff_time(self, backoff_time: Duration) -> Self {
+        Self {
+            backoff_time: Some(backoff_time),
+            ..self
+        }
+    }
+
+    // Replaces the tests added with `add_test` and `add_check`
+    pub fn with_checks(self, checks: Vec<Box<dyn HealthCheck + Send>>) -> Self {
+        let checks = checks.into_iter().map(|check| ReloadCheck {
+            check,
+            every: None,
+            advisory: false,
+        });
+        Self {
+            checks: Some(checks.collect()),
+            ..self
+        }
+    }
+
+    // Like `with_checks`, one check at a time. The first one added replaces
+    // the current ones too.
+    pub fn add_check(self, check: impl HealthCheck + Send + 'static) -> Self {
+        self.push_check(Box::new(check), None, false)
+    }
+
+    // Like `add_test_every` on the builder
+    pub fn add_check_every(
+        self,
+        every: Duration,
+        check: impl HealthCheck + Send + 'static,
+    ) -> Self {
+        self.push_check(Box::new(check), Some(every), false)
+    }
+
+    // Like `add_advisory_test` on the builder
+    pub fn add_advisory_check(self, check: impl HealthCheck + Send + 'static) -> Self {
+        self.push_check(Box::new(check), None, true)
+    }
+
+    fn push_check(
+        self,
+        check: Box<dyn HealthCheck + Send>,
+        every: Option<Duration>,
+        advisory: bool,
+    ) -> Self {
+        let mut checks = self.checks.unwrap_or_default();
+        checks.push(ReloadCheck {
+            check,
+            every,
+            advisory,
+        });
+        Self {
+            checks: Some(checks),
+            ..self
+        }
+    }
+
+    // `fixed` are the names of the tests the reload leaves in place
+    pub(crate) fn validate(&self, fixed: &[String]) -> Result<(), BuilderError> {
+        let checks = self.checks.iter().flatten();
+        if self
+            .check_interval
+            .is_some_and(|interval| interval.is_zero())
+            || checks
+                .clone()
+                .any(|check| check.every.is_some_and(|every| every.is_zero()))
+        {
+            return Err(BuilderError::ZeroCheckInterval);
+        }
+        let mut names: Vec<&str> = checks.map(|check| check.check.name()).collect();
+        if self.checks.is_some() {
+            names.extend(fixed.iter().map(String::as_str));
+        }
+        names.sort_unstable();
+        if let Some(pair) = names.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(BuilderError::DuplicateTest(pair[0].to_string()));
+        }
+        Ok(())
+    }
+}
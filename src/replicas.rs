@@ -0,0 +1,157 @@
+use std::{
+    panic,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    RestartContext, RunOutcome, SupervisedProcess, SupervisorError, SupervisorHandle,
+    SupervisorSpec, Threaded,
+};
+
+type Replica = SupervisedProcess<'static, Threaded>;
+type ReplicaHandler = Arc<Mutex<dyn FnMut(usize, &RestartContext) + Send>>;
+
+// Runs copies of the same supervisor, each on its own thread and supervised
+// independently. Replicas are named after the spec with their index
+// appended, and get it as {replica} in their arguments and environment.
+pub struct ReplicatedSupervisor {
+    replicas: Vec<Replica>,
+}
+
+impl ReplicatedSupervisor {
+    pub fn new(spec: SupervisorSpec, replicas: usize) -> Self {
+        let name = spec.clone().build::<Threaded>().name().map(String::from);
+        let replicas = (0..replicas)
+            .map(|index| {
+                let spec = spec.clone().with_template_var("replica", index);
+                match &name {
+                    Some(name) => spec.with_name(&format!("{}-{}", name, index)),
+                    None => spec,
+                }
+                .build()
+            })
+            .collect();
+        Self { replicas }
+    }
+
+    // Adds what a spec can't hold, like tests, to every replica
+    pub fn configure(self, mut configure: impl FnMut(usize, Replica) -> Replica) -> Self {
+        let replicas = self
+            .replicas
+            .into_iter()
+            .enumerate()
+            .map(|(index, replica)| configure(index, replica))
+            .collect();
+        Self { replicas }
+    }
+
+    pub fn on_restart(
+        self,
+        on_restart: impl FnMut(usize, &RestartContext) + Send + 'static,
+    ) -> Self {
+        let handler: ReplicaHandler = Arc::new(Mutex::new(on_restart));
+        self.configure(|index, replica| {
+            let handler = handler.clone();
+            replica.on_restart(move |context: &RestartContext| {
+                (handler.lock().unwrap())(index, context)
+            })
+        })
+    }
+
+    pub fn on_no_restart(
+        self,
+        on_no_restart: impl FnMut(usize, &RestartContext) + Send + 'static,
+    ) -> Self {
+        let handler: ReplicaHandler = Arc::new(Mutex::new(on_no_restart));
+        self.configure(|index, replica| {
+            let handler = handler.clone();
+            replica.on_no_restart(move |context: &RestartContext| {
+                (handler.lock().unwrap())(index, context)
+            })
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.replicas.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.replicas.is_empty()
+    }
+
+    // One handle per replica, in order
+    pub fn handles(&self) -> Vec<SupervisorHandle> {
+        self.replicas
+            .iter()
+            .map(SupervisedProcess::handle)
+            .collect()
+    }
+
+    // Returns once every replica stopped, with the outcome of each replica or
+    // the first error
+    pub fn run(self) -> Result<Vec<RunOutcome>, SupervisorError> {
+        let threads = self
+            .replicas
+            .into_iter()
+            .map(Replica::start)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(SupervisorError::Start)?;
+        let results: Vec<_> = threads
+            .into_iter()
+            .map(|thread| {
+                thread
+                    .join()
+                    .unwrap_or_else(|panic| panic::resume_unwind(panic))
+            })
+            .collect();
+        results.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{ChildInfo, MockBackend, MockClock};
+
+    use super::*;
+
+    #[test]
+    fn replicas_are_supervised_independently() {
+        let spec = SupervisedProcess::new("worker")
+            .with_name("worker")
+            .with_args(["--id", "{replica}"])
+            .with_check_interval(Duration::from_secs(1))
+            .with_backoff_time(Duration::from_secs(1))
+            .spec();
+        let backend = MockBackend::new();
+        let restarts = Arc::new(Mutex::new(vec![]));
+        let seen = restarts.clone();
+
+        let replicas = ReplicatedSupervisor::new(spec, 3)
+            .configure(|index, replica| {
+                replica
+                    .with_backend(backend.clone())
+                    .with_clock(MockClock::new())
+                    .with_restart_times(u64::from(index == 1))
+                    .add_test("always false", |_: &mut ChildInfo| false)
+            })
+            .on_restart(move |index, context| {
+                seen.lock().unwrap().push((index, context.name.clone()));
+            });
+        assert_eq!(replicas.handles().len(), 3);
+        assert!(replicas.run().is_ok());
+
+        assert_eq!(
+            *restarts.lock().unwrap(),
+            vec![(1, Some("worker-1".to_string()))]
+        );
+        let mut ids: Vec<_> = backend
+            .pids()
+            .into_iter()
+            .map(|pid| backend.command(pid).unwrap()[2].clone())
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec!["0", "1", "1", "2"]);
+    }
+}
@@ -0,0 +1,61 @@
+use std::{fs, io, path::Path};
+
+// Replaces `dst` by a copy of `src`
+pub(crate) fn reset(src: &Path, dst: &Path) -> io::Result<()> {
+    match fs::remove_dir_all(dst) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    copy(src, dst)
+}
+
+// Symlinks are copied as they are rather than followed
+fn copy(src: &Path, dst: &Path) -> io::Result<()> {
+    fs::create_dir_all(dst)?;
+    fs::set_permissions(dst, fs::metadata(src)?.permissions())?;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let (from, to) = (entry.path(), dst.join(entry.file_name()));
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            copy(&from, &to)?;
+        } else if file_type.is_symlink() {
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(fs::read_link(&from)?, &to)?;
+            #[cfg(windows)]
+            match fs::metadata(&from)?.is_dir() {
+                true => std::os::windows::fs::symlink_dir(fs::read_link(&from)?, &to)?,
+                false => std::os::windows::fs::symlink_file(fs::read_link(&from)?, &to)?,
+            }
+        } else {
+            fs::copy(&from, &to)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+
+    #[test]
+    fn it_replaces_the_directory_by_a_copy() {
+        let dir = env::temp_dir().join(format!("reset-{}", process::id()));
+        let (src, dst) = (dir.join("seed"), dir.join("data"));
+        fs::create_dir_all(src.join("nested")).unwrap();
+        fs::write(src.join("nested/table"), "rows").unwrap();
+        fs::create_dir_all(&dst).unwrap();
+        fs::write(dst.join("wal"), "garbage").unwrap();
+
+        reset(&src, &dst).unwrap();
+        assert_eq!(
+            fs::read_to_string(dst.join("nested/table")).unwrap(),
+            "rows"
+        );
+        assert!(!dst.join("wal").exists());
+        assert!(src.join("nested/table").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,51 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+// A directory of the child's own, removed with everything in it once the child
+// is gone
+pub(crate) struct ScratchDir {
+    path: PathBuf,
+}
+
+impl ScratchDir {
+    // Starts empty, even when a supervisor that crashed left it behind
+    pub(crate) fn create(path: PathBuf) -> io::Result<Self> {
+        match fs::remove_dir_all(&path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+        fs::create_dir_all(&path)?;
+        Ok(Self { path })
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+
+    #[test]
+    fn it_starts_empty_and_is_removed_when_dropped() {
+        let path = env::temp_dir().join(format!("scratch-{}", process::id()));
+        fs::create_dir_all(path.join("left-behind")).unwrap();
+
+        let scratch = ScratchDir::create(path.clone()).unwrap();
+        assert_eq!(fs::read_dir(scratch.path()).unwrap().count(), 0);
+        fs::write(path.join("file"), "").unwrap();
+        drop(scratch);
+        assert!(!path.exists());
+    }
+}
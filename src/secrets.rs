@@ -0,0 +1,86 @@
+use std::{
+    ffi::{OsStr, OsString},
+    fmt,
+};
+
+use crate::SubResult;
+
+const MASK: &str = "***";
+
+// Values masked in everything the supervisor reports: events, logs, history
+// and errors
+#[derive(Clone, Default)]
+pub(crate) struct Secrets {
+    values: Vec<String>,
+}
+
+impl Secrets {
+    // Empty values would mask everything
+    pub(crate) fn push(&mut self, value: &OsStr) {
+        let value = value.to_string_lossy();
+        if !value.is_empty() {
+            self.values.push(value.into_owned());
+        }
+    }
+
+    // Values are also masked when escaped by `Debug`
+    pub(crate) fn redact(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for value in &self.values {
+            text = text.replace(value.as_str(), MASK);
+            let escaped = value.escape_debug().to_string();
+            if escaped != *value {
+                text = text.replace(&escaped, MASK);
+            }
+        }
+        text
+    }
+
+    // Errors of probes within composite checks too
+    pub(crate) fn redact_sub_results(&self, results: &mut [SubResult]) {
+        for result in results {
+            if let Err(message) = &mut result.result {
+                *message = self.redact(message);
+            }
+            self.redact_sub_results(&mut result.sub_results);
+        }
+    }
+
+    pub(crate) fn redact_os(&self, value: &OsStr) -> OsString {
+        if self.values.is_empty() {
+            return value.to_os_string();
+        }
+        self.redact(&value.to_string_lossy()).into()
+    }
+}
+
+// Only tells how many there are
+impl fmt::Debug for Secrets {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Secrets({})", self.values.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_masks_every_secret() {
+        let mut secrets = Secrets::default();
+        secrets.push(OsStr::new("hunter2"));
+        secrets.push(OsStr::new("say \"hi\""));
+        secrets.push(OsStr::new(""));
+
+        assert_eq!(
+            secrets.redact("password=hunter2 token=hunter2"),
+            "password=*** token=***"
+        );
+        assert_eq!(
+            secrets.redact(&format!("{:?}", "say \"hi\"")),
+            format!("\"{}\"", MASK)
+        );
+        assert_eq!(secrets.redact_os(OsStr::new("public")), "public");
+        assert_eq!(format!("{:?}", secrets), "Secrets(2)");
+    }
+}
@@ -0,0 +1,152 @@
+use std::{process::ExitStatus, time::Duration};
+
+#[cfg(feature = "tokio")]
+use std::future::Future;
+
+#[cfg(feature = "tokio")]
+use crate::{async_tests::TestFuture, Probe, TestOutcome};
+use crate::{ChildInfo, HealthCheck, IntoTestResult, Notifier, RestartContext, SubResult};
+
+// Markers choosing whether a supervisor can be moved to another thread.
+// `Local` accepts any test and handler, `Threaded` only accepts `Send` ones.
+pub struct Local;
+pub struct Threaded;
+
+macro_rules! threading {
+    ($($kind:ident: $bound:path => $boxed:ident,)*) => {
+        // What a supervisor keeps its tests and handlers boxed as. They are
+        // `Send` for `Threaded` ones, so that the supervisor is `Send` too.
+        pub trait Threading: Sized {
+            $(type $kind<'a>: $bound + ?Sized + 'a;)*
+            #[cfg(feature = "tokio")]
+            type AsyncTest: FnMut(Probe) -> TestFuture + ?Sized;
+
+            fn boxed_send_check(check: Box<dyn HealthCheck + Send>) -> Box<Self::Check<'static>>;
+        }
+
+        impl Threading for Local {
+            $(type $kind<'a> = dyn $bound + 'a;)*
+            #[cfg(feature = "tokio")]
+            type AsyncTest = dyn FnMut(Probe) -> TestFuture;
+
+            fn boxed_send_check(check: Box<dyn HealthCheck + Send>) -> Box<Self::Check<'static>> {
+                check
+            }
+        }
+
+        impl Threading for Threaded {
+            $(type $kind<'a> = dyn $bound + Send + 'a;)*
+            #[cfg(feature = "tokio")]
+            type AsyncTest = dyn FnMut(Probe) -> TestFuture + Send;
+
+            fn boxed_send_check(check: Box<dyn HealthCheck + Send>) -> Box<Self::Check<'static>> {
+                check
+            }
+        }
+
+        // Tests and handlers that can be added to a supervisor, and boxed the
+        // way it keeps them
+        pub trait MaybeSend<S: Threading> {
+            $(
+                #[doc(hidden)]
+                fn $boxed<'a>(self) -> Box<S::$kind<'a>>
+                where
+                    Self: $bound + Sized + 'a;
+            )*
+
+            #[doc(hidden)]
+            fn boxed_test<O>(self, name: String) -> Box<S::Check<'static>>
+            where
+                Self: FnMut(&mut ChildInfo) -> O + Sized + 'static,
+                O: IntoTestResult + 'static;
+
+            #[cfg(feature = "tokio")]
+            #[doc(hidden)]
+            fn boxed_async_test<F, O>(self) -> Box<S::AsyncTest>
+            where
+                Self: FnMut(Probe) -> F + Sized + 'static,
+                F: Future<Output = O> + 'static,
+                O: Into<TestOutcome>;
+        }
+
+        impl<T: ?Sized> MaybeSend<Local> for T {
+            $(
+                fn $boxed<'a>(self) -> Box<dyn $bound + 'a>
+                where
+                    Self: $bound + Sized + 'a,
+                {
+                    Box::new(self)
+                }
+            )*
+
+            fn boxed_test<O>(self, name: String) -> Box<dyn HealthCheck>
+            where
+                Self: FnMut(&mut ChildInfo) -> O + Sized + 'static,
+                O: IntoTestResult + 'static,
+            {
+                Box::new((name, self))
+            }
+
+            #[cfg(feature = "tokio")]
+            fn boxed_async_test<F, O>(self) -> Box<dyn FnMut(Probe) -> TestFuture>
+            where
+                Self: FnMut(Probe) -> F + Sized + 'static,
+                F: Future<Output = O> + 'static,
+                O: Into<TestOutcome>,
+            {
+                let mut test = self;
+                Box::new(move |probe| {
+                    let test = test(probe);
+                    Box::pin(async move { test.await.into() })
+                })
+            }
+        }
+
+        impl<T: Send + ?Sized> MaybeSend<Threaded> for T {
+            $(
+                fn $boxed<'a>(self) -> Box<dyn $bound + Send + 'a>
+                where
+                    Self: $bound + Sized + 'a,
+                {
+                    Box::new(self)
+                }
+            )*
+
+            fn boxed_test<O>(self, name: String) -> Box<dyn HealthCheck + Send>
+            where
+                Self: FnMut(&mut ChildInfo) -> O + Sized + 'static,
+                O: IntoTestResult + 'static,
+            {
+                Box::new((name, self))
+            }
+
+            #[cfg(feature = "tokio")]
+            fn boxed_async_test<F, O>(self) -> Box<dyn FnMut(Probe) -> TestFuture + Send>
+            where
+                Self: FnMut(Probe) -> F + Sized + 'static,
+                F: Future<Output = O> + 'static,
+                O: Into<TestOutcome>,
+            {
+                let mut test = self;
+                Box::new(move |probe| {
+                    let test = test(probe);
+                    Box::pin(async move { test.await.into() })
+                })
+            }
+        }
+    };
+}
+
+threading! {
+    Check: HealthCheck => boxed_check,
+    Notifier: Notifier => boxed_notifier,
+    Handler: FnMut() => boxed_handler,
+    TestHandler: FnMut(&str) => boxed_test_handler,
+    PanicHandler: FnMut(&str, &str) => boxed_panic_handler,
+    SubResultsHandler: FnMut(&str, &[SubResult]) => boxed_sub_results_handler,
+    RestartHandler: FnMut(&RestartContext) => boxed_restart_handler,
+    SpawnHandler: FnMut(u32) => boxed_spawn_handler,
+    ExitHandler: FnMut(ExitStatus) => boxed_exit_handler,
+    BackoffHandler: FnMut(Duration, u64) => boxed_backoff_handler,
+    BackoffEndHandler: FnMut(u64) => boxed_backoff_end_handler,
+}
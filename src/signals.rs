@@ -0,0 +1,142 @@
+use std::{
+    io, mem, ptr,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+// How many times each signal was received, counted by the handlers installed
+// for forwarding. Every forwarder keeps its own tally of what it relayed, so
+// supervisors sharing the process each see every signal.
+static RECEIVED: [AtomicU64; 65] = [const { AtomicU64::new(0) }; 65];
+
+extern "C" fn record(signal: libc::c_int) {
+    if let Some(count) = RECEIVED.get(signal as usize) {
+        count.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+// Signals after which the child is expected to exit, and the supervisor to
+// stop along with it instead of restarting it
+pub(crate) fn is_terminating(signal: i32) -> bool {
+    [libc::SIGTERM, libc::SIGINT, libc::SIGQUIT].contains(&signal)
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct SignalForwarder {
+    signals: Vec<i32>,
+    seen: Vec<u64>,
+}
+
+impl SignalForwarder {
+    pub(crate) fn new(signals: Vec<i32>) -> Self {
+        let seen = vec![0; signals.len()];
+        Self { signals, seen }
+    }
+
+    // Replaces the handlers of the signals, which then no longer have their
+    // default effect on this process until the returned guard is dropped
+    pub(crate) fn install(&mut self) -> io::Result<InstalledHandlers> {
+        let mut installed = InstalledHandlers { previous: vec![] };
+        for (&signal, seen) in self.signals.iter().zip(&mut self.seen) {
+            let Some(count) = RECEIVED.get(signal as usize).filter(|_| signal > 0) else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("invalid signal {}", signal),
+                ));
+            };
+            unsafe {
+                let mut action: libc::sigaction = mem::zeroed();
+                action.sa_sigaction = record as extern "C" fn(libc::c_int) as libc::sighandler_t;
+                action.sa_flags = libc::SA_RESTART;
+                libc::sigemptyset(&mut action.sa_mask);
+                let mut previous: libc::sigaction = mem::zeroed();
+                if libc::sigaction(signal, &action, &mut previous) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                installed.previous.push((signal, previous));
+            }
+            *seen = count.load(Ordering::SeqCst);
+        }
+        Ok(installed)
+    }
+
+    // Whether any of the signals was received since the handlers were
+    // installed
+    pub(crate) fn any_received(&self) -> bool {
+        self.signals
+            .iter()
+            .zip(&self.seen)
+            .any(|(&signal, &seen)| RECEIVED[signal as usize].load(Ordering::SeqCst) > seen)
+    }
+
+    // The signals received since last asked, once per time received
+    pub(crate) fn received(&mut self) -> Vec<i32> {
+        let mut received = vec![];
+        for (&signal, seen) in self.signals.iter().zip(&mut self.seen) {
+            let count = RECEIVED[signal as usize].load(Ordering::SeqCst);
+            received.extend((*seen..count).map(|_| signal));
+            *seen = count;
+        }
+        received
+    }
+
+    // Relays the signals received since last asked with `send`. Returns
+    // whether one of them was terminating.
+    pub(crate) fn forward(&mut self, mut send: impl FnMut(i32)) -> bool {
+        let mut terminating = false;
+        for signal in self.received() {
+            send(signal);
+            terminating |= is_terminating(signal);
+        }
+        terminating
+    }
+}
+
+// Puts back the handlers the signals had before they were installed
+pub(crate) struct InstalledHandlers {
+    previous: Vec<(i32, libc::sigaction)>,
+}
+
+impl Drop for InstalledHandlers {
+    fn drop(&mut self) {
+        for (signal, previous) in self.previous.iter().rev() {
+            unsafe { libc::sigaction(*signal, previous, ptr::null_mut()) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_counts_signals_for_every_forwarder() {
+        let mut first = SignalForwarder::new(vec![libc::SIGUSR1]);
+        let mut second = first.clone();
+        let installed_first = first.install().unwrap();
+        let installed_second = second.install().unwrap();
+        assert!(first.received().is_empty());
+
+        unsafe {
+            libc::raise(libc::SIGUSR1);
+            libc::raise(libc::SIGUSR1);
+        }
+        assert_eq!(first.received(), vec![libc::SIGUSR1, libc::SIGUSR1]);
+        assert!(first.received().is_empty());
+        assert_eq!(second.received(), vec![libc::SIGUSR1, libc::SIGUSR1]);
+
+        let handler = || unsafe {
+            let mut action: libc::sigaction = mem::zeroed();
+            libc::sigaction(libc::SIGUSR1, ptr::null(), &mut action);
+            action.sa_sigaction
+        };
+        drop(installed_second);
+        assert_eq!(
+            handler(),
+            record as extern "C" fn(libc::c_int) as libc::sighandler_t
+        );
+        drop(installed_first);
+        assert_eq!(handler(), libc::SIG_DFL);
+
+        assert!(SignalForwarder::new(vec![0]).install().is_err());
+    }
+}
@@ -0,0 +1,227 @@
+use std::{
+    ffi::{OsStr, OsString},
+    fmt,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+#[cfg(feature = "status-server")]
+use std::net::SocketAddr;
+
+#[cfg(unix)]
+use crate::signals::SignalForwarder;
+#[cfg(all(feature = "cgroup", target_os = "linux"))]
+use crate::CgroupLimits;
+use crate::{
+    hook::ScriptHooks, secrets::Secrets, BackoffPolicy, CircuitBreaker, FlapDetector, JobPolicy,
+    MaintenanceWindow, StdinSource, SupervisedProcess, TestAggregation, Threading,
+};
+#[cfg(target_os = "linux")]
+use crate::{IoPriorityClass, NamespaceFlags};
+
+macro_rules! spec {
+    ($($(#[$attr:meta])* $field:ident: $type:ty,)*) => {
+        // The settings of a supervisor, without its tests, handlers, backend,
+        // clock or state. Clone it to stamp out many similar supervisors.
+        #[derive(Clone)]
+        pub struct SupervisorSpec {
+            $($(#[$attr])* $field: $type,)*
+        }
+
+        impl<'a, S: Threading> SupervisedProcess<'a, S> {
+            pub fn spec(&self) -> SupervisorSpec {
+                SupervisorSpec {
+                    $($(#[$attr])* $field: self.$field.clone(),)*
+                }
+            }
+        }
+
+        // With the secrets masked, so it can be dumped for debugging
+        impl fmt::Debug for SupervisorSpec {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                struct Fields<'s>(&'s SupervisorSpec);
+
+                impl fmt::Debug for Fields<'_> {
+                    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                        let mut debug = f.debug_struct("SupervisorSpec");
+                        $($(#[$attr])* debug.field(stringify!($field), &self.0.$field);)*
+                        debug.finish()
+                    }
+                }
+
+                let fields = match f.alternate() {
+                    true => format!("{:#?}", Fields(self)),
+                    false => format!("{:?}", Fields(self)),
+                };
+                f.write_str(&self.secrets.redact(&fields))
+            }
+        }
+
+        impl SupervisorSpec {
+            // A supervisor with these settings, to add tests and handlers to
+            pub fn build<'a, S: Threading>(self) -> SupervisedProcess<'a, S> {
+                SupervisedProcess {
+                    $($(#[$attr])* $field: self.$field,)*
+                    ..SupervisedProcess::default()
+                }
+            }
+        }
+    };
+}
+
+spec! {
+    name: Option<String>,
+    process: OsString,
+    args: Vec<OsString>,
+    working_dir: Option<PathBuf>,
+    env: Vec<(OsString, OsString)>,
+    env_files: Vec<PathBuf>,
+    secrets: Secrets,
+    template_vars: Vec<(String, String)>,
+    scratch_dir: Option<String>,
+    reset_dirs: Vec<(PathBuf, PathBuf)>,
+    pid_file: Option<PathBuf>,
+    dynamic_port: Option<OsString>,
+    stdin: Option<StdinSource>,
+    restart_times: Option<u64>,
+    restart_limit: Option<u64>,
+    backoff_reset_after: Option<Duration>,
+    min_uptime: Option<Duration>,
+    circuit_breaker: Option<CircuitBreaker>,
+    flap_detector: Option<FlapDetector>,
+    fallback_command: Option<(Vec<OsString>, u32)>,
+    check_interval: Duration,
+    startup_checks: Option<(Duration, Duration)>,
+    initial_delay: Option<Duration>,
+    backoff_time: Duration,
+    backoff_policy: BackoffPolicy,
+    oom_backoff_time: Option<Duration>,
+    maintenance_windows: Vec<MaintenanceWindow>,
+    max_uptime: Option<Duration>,
+    total_timeout: Option<Duration>,
+    job_policy: JobPolicy,
+    fail_fast_on_spawn: bool,
+    spawn_grace_period: Duration,
+    post_restart_grace: Option<Duration>,
+    aligned_checks: bool,
+    history_capacity: usize,
+    history_file: Option<PathBuf>,
+    kill_timeout: Duration,
+    test_deadline: Option<Duration>,
+    test_aggregation: TestAggregation,
+    dependencies: Vec<String>,
+    stdio_watchdog: Option<Duration>,
+    output_tail: Option<usize>,
+    #[cfg(feature = "tracing")]
+    output_tracing: bool,
+    #[cfg(windows)]
+    job_object: bool,
+    #[cfg(target_os = "linux")]
+    reap_orphans: bool,
+    #[cfg(target_os = "linux")]
+    chroot: Option<PathBuf>,
+    #[cfg(target_os = "linux")]
+    namespaces: NamespaceFlags,
+    #[cfg(all(feature = "cgroup", target_os = "linux"))]
+    cgroup: Option<(PathBuf, CgroupLimits)>,
+    #[cfg(unix)]
+    core_dumps: Option<PathBuf>,
+    #[cfg(unix)]
+    nice: Option<i8>,
+    #[cfg(unix)]
+    umask: Option<u32>,
+    #[cfg(target_os = "linux")]
+    cpu_affinity: Vec<usize>,
+    #[cfg(target_os = "linux")]
+    io_priority: Option<(IoPriorityClass, u8)>,
+    state_file: Option<PathBuf>,
+    #[cfg(unix)]
+    readopt_on_resume: bool,
+    #[cfg(unix)]
+    signal_forwarder: Option<SignalForwarder>,
+    #[cfg(unix)]
+    ctrlc_shutdown: Option<SignalForwarder>,
+    #[cfg(all(feature = "control", unix))]
+    control_socket: Option<PathBuf>,
+    #[cfg(feature = "status-server")]
+    status_server: Option<SocketAddr>,
+    #[cfg(feature = "watch")]
+    watch_paths: Vec<PathBuf>,
+    script_hooks: ScriptHooks,
+}
+
+// What usually differs between supervisors stamped out of the same spec
+impl SupervisorSpec {
+    pub fn with_name(self, name: &str) -> Self {
+        Self {
+            name: Some(name.into()),
+            ..self
+        }
+    }
+
+    pub fn with_args(self, args: impl IntoIterator<Item = impl AsRef<OsStr>>) -> Self {
+        let args = args
+            .into_iter()
+            .map(|a| a.as_ref().to_os_string())
+            .collect();
+        Self { args, ..self }
+    }
+
+    pub fn with_working_dir(self, working_dir: impl AsRef<Path>) -> Self {
+        Self {
+            working_dir: Some(working_dir.as_ref().to_path_buf()),
+            ..self
+        }
+    }
+
+    // Set after the environment of the spec, so it overrides it
+    pub fn with_env(self, key: impl Into<OsString>, value: impl Into<OsString>) -> Self {
+        let mut env = self.env;
+        env.push((key.into(), value.into()));
+        Self { env, ..self }
+    }
+
+    pub fn with_template_var(self, key: &str, value: impl ToString) -> Self {
+        let mut template_vars = self.template_vars;
+        template_vars.push((key.into(), value.to_string()));
+        Self {
+            template_vars,
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ChildInfo, Local, MockBackend, MockClock};
+
+    use super::*;
+
+    #[test]
+    fn supervisors_are_stamped_out_of_a_spec() {
+        let spec = SupervisedProcess::new("worker")
+            .with_args(["--port", "{port}"])
+            .with_check_interval(Duration::from_secs(5))
+            .with_restart_times(0)
+            .add_test("always false", |_: &mut ChildInfo| false)
+            .spec();
+
+        let backend = MockBackend::new();
+        for port in [8001, 8002] {
+            let mut process: SupervisedProcess<Local> = spec
+                .clone()
+                .with_name(&format!("worker-{}", port))
+                .with_template_var("port", port)
+                .build()
+                .with_backend(backend.clone())
+                .with_clock(MockClock::new())
+                .add_test("always false", |_: &mut ChildInfo| false);
+            assert_eq!(process.name(), Some(format!("worker-{}", port).as_str()));
+            assert!(process.run().is_ok());
+        }
+        assert_eq!(
+            backend.command(2),
+            Some(vec!["worker".into(), "--port".into(), "8002".into()])
+        );
+    }
+}
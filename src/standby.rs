@@ -0,0 +1,192 @@
+use std::{
+    panic,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    RestartContext, RunOutcome, SupervisedProcess, SupervisorError, SupervisorHandle, Threaded,
+};
+
+type Member = SupervisedProcess<'static, Threaded>;
+type Handler = Box<dyn FnMut() + Send>;
+
+struct Failover {
+    standby: SupervisorHandle,
+    promote_after: u32,
+    failures: u32,
+    promoted: bool,
+    on_promote: Option<Handler>,
+    on_demote: Option<Handler>,
+}
+
+impl Failover {
+    fn failed(&mut self, context: &RestartContext) {
+        if context.failed_test.is_none() {
+            return;
+        }
+        self.failures += 1;
+        if self.failures >= self.promote_after {
+            self.promote();
+        }
+    }
+
+    fn promote(&mut self) {
+        if !std::mem::replace(&mut self.promoted, true) {
+            self.standby.resume();
+            if let Some(on_promote) = &mut self.on_promote {
+                on_promote();
+            }
+        }
+    }
+
+    fn healthy(&mut self) {
+        self.failures = 0;
+        if std::mem::replace(&mut self.promoted, false) {
+            self.standby.pause();
+            if let Some(on_demote) = &mut self.on_demote {
+                on_demote();
+            }
+        }
+    }
+}
+
+// An HA pair on a single host. The standby is kept paused while the primary
+// is healthy, and runs once the primary failed `promote_after` times in a
+// row. The primary keeps being restarted meanwhile, and the standby is paused
+// again as soon as the primary passes its tests. The restart and tests
+// passing handlers of the primary are taken over to follow its health.
+pub struct StandbyPair {
+    primary: Member,
+    standby: Member,
+    promote_after: u32,
+    on_promote: Option<Handler>,
+    on_demote: Option<Handler>,
+}
+
+impl StandbyPair {
+    pub fn new(primary: Member, standby: Member, promote_after: u32) -> Self {
+        Self {
+            primary,
+            standby,
+            promote_after,
+            on_promote: None,
+            on_demote: None,
+        }
+    }
+
+    pub fn on_promote(self, on_promote: impl FnMut() + Send + 'static) -> Self {
+        Self {
+            on_promote: Some(Box::new(on_promote)),
+            ..self
+        }
+    }
+
+    pub fn on_demote(self, on_demote: impl FnMut() + Send + 'static) -> Self {
+        Self {
+            on_demote: Some(Box::new(on_demote)),
+            ..self
+        }
+    }
+
+    pub fn primary_handle(&self) -> SupervisorHandle {
+        self.primary.handle()
+    }
+
+    pub fn standby_handle(&self) -> SupervisorHandle {
+        self.standby.handle()
+    }
+
+    // Runs the primary on this thread and the standby on another one. Once
+    // the primary is stopped the standby is too, unless it was promoted
+    // because the primary gave up, in which case it runs until stopped.
+    // Returns the outcome of the primary.
+    pub fn run(self) -> Result<RunOutcome, SupervisorError> {
+        let standby = self.standby.handle();
+        standby.pause();
+        let failover = Arc::new(Mutex::new(Failover {
+            standby: standby.clone(),
+            promote_after: self.promote_after,
+            failures: 0,
+            promoted: false,
+            on_promote: self.on_promote,
+            on_demote: self.on_demote,
+        }));
+
+        let standby_thread = self.standby.start().map_err(SupervisorError::Start)?;
+
+        let (restarted, gave_up, passing) = (failover.clone(), failover.clone(), failover.clone());
+        let mut primary = self
+            .primary
+            .on_restart(move |context: &RestartContext| restarted.lock().unwrap().failed(context))
+            .on_no_restart(move |context: &RestartContext| {
+                let mut failover = gave_up.lock().unwrap();
+                if context.failed_test.is_some() {
+                    failover.promote();
+                }
+            })
+            .on_tests_passing(move || passing.lock().unwrap().healthy());
+        let result = primary.run();
+
+        if !failover.lock().unwrap().promoted {
+            standby.stop();
+        }
+        let standby_result = standby_thread
+            .join()
+            .unwrap_or_else(|panic| panic::resume_unwind(panic));
+        let outcome = result?;
+        standby_result.map(|_| outcome)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicBool, Ordering},
+        time::Duration,
+    };
+
+    use crate::{ChildInfo, MockBackend};
+
+    use super::*;
+
+    fn member(name: &str, backend: &MockBackend) -> Member {
+        SupervisedProcess::new_send(name)
+            .with_backend(backend.clone())
+            .with_check_interval(Duration::from_millis(10))
+            .with_backoff_time(Duration::from_millis(10))
+    }
+
+    #[test]
+    fn the_standby_covers_for_a_failing_primary() {
+        let (primary_backend, standby_backend) = (MockBackend::new(), MockBackend::new());
+        let primary_healthy = Arc::new(AtomicBool::new(false));
+        let events = Arc::new(Mutex::new(vec![]));
+
+        let healthy = primary_healthy.clone();
+        let primary = member("primary", &primary_backend)
+            .add_test("healthy", move |_: &mut ChildInfo| {
+                healthy.load(Ordering::SeqCst)
+            });
+        // The primary only recovers once the standby took over
+        let recovered = primary_healthy.clone();
+        let standby = member("standby", &standby_backend)
+            .on_spawn(move |_| recovered.store(true, Ordering::SeqCst));
+
+        let pair = StandbyPair::new(primary, standby, 2);
+        let primary_handle = pair.primary_handle();
+        let (promoted, demoted) = (events.clone(), events.clone());
+        let pair = pair
+            .on_promote(move || promoted.lock().unwrap().push("promote"))
+            .on_demote(move || {
+                demoted.lock().unwrap().push("demote");
+                primary_handle.stop();
+            });
+        assert!(pair.run().is_ok());
+
+        assert_eq!(*events.lock().unwrap(), vec!["promote", "demote"]);
+        assert!(primary_backend.pids().len() >= 3);
+        let standby_pids = standby_backend.pids();
+        assert_eq!(standby_pids.len(), 1);
+        assert!(!standby_backend.is_running(standby_pids[0]));
+    }
+}
@@ -0,0 +1,98 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+};
+
+// What survives a crash of the supervising program, stored as `key=value`
+// lines so it can be inspected by hand
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct State {
+    pub(crate) pid: Option<u32>,
+    // Of the process with `pid`, so that another one given the same PID
+    // isn't taken for it
+    pub(crate) start_time: Option<String>,
+    pub(crate) restarts: u64,
+    pub(crate) last_failure: Option<String>,
+}
+
+impl State {
+    pub(crate) fn load(path: &Path) -> io::Result<Option<Self>> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        let invalid = |line: &str| {
+            io::Error::new(
+                ErrorKind::InvalidData,
+                format!("invalid state line `{}`", line),
+            )
+        };
+        let mut state = Self::default();
+        for line in contents.lines().filter(|line| !line.is_empty()) {
+            let (key, value) = line.split_once('=').ok_or_else(|| invalid(line))?;
+            match key {
+                "pid" => state.pid = Some(value.parse().map_err(|_| invalid(line))?),
+                "start_time" => state.start_time = Some(value.to_string()),
+                "restarts" => state.restarts = value.parse().map_err(|_| invalid(line))?,
+                "last_failure" => state.last_failure = Some(value.to_string()),
+                _ => return Err(invalid(line)),
+            }
+        }
+        Ok(Some(state))
+    }
+
+    // Writes to a temporary file first, so a crash never leaves a truncated
+    // state behind
+    pub(crate) fn save(&self, path: &Path) -> io::Result<()> {
+        let mut contents = format!("restarts={}\n", self.restarts);
+        if let Some(pid) = self.pid {
+            contents.push_str(&format!("pid={}\n", pid));
+        }
+        if let Some(start_time) = &self.start_time {
+            contents.push_str(&format!("start_time={}\n", start_time));
+        }
+        if let Some(last_failure) = &self.last_failure {
+            contents.push_str(&format!(
+                "last_failure={}\n",
+                last_failure.replace(['\r', '\n'], " ")
+            ));
+        }
+
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+        fs::write(&temporary, contents)?;
+        fs::rename(&temporary, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_round_trips_the_state() {
+        let path = std::env::temp_dir().join(format!("state-round-trip-{}", std::process::id()));
+        let state = State {
+            pid: Some(42),
+            start_time: Some("Mon Oct 12 09:30:00 2026".to_string()),
+            restarts: 3,
+            last_failure: Some("http = ok".to_string()),
+        };
+        state.save(&path).unwrap();
+        assert_eq!(State::load(&path).unwrap(), Some(state));
+        fs::remove_file(&path).unwrap();
+        assert_eq!(State::load(&path).unwrap(), None);
+    }
+
+    #[test]
+    fn it_rejects_invalid_state() {
+        let path = std::env::temp_dir().join(format!("state-invalid-{}", std::process::id()));
+        fs::write(&path, "restarts=many\n").unwrap();
+        let error = State::load(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+}
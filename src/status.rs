@@ -0,0 +1,140 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use crate::{SupervisorError, SupervisorHandle};
+
+// A tiny HTTP server for orchestrators to probe the supervisor itself:
+// `/status` answers with a JSON snapshot of it, `/healthz` with 200 while the
+// child is healthy and 503 otherwise
+pub(crate) struct StatusServer {
+    addr: SocketAddr,
+    closed: Arc<AtomicBool>,
+}
+
+impl StatusServer {
+    pub(crate) fn bind(
+        addr: SocketAddr,
+        handle: SupervisorHandle,
+    ) -> Result<Self, SupervisorError> {
+        let listener = TcpListener::bind(addr).map_err(SupervisorError::StatusServer)?;
+        let addr = listener
+            .local_addr()
+            .map_err(SupervisorError::StatusServer)?;
+        let closed = Arc::new(AtomicBool::new(false));
+        let serving = closed.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if serving.load(Ordering::SeqCst) {
+                    break;
+                }
+                if let Ok(stream) = stream {
+                    let handle = handle.clone();
+                    thread::spawn(move || serve(stream, &handle));
+                }
+            }
+        });
+
+        Ok(Self { addr, closed })
+    }
+}
+
+impl Drop for StatusServer {
+    fn drop(&mut self) {
+        // Wakes the listener up so it notices it was closed
+        self.closed.store(true, Ordering::SeqCst);
+        let _ = TcpStream::connect(self.addr);
+    }
+}
+
+fn serve(stream: TcpStream, handle: &SupervisorHandle) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // The headers don't matter, but are read so that closing the connection
+    // doesn't reset it before the client got the response
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let mut parts = request.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/status")) => ("200 OK", handle.status().to_string()),
+        (Some("GET"), Some("/healthz")) if handle.is_healthy() => ("200 OK", "ok".into()),
+        (Some("GET"), Some("/healthz")) => ("503 Service Unavailable", "unhealthy".into()),
+        (Some("GET"), _) => ("404 Not Found", "not found".into()),
+        _ => ("405 Method Not Allowed", "method not allowed".into()),
+    };
+    let content_type = match body.starts_with('{') {
+        true => "application/json",
+        false => "text/plain",
+    };
+    write!(
+        writer,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+    use crate::{handle::Control, TestOutcome, TestStats};
+
+    fn get(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn it_serves_the_status_of_the_supervisor() {
+        let control = Arc::new(Control::default());
+        let handle = SupervisorHandle::new(control.clone());
+        let server = StatusServer::bind("127.0.0.1:0".parse().unwrap(), handle).unwrap();
+
+        assert!(get(server.addr, "/healthz").starts_with("HTTP/1.1 503 "));
+        control.update_stats(|stats| {
+            stats.pid = Some(42);
+            stats.tests.push(TestStats {
+                name: "http".into(),
+                last_outcome: TestOutcome::Healthy,
+                consecutive_failures: 0,
+                latency: None,
+            });
+        });
+        assert!(get(server.addr, "/healthz").starts_with("HTTP/1.1 200 OK\r\n"));
+
+        let status = get(server.addr, "/status");
+        assert!(status.contains("Content-Type: application/json\r\n"));
+        let body = status.split("\r\n\r\n").nth(1).unwrap();
+        let body: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(body["state"], "running");
+        assert_eq!(body["pid"], 42);
+        assert_eq!(body["tests"][0]["name"], "http");
+
+        assert!(get(server.addr, "/nope").starts_with("HTTP/1.1 404 "));
+
+        control.update_stats(|stats| stats.finished = true);
+        assert!(get(server.addr, "/healthz").starts_with("HTTP/1.1 503 "));
+        let status = get(server.addr, "/status");
+        let body = status.split("\r\n\r\n").nth(1).unwrap();
+        let body: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(body["state"], "finished");
+    }
+}
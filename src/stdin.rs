@@ -0,0 +1,38 @@
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::PathBuf,
+    process::{ChildStdin, Stdio},
+    thread,
+};
+
+// What every child reads from its stdin, given again on each restart
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum StdinSource {
+    Null,
+    #[default]
+    Inherit,
+    // Written to the child once it started, then stdin is closed
+    Bytes(Vec<u8>),
+    File(PathBuf),
+}
+
+impl StdinSource {
+    pub(crate) fn stdio(&self) -> io::Result<Stdio> {
+        Ok(match self {
+            Self::Null => Stdio::null(),
+            Self::Inherit => Stdio::inherit(),
+            Self::Bytes(_) => Stdio::piped(),
+            Self::File(path) => File::open(path)?.into(),
+        })
+    }
+
+    // Feeds the bytes in the background, so a child that doesn't read its
+    // stdin can't block the supervisor
+    pub(crate) fn feed(&self, stdin: Option<ChildStdin>) {
+        if let (Self::Bytes(bytes), Some(mut stdin)) = (self, stdin) {
+            let bytes = bytes.clone();
+            thread::spawn(move || stdin.write_all(&bytes));
+        }
+    }
+}
@@ -0,0 +1,431 @@
+use std::{
+    fmt::Display,
+    io,
+    panic::{self, AssertUnwindSafe},
+    process::{Child, Command},
+    sync::Arc,
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    check,
+    handle::{Control, Request},
+    BuilderError, CircuitBreaker, RestartContext, RestartReason, RunOutcome, SupervisorError,
+    SupervisorHandle, TestOutcome,
+};
+
+// Something that can be started again and again, like a process or a worker
+// thread
+pub trait Supervisable {
+    type Instance: Instance;
+
+    fn start(&mut self) -> io::Result<Self::Instance>;
+}
+
+// A started `Supervisable`
+pub trait Instance {
+    // None while still running, otherwise whether it finished successfully
+    fn try_wait(&mut self) -> Option<Result<(), String>>;
+    fn stop(&mut self);
+}
+
+impl Supervisable for Command {
+    type Instance = Child;
+
+    fn start(&mut self) -> io::Result<Child> {
+        self.spawn()
+    }
+}
+
+impl Instance for Child {
+    fn try_wait(&mut self) -> Option<Result<(), String>> {
+        let status = Child::try_wait(self).ok().flatten()?;
+        Some(match status.success() {
+            true => Ok(()),
+            false => Err(status.to_string()),
+        })
+    }
+
+    fn stop(&mut self) {
+        let _ = self.kill();
+        let _ = self.wait();
+    }
+}
+
+// A worker started by a function spawning its thread
+pub struct ThreadWorker<F> {
+    spawn: F,
+}
+
+impl<F, E> ThreadWorker<F>
+where
+    F: FnMut() -> JoinHandle<Result<(), E>>,
+{
+    pub fn new(spawn: F) -> Self {
+        Self { spawn }
+    }
+}
+
+// Threads can't be killed, so a stopped worker is left to finish on its own
+// while the supervisor goes on without it
+pub struct WorkerThread<E> {
+    handle: Option<JoinHandle<Result<(), E>>>,
+}
+
+impl<F, E: Display> Supervisable for ThreadWorker<F>
+where
+    F: FnMut() -> JoinHandle<Result<(), E>>,
+{
+    type Instance = WorkerThread<E>;
+
+    fn start(&mut self) -> io::Result<WorkerThread<E>> {
+        Ok(WorkerThread {
+            handle: Some((self.spawn)()),
+        })
+    }
+}
+
+impl<E: Display> Instance for WorkerThread<E> {
+    fn try_wait(&mut self) -> Option<Result<(), String>> {
+        if !self.handle.as_ref()?.is_finished() {
+            return None;
+        }
+        Some(match self.handle.take()?.join() {
+            Ok(result) => result.map_err(|e| e.to_string()),
+            Err(_) => Err("worker panicked".to_string()),
+        })
+    }
+
+    fn stop(&mut self) {
+        self.handle = None;
+    }
+}
+
+// A worker running `work` on a new thread every time it is started
+pub struct FnWorker<F> {
+    work: F,
+}
+
+impl<F, E> FnWorker<F>
+where
+    F: Fn() -> Result<(), E> + Clone + Send + 'static,
+    E: Display + Send + 'static,
+{
+    pub fn new(work: F) -> Self {
+        Self { work }
+    }
+}
+
+impl<F, E> Supervisable for FnWorker<F>
+where
+    F: Fn() -> Result<(), E> + Clone + Send + 'static,
+    E: Display + Send + 'static,
+{
+    type Instance = WorkerThread<E>;
+
+    fn start(&mut self) -> io::Result<WorkerThread<E>> {
+        let work = self.work.clone();
+        Ok(WorkerThread {
+            handle: Some(thread::Builder::new().spawn(work)?),
+        })
+    }
+}
+
+type Test<I> = Box<dyn FnMut(&mut I) -> TestOutcome>;
+type RestartHandler<'a> = Box<dyn FnMut(&RestartContext) + 'a>;
+
+// The restart and backoff machinery of `SupervisedProcess` for anything
+// `Supervisable`. Finishing successfully ends the supervision, failing or
+// failing a test restarts. Contexts given to the handlers have a pid of 0.
+pub struct Supervisor<'a, T: Supervisable> {
+    target: T,
+    name: Option<String>,
+    check_interval: Duration,
+    backoff_time: Duration,
+    restart_times: Option<u64>,
+    circuit_breaker: Option<CircuitBreaker>,
+    tests: Vec<(String, Test<T::Instance>)>,
+    restarts: u64,
+    control: Arc<Control>,
+    on_restart: Option<RestartHandler<'a>>,
+    on_no_restart: Option<RestartHandler<'a>>,
+}
+
+enum Ended {
+    Finished,
+    Stopped,
+    Restarted,
+    // Finished unsuccessfully, with the error
+    Exited(String),
+    Failed(String),
+    // The name of the test and what it panicked with
+    TestPanicked(String, String),
+}
+
+impl<'a, T: Supervisable> Supervisor<'a, T> {
+    pub fn new(target: T) -> Self {
+        Self {
+            target,
+            name: None,
+            check_interval: Duration::from_secs(30),
+            backoff_time: Duration::from_secs(30),
+            restart_times: None,
+            circuit_breaker: None,
+            tests: vec![],
+            restarts: 0,
+            control: Arc::default(),
+            on_restart: None,
+            on_no_restart: None,
+        }
+    }
+
+    pub fn with_name(self, name: &str) -> Self {
+        Self {
+            name: Some(name.into()),
+            ..self
+        }
+    }
+
+    pub fn with_check_interval(self, check_interval: Duration) -> Self {
+        Self {
+            check_interval,
+            ..self
+        }
+    }
+
+    pub fn with_backoff_time(self, backoff_time: Duration) -> Self {
+        Self {
+            backoff_time,
+            ..self
+        }
+    }
+
+    pub fn with_restart_times(self, restart_times: u64) -> Self {
+        Self {
+            restart_times: Some(restart_times),
+            ..self
+        }
+    }
+
+    pub fn with_circuit_breaker(self, circuit_breaker: CircuitBreaker) -> Self {
+        Self {
+            circuit_breaker: Some(circuit_breaker),
+            ..self
+        }
+    }
+
+    pub fn add_test<O: Into<TestOutcome>>(
+        self,
+        name: &str,
+        mut test: impl FnMut(&mut T::Instance) -> O + 'static,
+    ) -> Self {
+        let mut tests = self.tests;
+        tests.push((name.into(), Box::new(move |instance| test(instance).into())));
+        Self { tests, ..self }
+    }
+
+    pub fn on_restart(self, on_restart: impl FnMut(&RestartContext) + 'a) -> Self {
+        Self {
+            on_restart: Some(Box::new(on_restart)),
+            ..self
+        }
+    }
+
+    pub fn on_no_restart(self, on_no_restart: impl FnMut(&RestartContext) + 'a) -> Self {
+        Self {
+            on_no_restart: Some(Box::new(on_no_restart)),
+            ..self
+        }
+    }
+
+    pub fn handle(&self) -> SupervisorHandle {
+        SupervisorHandle::new(self.control.clone())
+    }
+
+    fn should_restart(&mut self) -> bool {
+        match self.restart_times {
+            None => true,
+            Some(0) => false,
+            Some(times) => {
+                self.restart_times = Some(times - 1);
+                true
+            }
+        }
+    }
+
+    // Waits for the instance to end, by itself, through the handle or by
+    // failing a test
+    fn supervise(&mut self, instance: &mut T::Instance) -> Ended {
+        loop {
+            match self.control.wait_timeout(self.check_interval) {
+                Some(Request::Stop) | Some(Request::Pause) => return Ended::Stopped,
+                Some(Request::Restart) => return Ended::Restarted,
+                // Nothing to reload outside of processes
+                Some(Request::Reload) => drop(self.control.take_reload()),
+                None => {}
+            }
+            match instance.try_wait() {
+                Some(Ok(())) => return Ended::Finished,
+                Some(Err(e)) => return Ended::Exited(e),
+                None => {}
+            }
+            for (name, test) in &mut self.tests {
+                match panic::catch_unwind(AssertUnwindSafe(|| test(instance))) {
+                    Ok(outcome) if outcome.is_failure() => return Ended::Failed(name.clone()),
+                    Ok(_) => {}
+                    Err(payload) => {
+                        return Ended::TestPanicked(name.clone(), check::panic_message(&*payload))
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn run(&mut self) -> Result<RunOutcome, SupervisorError> {
+        if self.check_interval.is_zero() {
+            return Err(BuilderError::ZeroCheckInterval.into());
+        }
+
+        loop {
+            if self.control.is_stopped() {
+                return Ok(RunOutcome::StoppedByHandle);
+            }
+            let mut instance = self.target.start().map_err(SupervisorError::Start)?;
+            let started = Instant::now();
+            let ended = self.supervise(&mut instance);
+            instance.stop();
+
+            let (reason, failure, test_panic) = match ended {
+                Ended::Finished => return Ok(RunOutcome::JobSucceeded),
+                Ended::Stopped => return Ok(RunOutcome::StoppedByHandle),
+                Ended::Restarted => (RestartReason::Requested, None, None),
+                Ended::Exited(error) => (RestartReason::Exited, Some(error), None),
+                Ended::Failed(failure) => (RestartReason::FailedTest, Some(failure), None),
+                Ended::TestPanicked(name, message) => {
+                    (RestartReason::FailedTest, Some(name), Some(message))
+                }
+            };
+            let mut context = RestartContext {
+                reason,
+                failed_test: failure.clone(),
+                test_panic,
+                ..RestartContext::new(self.name.clone(), self.restarts + 1, started.elapsed(), 0)
+            };
+            if failure.is_some() {
+                if !self.should_restart() {
+                    if let Some(on_no_restart) = &mut self.on_no_restart {
+                        on_no_restart(&context.emitted());
+                    }
+                    return Ok(RunOutcome::RestartsExhausted);
+                }
+                let delay = self
+                    .circuit_breaker
+                    .as_mut()
+                    .and_then(|breaker| breaker.record_failure(Instant::now()))
+                    .unwrap_or(self.backoff_time);
+                if self.control.wait_timeout(delay) == Some(Request::Stop) {
+                    return Ok(RunOutcome::StoppedByHandle);
+                }
+                context.backoff = delay;
+            }
+            self.restarts += 1;
+            if let Some(on_restart) = &mut self.on_restart {
+                on_restart(&context.emitted());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex,
+    };
+
+    use super::*;
+
+    #[test]
+    fn it_restarts_failing_workers_until_they_succeed() {
+        let runs = Arc::new(AtomicU32::new(0));
+        let counter = runs.clone();
+        let mut failures = vec![];
+
+        let mut supervisor = Supervisor::new(FnWorker::new(move || {
+            match counter.fetch_add(1, Ordering::SeqCst) {
+                0 => Err("first run fails"),
+                1 => panic!("second run panics"),
+                _ => Ok(()),
+            }
+        }))
+        .with_check_interval(Duration::from_millis(10))
+        .with_backoff_time(Duration::from_millis(10))
+        .on_restart(|context: &RestartContext| failures.push(context.failed_test.clone()));
+        assert!(supervisor.run().is_ok());
+        drop(supervisor);
+
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+        assert_eq!(
+            failures,
+            vec![
+                Some("first run fails".to_string()),
+                Some("worker panicked".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn tests_run_against_the_instance() {
+        let pids = Arc::new(Mutex::new(vec![]));
+        let seen = pids.clone();
+        let mut gave_up = false;
+        let mut panics = vec![];
+        let mut command = Command::new("sleep");
+        command.arg("5");
+
+        let mut supervisor = Supervisor::new(command)
+            .add_test("always false", move |child: &mut Child| {
+                let mut pids = seen.lock().unwrap();
+                pids.push(child.id());
+                if pids.len() == 1 {
+                    drop(pids);
+                    panic!("no such endpoint");
+                }
+                false
+            })
+            .with_check_interval(Duration::from_millis(10))
+            .with_backoff_time(Duration::from_millis(10))
+            .with_restart_times(1)
+            .on_restart(|context: &RestartContext| panics.push(context.test_panic.clone()))
+            .on_no_restart(|_: &RestartContext| gave_up = true);
+        assert_eq!(supervisor.run().unwrap(), RunOutcome::RestartsExhausted);
+        drop(supervisor);
+
+        assert!(gave_up);
+        assert_eq!(panics, vec![Some("no such endpoint".to_string())]);
+        let pids = pids.lock().unwrap();
+        assert_eq!(pids.len(), 2);
+        assert_ne!(pids[0], pids[1]);
+    }
+
+    #[test]
+    fn it_stops_through_the_handle() {
+        let mut supervisor = Supervisor::new(ThreadWorker::new(|| {
+            thread::spawn(|| {
+                thread::sleep(Duration::from_secs(5));
+                Ok::<(), String>(())
+            })
+        }))
+        .with_check_interval(Duration::from_millis(10));
+        let handle = supervisor.handle();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            handle.stop();
+        });
+
+        let started = Instant::now();
+        assert_eq!(supervisor.run().unwrap(), RunOutcome::StoppedByHandle);
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,144 @@
+use std::{
+    io,
+    os::unix::net::UnixDatagram,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+// Where the local syslog daemon listens, journald included
+const SOCKET_PATHS: [&str; 3] = ["/dev/log", "/var/run/syslog", "/var/run/log"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SyslogFacility {
+    User,
+    #[default]
+    Daemon,
+    Local0,
+    Local1,
+    Local2,
+    Local3,
+    Local4,
+    Local5,
+    Local6,
+    Local7,
+}
+
+impl SyslogFacility {
+    fn code(self) -> u8 {
+        match self {
+            Self::User => 1,
+            Self::Daemon => 3,
+            Self::Local0 => 16,
+            Self::Local1 => 17,
+            Self::Local2 => 18,
+            Self::Local3 => 19,
+            Self::Local4 => 20,
+            Self::Local5 => 21,
+            Self::Local6 => 22,
+            Self::Local7 => 23,
+        }
+    }
+}
+
+// Sends the output of the child to syslog, stdout as info and stderr as
+// errors, once connected when the supervisor starts
+pub(crate) struct Syslog {
+    identifier: String,
+    facility: SyslogFacility,
+    path: Option<PathBuf>,
+    socket: Option<Arc<UnixDatagram>>,
+}
+
+impl Syslog {
+    pub(crate) fn new(identifier: &str, facility: SyslogFacility) -> Self {
+        Self {
+            identifier: identifier.into(),
+            facility,
+            path: None,
+            socket: None,
+        }
+    }
+
+    #[cfg(test)]
+    fn at(self, path: &Path) -> Self {
+        Self {
+            path: Some(path.to_path_buf()),
+            ..self
+        }
+    }
+
+    pub(crate) fn connect(&mut self) -> io::Result<()> {
+        if self.socket.is_some() {
+            return Ok(());
+        }
+        let paths: Vec<&Path> = match &self.path {
+            Some(path) => vec![path],
+            None => SOCKET_PATHS.iter().map(Path::new).collect(),
+        };
+        let socket = UnixDatagram::unbound()?;
+        let mut result = Err(io::ErrorKind::NotFound.into());
+        for path in paths {
+            result = socket.connect(path);
+            if result.is_ok() {
+                break;
+            }
+        }
+        result?;
+        self.socket = Some(Arc::new(socket));
+        Ok(())
+    }
+
+    // What the output of a child gets sent with
+    pub(crate) fn sender(&self, pid: u32) -> Option<SyslogSender> {
+        Some(SyslogSender {
+            socket: self.socket.clone()?,
+            prefix: format!("{}[{}]: ", self.identifier, pid),
+            facility: self.facility,
+        })
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct SyslogSender {
+    socket: Arc<UnixDatagram>,
+    prefix: String,
+    facility: SyslogFacility,
+}
+
+impl SyslogSender {
+    // Like the other logs, failing to send doesn't stop the supervision
+    pub(crate) fn send(&self, line: &str, stderr: bool) {
+        let severity = if stderr { 3 } else { 6 };
+        let priority = self.facility.code() * 8 + severity;
+        let message = format!("<{}>{}{}", priority, self.prefix, line);
+        let _ = self.socket.send(message.as_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::*;
+
+    #[test]
+    fn it_sends_lines_with_facility_and_identifier() {
+        let path = env::temp_dir().join(format!("syslog-{}.sock", process::id()));
+        let _ = fs::remove_file(&path);
+        let server = UnixDatagram::bind(&path).unwrap();
+
+        let mut syslog = Syslog::new("web", SyslogFacility::Local3).at(&path);
+        assert!(syslog.sender(42).is_none());
+        syslog.connect().unwrap();
+        let sender = syslog.sender(42).unwrap();
+        sender.send("listening", false);
+        sender.send("oops", true);
+
+        let mut buffer = [0; 128];
+        for expected in ["<158>web[42]: listening", "<155>web[42]: oops"] {
+            let len = server.recv(&mut buffer).unwrap();
+            assert_eq!(&buffer[..len], expected.as_bytes());
+        }
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,69 @@
+use std::{
+    io,
+    net::{TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+use crate::{ChildInfo, HealthCheck, TestOutcome};
+
+// Passes when a connection to `address` succeeds
+pub struct TcpCheck {
+    name: String,
+    address: String,
+    timeout: Duration,
+}
+
+impl TcpCheck {
+    pub fn new(address: &str) -> Self {
+        Self {
+            name: format!("tcp {}", address),
+            address: address.into(),
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    // The check of the port given by `with_dynamic_port`
+    pub(crate) fn local_port(port: u16) -> Self {
+        Self::new(&format!("127.0.0.1:{}", port))
+    }
+
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+
+    fn connect(&self) -> io::Result<TcpStream> {
+        let address = self
+            .address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no address"))?;
+        TcpStream::connect_timeout(&address, self.timeout)
+    }
+}
+
+impl HealthCheck for TcpCheck {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn check(&mut self, _: &mut ChildInfo) -> TestOutcome {
+        TestOutcome::from(self.connect().is_ok())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn it_connects() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        assert!(TcpCheck::new(&address).connect().is_ok());
+
+        drop(listener);
+        assert!(TcpCheck::new(&address).connect().is_err());
+    }
+}
@@ -0,0 +1,36 @@
+use std::ffi::{OsStr, OsString};
+
+// Replaces every {key} in `template` by its value. Unknown placeholders are
+// left as they are.
+pub(crate) fn expand(template: &str, vars: &[(&str, String)]) -> String {
+    vars.iter()
+        .fold(template.to_string(), |expanded, (key, value)| {
+            expanded.replace(&format!("{{{}}}", key), value)
+        })
+}
+
+// Values that aren't valid Unicode can't hold placeholders and are kept
+pub(crate) fn expand_os(template: &OsStr, vars: &[(&str, String)]) -> OsString {
+    match template.to_str() {
+        Some(template) if template.contains('{') => expand(template, vars).into(),
+        _ => template.to_os_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_replaces_known_placeholders() {
+        let vars = [
+            ("name", "web".to_string()),
+            ("restart_count", "3".to_string()),
+        ];
+        assert_eq!(
+            expand("--log=/var/log/{name}-{restart_count}.log {other}", &vars),
+            "--log=/var/log/web-3.log {other}"
+        );
+        assert_eq!(expand_os(OsStr::new("{name}"), &vars), "web");
+    }
+}
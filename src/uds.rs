@@ -0,0 +1,135 @@
+use std::{
+    io::{self, Read, Write},
+    os::unix::net::UnixStream,
+    path::PathBuf,
+    time::Duration,
+};
+
+use crate::{ChildInfo, HealthCheck, TestOutcome};
+
+// Connects to a Unix socket, for services that don't listen on TCP. Passes
+// once connected, or once the response contains the expected bytes.
+pub struct UdsCheck {
+    name: String,
+    path: PathBuf,
+    payload: Vec<u8>,
+    expected: Option<Vec<u8>>,
+    timeout: Duration,
+}
+
+impl UdsCheck {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        Self {
+            name: format!("socket {}", path.display()),
+            path,
+            payload: vec![],
+            expected: None,
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    pub fn with_payload(self, payload: impl Into<Vec<u8>>) -> Self {
+        Self {
+            payload: payload.into(),
+            ..self
+        }
+    }
+
+    pub fn with_expected_response(self, expected: impl Into<Vec<u8>>) -> Self {
+        Self {
+            expected: Some(expected.into()),
+            ..self
+        }
+    }
+
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+
+    fn probe(&self) -> io::Result<bool> {
+        let mut stream = UnixStream::connect(&self.path)?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+        stream.write_all(&self.payload)?;
+        let Some(expected) = &self.expected else {
+            return Ok(true);
+        };
+
+        // Reads until the expected bytes show up, the service closes the
+        // connection or the timeout elapses
+        let mut response = vec![];
+        let mut buffer = [0; 1024];
+        loop {
+            let read = stream.read(&mut buffer)?;
+            if read == 0 {
+                return Ok(false);
+            }
+            response.extend_from_slice(&buffer[..read]);
+            if response
+                .windows(expected.len().max(1))
+                .any(|window| window == expected.as_slice())
+            {
+                return Ok(true);
+            }
+        }
+    }
+}
+
+impl HealthCheck for UdsCheck {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn check(&mut self, _: &mut ChildInfo) -> TestOutcome {
+        TestOutcome::from(self.probe().unwrap_or(false))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env, fs,
+        os::unix::net::UnixListener,
+        process::{self, Command},
+        thread,
+        time::Instant,
+    };
+
+    use super::*;
+    use crate::output::OutputTail;
+
+    #[test]
+    fn it_matches_the_response() {
+        let path = env::temp_dir().join(format!("uds-check-{}.sock", process::id()));
+        let _ = fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut request = [0; 4];
+                stream.read_exact(&mut request).unwrap();
+                stream.write_all(b"+PONG\r\n").unwrap();
+            }
+        });
+
+        let mut child = Command::new("true").spawn().unwrap();
+        let output = OutputTail::default();
+        let mut info = ChildInfo::new(&mut child, Instant::now(), &output);
+        let mut check = |expected: &str| {
+            UdsCheck::new(&path)
+                .with_payload("PING")
+                .with_expected_response(expected)
+                .check(&mut info)
+        };
+        assert_eq!(check("PONG"), TestOutcome::Healthy);
+        assert_eq!(check("ERR"), TestOutcome::Unhealthy);
+        child.wait().unwrap();
+
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            UdsCheck::new(&path).check(&mut ChildInfo::new(&mut child, Instant::now(), &output)),
+            TestOutcome::Unhealthy
+        );
+    }
+}
@@ -0,0 +1,62 @@
+use std::{io, os::unix::process::CommandExt, process::Command};
+
+pub(crate) const ADOPTED_ALIVE_TEST: &str = "adopted process alive";
+
+pub(crate) fn is_alive(pid: u32) -> bool {
+    // Signal 0 only checks whether the process exists. EPERM means it exists
+    // but belongs to someone else.
+    let result = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    let exists = result == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM);
+    #[cfg(target_os = "linux")]
+    return exists && !crate::linux::is_zombie(pid);
+    #[cfg(not(target_os = "linux"))]
+    exists
+}
+
+// Tells apart the process from whatever later gets the same PID, e.g. after a
+// reboot
+pub(crate) fn start_time(pid: u32) -> Option<String> {
+    #[cfg(target_os = "linux")]
+    return crate::linux::start_time(pid);
+    #[cfg(not(target_os = "linux"))]
+    {
+        let output = Command::new("ps")
+            .args(["-o", "lstart=", "-p", &pid.to_string()])
+            .output()
+            .ok()?;
+        let start_time = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        Some(start_time).filter(|start_time| output.status.success() && !start_time.is_empty())
+    }
+}
+
+// Sets the nice level of the child before it runs the program. Only
+// privileged users can go below the current one.
+pub(crate) fn set_nice(command: &mut Command, nice: i8) {
+    let set_nice = move || {
+        if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, nice.into()) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    };
+    unsafe { command.pre_exec(set_nice) };
+}
+
+pub(crate) fn set_umask(command: &mut Command, umask: u32) {
+    let set_umask = move || {
+        unsafe { libc::umask(umask as libc::mode_t) };
+        Ok(())
+    };
+    unsafe { command.pre_exec(set_umask) };
+}
+
+pub(crate) fn signal(pid: u32, signal: i32) {
+    unsafe {
+        libc::kill(pid as libc::pid_t, signal);
+    }
+}
+
+pub(crate) fn kill(pid: u32) {
+    unsafe {
+        libc::kill(pid as libc::pid_t, libc::SIGKILL);
+    }
+}
@@ -0,0 +1,30 @@
+use std::path::PathBuf;
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::{SupervisorError, SupervisorHandle};
+
+pub(crate) fn watch(
+    paths: &[PathBuf],
+    handle: SupervisorHandle,
+) -> Result<RecommendedWatcher, SupervisorError> {
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+        if let Ok(event) = event {
+            if matches!(
+                event.kind,
+                EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+            ) {
+                handle.restart();
+            }
+        }
+    })
+    .map_err(|e| SupervisorError::Watch(e.to_string()))?;
+
+    for path in paths {
+        watcher
+            .watch(path, RecursiveMode::Recursive)
+            .map_err(|e| SupervisorError::Watch(format!("{}: {}", path.display(), e)))?;
+    }
+
+    Ok(watcher)
+}
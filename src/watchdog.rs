@@ -0,0 +1,72 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    process::Child,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::output::OutputTail;
+
+pub(crate) const STDIO_WATCHDOG_TEST: &str = "stdio watchdog";
+
+const PING: &[u8] = b"ping\n";
+const PONG: &str = "pong";
+
+// Pings the child over stdin every `interval` and expects a pong line back on
+// stdout before the next ping. Any other output is forwarded to our stdout.
+pub(crate) struct StdioWatchdog {
+    failing: Arc<AtomicBool>,
+}
+
+impl StdioWatchdog {
+    pub(crate) fn start(child: &mut Child, interval: Duration, output: OutputTail) -> Self {
+        let failing = Arc::new(AtomicBool::new(false));
+        let (Some(mut stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            failing.store(true, Ordering::SeqCst);
+            return Self { failing };
+        };
+
+        let (pongs, received) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if line.trim() == PONG {
+                    if pongs.send(()).is_err() {
+                        return;
+                    }
+                } else {
+                    output.forward(line, false);
+                }
+            }
+        });
+
+        let pinger_failing = failing.clone();
+        thread::spawn(move || loop {
+            while received.try_recv().is_ok() {}
+            let pinged_at = Instant::now();
+            if stdin.write_all(PING).and_then(|_| stdin.flush()).is_err() {
+                pinger_failing.store(true, Ordering::SeqCst);
+                return;
+            }
+
+            match received.recv_timeout(interval) {
+                Ok(()) => thread::sleep(interval.saturating_sub(pinged_at.elapsed())),
+                Err(RecvTimeoutError::Timeout) => pinger_failing.store(true, Ordering::SeqCst),
+                Err(RecvTimeoutError::Disconnected) => {
+                    pinger_failing.store(true, Ordering::SeqCst);
+                    return;
+                }
+            }
+        });
+
+        Self { failing }
+    }
+
+    pub(crate) fn is_failing(&self) -> bool {
+        self.failing.load(Ordering::SeqCst)
+    }
+}
@@ -0,0 +1,127 @@
+use std::{
+    thread,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use serde_json::{json, Value};
+use ureq::Agent;
+
+use crate::{notify::NotifyEvent, Notifier, RestartContext};
+
+// POSTs a JSON payload describing each event to `url`, for wiring up Slack,
+// PagerDuty and the like. Requests are sent in the background and failures
+// are ignored, so a down receiver never holds up the supervisor.
+pub struct WebhookNotifier {
+    url: String,
+    agent: Agent,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.into(),
+            agent: agent(Duration::from_secs(10)),
+        }
+    }
+
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Self {
+            agent: agent(timeout),
+            ..self
+        }
+    }
+}
+
+fn agent(timeout: Duration) -> Agent {
+    Agent::config_builder()
+        .timeout_global(Some(timeout))
+        .build()
+        .new_agent()
+}
+
+fn payload(event: NotifyEvent, context: &RestartContext) -> Value {
+    json!({
+        "service": context.name,
+        "event": event.as_str(),
+        "timestamp": context
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+        "sequence": context.sequence,
+        "attempt": context.attempt,
+        "failed_test": context.failed_test,
+        "output_tail": context.output_tail,
+        "core_dump": context.core_dump,
+    })
+}
+
+impl Notifier for WebhookNotifier {
+    fn notify(&mut self, event: NotifyEvent, context: &RestartContext) {
+        let body = payload(event, context).to_string();
+        let request = self
+            .agent
+            .post(&self.url)
+            .header("Content-Type", "application/json");
+        thread::spawn(move || request.send(body));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+    };
+
+    use super::*;
+
+    #[test]
+    fn it_posts_the_event() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut length = 0;
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                if header == "\r\n" {
+                    break;
+                }
+                if let Some(value) = header.to_lowercase().strip_prefix("content-length:") {
+                    length = value.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            reader
+                .get_mut()
+                .write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+                .unwrap();
+            (
+                request_line,
+                serde_json::from_slice::<Value>(&body).unwrap(),
+            )
+        });
+
+        let context = RestartContext {
+            failed_test: Some("http".into()),
+            output_tail: vec!["FATAL: oops".into()],
+            ..RestartContext::new(Some("web".into()), 3, Duration::from_secs(1), 42)
+        };
+        WebhookNotifier::new(&url).notify(NotifyEvent::Restart, &context.emitted());
+
+        let (request_line, body) = server.join().unwrap();
+        assert_eq!(request_line, "POST /hook HTTP/1.1\r\n");
+        assert_eq!(body["service"], "web");
+        assert_eq!(body["event"], "restart");
+        assert_eq!(body["attempt"], 3);
+        assert_eq!(body["output_tail"], json!(["FATAL: oops"]));
+        assert!(body["timestamp"].as_u64().unwrap() > 0);
+        assert!(body["sequence"].as_u64().unwrap() > 0);
+    }
+}
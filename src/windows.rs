@@ -0,0 +1,84 @@
+use std::{
+    ffi::c_void,
+    io, mem,
+    os::windows::{io::AsRawHandle, process::CommandExt},
+    process::{Child, Command},
+    ptr,
+};
+
+use windows_sys::Win32::{
+    Foundation::{CloseHandle, HANDLE},
+    System::{
+        Console::{GenerateConsoleCtrlEvent, CTRL_BREAK_EVENT},
+        JobObjects::{
+            AssignProcessToJobObject, CreateJobObjectW, JobObjectExtendedLimitInformation,
+            SetInformationJobObject, TerminateJobObject, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+            JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+        },
+        Threading::CREATE_NEW_PROCESS_GROUP,
+    },
+};
+
+// Starts the child in a process group of its own, so that a CTRL_BREAK_EVENT
+// can be sent to it and its descendants without reaching the supervisor
+pub(crate) fn new_process_group(command: &mut Command) {
+    command.creation_flags(CREATE_NEW_PROCESS_GROUP);
+}
+
+// The Windows counterpart of a SIGTERM. Only reaches children attached to the
+// console of the supervisor, false when it couldn't be sent.
+pub(crate) fn ctrl_break(pid: u32) -> bool {
+    unsafe { GenerateConsoleCtrlEvent(CTRL_BREAK_EVENT, pid) != 0 }
+}
+
+// A job object owning a child and every process it spawns. Closing the last
+// handle to the job kills the whole tree, so dropping it is enough to make
+// sure nothing is left behind.
+pub(crate) struct JobObject(HANDLE);
+
+// Job object handles can be used from any thread
+unsafe impl Send for JobObject {}
+
+impl JobObject {
+    pub(crate) fn assign(child: &Child) -> io::Result<Self> {
+        unsafe {
+            let handle = CreateJobObjectW(ptr::null(), ptr::null());
+            if handle.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            let job = Self(handle);
+
+            let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = mem::zeroed();
+            info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+            if SetInformationJobObject(
+                handle,
+                JobObjectExtendedLimitInformation,
+                &info as *const _ as *const c_void,
+                mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+            ) == 0
+            {
+                return Err(io::Error::last_os_error());
+            }
+
+            if AssignProcessToJobObject(handle, child.as_raw_handle() as HANDLE) == 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            Ok(job)
+        }
+    }
+
+    pub(crate) fn terminate(&self) {
+        unsafe {
+            TerminateJobObject(self.0, 1);
+        }
+    }
+}
+
+impl Drop for JobObject {
+    fn drop(&mut self) {
+        unsafe {
+            CloseHandle(self.0);
+        }
+    }
+}
@@ -0,0 +1,48 @@
+// Signals are raised on the whole test binary, so these tests get a process
+// of their own
+#![cfg(unix)]
+
+use std::{
+    mem, ptr, thread,
+    time::{Duration, Instant},
+};
+
+use supervised_process::{MockBackend, RunOutcome, SupervisedProcess};
+
+fn handler(signal: i32) -> libc::sighandler_t {
+    unsafe {
+        let mut action: libc::sigaction = mem::zeroed();
+        libc::sigaction(signal, ptr::null(), &mut action);
+        action.sa_sigaction
+    }
+}
+
+#[test]
+fn ctrlc_stops_the_child_gracefully() {
+    let backend = MockBackend::new();
+    let mut process = SupervisedProcess::new("my-daemon")
+        .with_backend(backend.clone())
+        .with_check_interval(Duration::from_secs(1))
+        .with_ctrlc_shutdown(true);
+
+    let mock = backend.clone();
+    thread::spawn(move || {
+        while mock.pids().is_empty() {
+            thread::sleep(Duration::from_millis(5));
+        }
+        unsafe { libc::raise(libc::SIGINT) };
+        while mock.signals(1).is_empty() {
+            thread::sleep(Duration::from_millis(5));
+        }
+        mock.exit(1, 0);
+    });
+
+    let started = Instant::now();
+    assert_eq!(process.run().unwrap(), RunOutcome::StoppedBySignal);
+    assert!(started.elapsed() < Duration::from_secs(1));
+    assert_eq!(backend.pids(), vec![1]);
+    assert_eq!(backend.signals(1), vec![libc::SIGTERM]);
+    // Ctrl-C interrupts the tests again
+    assert_eq!(handler(libc::SIGINT), libc::SIG_DFL);
+    assert_eq!(handler(libc::SIGTERM), libc::SIG_DFL);
+}
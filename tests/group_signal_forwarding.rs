@@ -0,0 +1,51 @@
+// Signals are raised on the whole test binary, so these tests get a process
+// of their own
+#![cfg(unix)]
+
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use supervised_process::{ChildInfo, MockBackend, RunOutcome, SupervisedProcess, SupervisorGroup};
+
+#[test]
+fn signals_reach_the_children_during_a_backoff() {
+    let failing = MockBackend::new();
+    let healthy = MockBackend::new();
+    let mut group = SupervisorGroup::new()
+        .with_check_interval(Duration::from_millis(10))
+        .with_backoff_time(Duration::from_secs(10))
+        .with_signal_forwarding([libc::SIGTERM])
+        .add(
+            "failing",
+            SupervisedProcess::new("my-daemon")
+                .with_backend(failing.clone())
+                .add_test("always false", |_: &mut ChildInfo| false),
+        )
+        .add(
+            "healthy",
+            SupervisedProcess::new("my-daemon")
+                .with_backend(healthy.clone())
+                .add_test("always true", |_: &mut ChildInfo| true),
+        );
+
+    let mock = healthy.clone();
+    thread::spawn(move || {
+        // Long enough for the failing child to be backing off
+        while failing.pids().is_empty() || failing.is_running(1) {
+            thread::sleep(Duration::from_millis(5));
+        }
+        thread::sleep(Duration::from_millis(50));
+        unsafe { libc::raise(libc::SIGTERM) };
+        while mock.signals(1).is_empty() {
+            thread::sleep(Duration::from_millis(5));
+        }
+        mock.exit(1, 0);
+    });
+
+    let started = Instant::now();
+    assert_eq!(group.run().unwrap(), RunOutcome::StoppedBySignal);
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert_eq!(healthy.signals(1), vec![libc::SIGTERM]);
+}
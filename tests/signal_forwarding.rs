@@ -0,0 +1,50 @@
+// Signals are raised on the whole test binary, so these tests get a process
+// of their own
+#![cfg(unix)]
+
+use std::{
+    mem, ptr, thread,
+    time::{Duration, Instant},
+};
+
+use supervised_process::{MockBackend, SupervisedProcess};
+
+fn handler(signal: i32) -> libc::sighandler_t {
+    unsafe {
+        let mut action: libc::sigaction = mem::zeroed();
+        libc::sigaction(signal, ptr::null(), &mut action);
+        action.sa_sigaction
+    }
+}
+
+#[test]
+fn it_forwards_signals_to_the_child() {
+    let backend = MockBackend::new();
+    let mut process = SupervisedProcess::new("my-daemon")
+        .with_backend(backend.clone())
+        .with_check_interval(Duration::from_secs(1))
+        .with_signal_forwarding([libc::SIGUSR2, libc::SIGTERM]);
+
+    let mock = backend.clone();
+    thread::spawn(move || {
+        let wait_for = |done: &dyn Fn() -> bool| {
+            while !done() {
+                thread::sleep(Duration::from_millis(5));
+            }
+        };
+        wait_for(&|| !mock.pids().is_empty());
+        unsafe { libc::raise(libc::SIGUSR2) };
+        wait_for(&|| mock.signals(1) == [libc::SIGUSR2]);
+        unsafe { libc::raise(libc::SIGTERM) };
+        wait_for(&|| mock.signals(1).len() == 2);
+        mock.exit(1, 0);
+    });
+
+    let started = Instant::now();
+    assert!(process.run().is_ok());
+    assert!(started.elapsed() < Duration::from_secs(1));
+    assert_eq!(backend.pids(), vec![1]);
+    assert_eq!(backend.signals(1), vec![libc::SIGUSR2, libc::SIGTERM]);
+    assert_eq!(handler(libc::SIGUSR2), libc::SIG_DFL);
+    assert_eq!(handler(libc::SIGTERM), libc::SIG_DFL);
+}